@@ -1,9 +1,9 @@
 import { Box, Typography } from "@mui/joy";
 import { WordCloudController } from "chartjs-chart-wordcloud";
 import { useAtomValue } from "jotai";
-import { Chart, Pie } from "react-chartjs-2";
+import { Bar, Chart, Pie } from "react-chartjs-2";
 
-import { textModeAtom } from "../data/atoms";
+import { matchFieldsAtom, pitFieldsAtom, textModeAtom } from "../data/atoms";
 import { useCharts } from "../data/hooks";
 import { TeamInfoEntry } from "../generated/TeamInfoEntry";
 
@@ -37,6 +37,9 @@ const excludeWords = [
 export function DataValue(props: DataValueProps) {
 	const chartsReady = useCharts();
 	const textMode = useAtomValue(textModeAtom);
+	const matchFields = useAtomValue(matchFieldsAtom);
+	const pitFields = useAtomValue(pitFieldsAtom);
+	const fieldImage = matchFields?.field_image ?? pitFields?.field_image;
 
 	switch (props.value.graphic?.type) {
 		case undefined:
@@ -239,6 +242,138 @@ export function DataValue(props: DataValueProps) {
 				);
 			}
 		}
+		case "event_log": {
+			return (
+				<>
+					<Typography
+						level="h2"
+						style={{
+							color: `rgb(${props.value.colour})`,
+						}}
+					>
+						{props.value.text}
+					</Typography>
+					{!props.listView && (
+						<>
+							{props.value.graphic.phase_counts.map((phase) => (
+								<Typography>
+									{phase.name}: {phase.average.toFixed(1)} per match
+								</Typography>
+							))}
+							{props.value.graphic.best_cycle_seconds !== null && (
+								<Typography>
+									Best cycle:{" "}
+									{props.value.graphic.best_cycle_seconds.toFixed(1)}s
+								</Typography>
+							)}
+						</>
+					)}
+				</>
+			);
+		}
+		case "heatmap": {
+			if (textMode) {
+				return <Typography level="h2">{props.value.text}</Typography>;
+			}
+			const heatmap = props.value.graphic;
+			const mostCommon = Math.max(...heatmap.cells);
+			return (
+				<Box
+					sx={{
+						position: "relative",
+						width: props.listView ? "150px" : "100%",
+						maxWidth: "40rem",
+						margin: "auto",
+					}}
+				>
+					{fieldImage ? (
+						<img src={fieldImage} width="100%" style={{ display: "block" }} />
+					) : (
+						<Box sx={{ aspectRatio: "2", backgroundColor: "#7a7a7a" }} />
+					)}
+					<Box
+						sx={{
+							position: "absolute",
+							inset: 0,
+							display: "grid",
+							gridTemplateColumns: `repeat(${heatmap.columns}, 1fr)`,
+							gridTemplateRows: `repeat(${heatmap.rows}, 1fr)`,
+						}}
+					>
+						{heatmap.cells.map((cell) => (
+							<Box
+								sx={{
+									backgroundColor: `rgba(231, 76, 60, ${
+										mostCommon > 0 ? (cell / mostCommon) * 0.8 : 0
+									})`,
+								}}
+							/>
+						))}
+					</Box>
+					{!props.listView &&
+						heatmap.clusters.map((cluster) => (
+							<Box
+								title={`${Math.round(cluster.fraction * 100)}%`}
+								sx={{
+									position: "absolute",
+									left: `${cluster.center.x * 100}%`,
+									top: `${cluster.center.y * 100}%`,
+									width: "0.75rem",
+									height: "0.75rem",
+									borderRadius: "50%",
+									transform: "translate(-50%, -50%)",
+									backgroundColor: "#f1c40f",
+									border: "1px solid black",
+								}}
+							/>
+						))}
+				</Box>
+			);
+		}
+		case "option_frequency": {
+			return textMode ? (
+				<Typography
+					level="h2"
+					style={{
+						color: `rgb(${props.value.colour})`,
+					}}
+				>
+					{props.value.text}
+				</Typography>
+			) : (
+				<Box
+					sx={{
+						width: props.listView ? "150px" : "100%",
+						maxWidth: "30rem",
+						margin: "auto",
+					}}
+				>
+					{chartsReady && (
+						<Bar
+							data={{
+								labels: props.value.graphic.options.map(
+									(op) => op.label,
+								),
+								datasets: [
+									{
+										label: "% of entries",
+										data: props.value.graphic.options.map(
+											(op) => op.value * 100,
+										),
+										backgroundColor: props.colorScheme,
+									},
+								],
+							}}
+							options={{
+								indexAxis: "y",
+								scales: { x: { min: 0, max: 100 } },
+								plugins: { legend: { display: false } },
+							}}
+						/>
+					)}
+				</Box>
+			);
+		}
 		case "numeric": {
 			return (
 				<Typography
@@ -3,6 +3,7 @@ import {
 	Button,
 	CircularProgress,
 	Input,
+	Slider,
 	Stack,
 	Textarea,
 	ToggleButtonGroup,
@@ -12,13 +13,20 @@ import { useEffect, useState } from "preact/hooks";
 import { JSXInternal } from "preact/src/jsx";
 
 import { AbilityMetric } from "../generated/AbilityMetric";
+import { AttemptCounterMetric } from "../generated/AttemptCounterMetric";
 import { BoolMetric } from "../generated/BoolMetric";
 import { CounterMetric } from "../generated/CounterMetric";
+import { EventLogMetric } from "../generated/EventLogMetric";
+import { FieldPoint } from "../generated/FieldPoint";
+import { FieldPositionMetric } from "../generated/FieldPositionMetric";
 import { ImageMetric } from "../generated/ImageMetric";
+import { LoggedEvent } from "../generated/LoggedEvent";
 import { MatchAbilityValue } from "../generated/MatchAbilityValue";
 import { MatchEntry } from "../generated/MatchEntry";
 import { MatchEntryPage } from "../generated/MatchEntryPage";
 import { MatchEntryValue } from "../generated/MatchEntryValue";
+import { MultiSelectMetric } from "../generated/MultiSelectMetric";
+import { RatingMetric } from "../generated/RatingMetric";
 import { TextEntryMetric } from "../generated/TextEntryMetric";
 import { TimerMetric } from "../generated/TimerMetric";
 import { getImage, saveImage } from "../images";
@@ -31,6 +39,7 @@ interface MatchPageProps {
 	entries: Record<string, MatchEntry>;
 	allEntries: Record<string, MatchEntryValue>;
 	setEntry: (id: string, value: OptionalEntryValue) => void;
+	fieldImage: string | null;
 }
 
 /**
@@ -55,6 +64,7 @@ export function MatchPage(props: MatchPageProps) {
 						props.setEntry(entryName, value);
 					}}
 					value={props.allEntries[entryName]}
+					fieldImage={props.fieldImage}
 				></MatchDetail>
 			))}
 		</>
@@ -66,6 +76,7 @@ interface MatchDetailProps {
 	entry: MatchEntry;
 	value: MatchEntryValue | undefined;
 	setValue: (value: OptionalEntryValue) => void;
+	fieldImage: string | null;
 }
 
 /**
@@ -114,6 +125,42 @@ function MatchDetail(props: MatchDetailProps) {
 					value={props.value}
 					setValue={props.setValue}
 				/>
+			) : props.entry.entry.type === "attempt_counter" ? (
+				<AttemptCounterEntry
+					scout={props.scout}
+					entry={props.entry.entry}
+					value={props.value}
+					setValue={props.setValue}
+				/>
+			) : props.entry.entry.type === "event_log" ? (
+				<EventLogEntry
+					scout={props.scout}
+					entry={props.entry.entry}
+					value={props.value}
+					setValue={props.setValue}
+				/>
+			) : props.entry.entry.type === "field_position" ? (
+				<FieldPositionEntry
+					scout={props.scout}
+					entry={props.entry.entry}
+					value={props.value}
+					setValue={props.setValue}
+					fieldImage={props.fieldImage}
+				/>
+			) : props.entry.entry.type === "rating" ? (
+				<RatingEntry
+					scout={props.scout}
+					entry={props.entry.entry}
+					value={props.value}
+					setValue={props.setValue}
+				/>
+			) : props.entry.entry.type === "multi_select" ? (
+				<MultiSelectEntry
+					scout={props.scout}
+					entry={props.entry.entry}
+					value={props.value}
+					setValue={props.setValue}
+				/>
 			) : props.entry.entry.type === "text_entry" ? (
 				<TextFieldEntry
 					scout={props.scout}
@@ -377,6 +424,334 @@ function CounterEntry(props: CounterEntryProps) {
 	);
 }
 
+interface AttemptCounterEntryProps {
+	scout: string;
+	value: MatchEntryValue | undefined;
+	setValue: (value: OptionalEntryValue) => void;
+	entry: AttemptCounterMetric;
+}
+
+/**
+ *	An entry for attempts and how many of them succeeded
+ */
+function AttemptCounterEntry(props: AttemptCounterEntryProps) {
+	const attempts =
+		props.value?.type === "attempt_counter" ? props.value.attempts : 0;
+	const successes =
+		props.value?.type === "attempt_counter" ? props.value.successes : 0;
+	const setCounts = (newAttempts: number, newSuccesses: number) => {
+		props.setValue({
+			type: "attempt_counter",
+			attempts: newAttempts,
+			successes: newSuccesses,
+			timestamp_ms: Date.now(),
+			scout: props.scout,
+		});
+	};
+	return (
+		<Stack spacing={1}>
+			<Typography level="h4">
+				{successes} of {attempts} succeeded
+			</Typography>
+			<Stack direction="row" spacing={1} height={{ xs: "6rem", md: "4rem" }}>
+				<Button
+					color="success"
+					sx={{ flex: 1, fontSize: "1.5rem" }}
+					onClick={() => setCounts(attempts + 1, successes + 1)}
+				>
+					Succeeded
+				</Button>
+				<Button
+					color="danger"
+					sx={{ flex: 1, fontSize: "1.5rem" }}
+					onClick={() => setCounts(attempts + 1, successes)}
+				>
+					Missed
+				</Button>
+			</Stack>
+			<Stack direction="row" spacing={1}>
+				<Button
+					variant="outlined"
+					color="success"
+					disabled={successes === 0}
+					onClick={() => setCounts(attempts - 1, successes - 1)}
+				>
+					Undo a success
+				</Button>
+				<Button
+					variant="outlined"
+					color="danger"
+					disabled={attempts === successes}
+					onClick={() => setCounts(attempts - 1, successes)}
+				>
+					Undo a miss
+				</Button>
+			</Stack>
+		</Stack>
+	);
+}
+
+/**
+ * Format seconds since the start of the match like a match clock, e.g. 1:05
+ */
+function formatMatchTime(seconds: number): string {
+	return `${Math.floor(seconds / 60)}:${Math.floor(seconds % 60)
+		.toString()
+		.padStart(2, "0")}`;
+}
+
+interface EventLogEntryProps {
+	scout: string;
+	value: MatchEntryValue | undefined;
+	setValue: (value: OptionalEntryValue) => void;
+	entry: EventLogMetric;
+}
+
+/**
+ *	An entry for actions and when they happened, timed from when the scout starts the match clock
+ */
+function EventLogEntry(props: EventLogEntryProps) {
+	const [startTime, setStartTime] = useState<number>();
+	const [currentTime, setCurrentTime] = useState<number>(Date.now());
+	const [location, setLocation] = useState<string | null>(null);
+
+	useEffect(() => {
+		if (startTime !== undefined) {
+			const id = setTimeout(() => setCurrentTime(Date.now()), 500);
+
+			return () => clearTimeout(id);
+		}
+	}, [currentTime, startTime]);
+
+	const events = props.value?.type === "event_log" ? props.value.events : [];
+	const setEvents = (newEvents: Array<LoggedEvent>) => {
+		props.setValue({
+			type: "event_log",
+			events: newEvents,
+			timestamp_ms: Date.now(),
+			scout: props.scout,
+		});
+	};
+	return (
+		<Stack spacing={1}>
+			{startTime === undefined ? (
+				<Button
+					onClick={() => {
+						setStartTime(Date.now());
+						setCurrentTime(Date.now());
+					}}
+				>
+					Start the match clock
+				</Button>
+			) : (
+				<Typography level="h4">
+					{formatMatchTime((currentTime - startTime) / 1000)}
+				</Typography>
+			)}
+			{props.entry.locations.length > 0 && (
+				<ToggleButtonGroup
+					value={location}
+					onChange={(_, newValue) => setLocation(newValue ?? null)}
+				>
+					{props.entry.locations.map((option) => (
+						<Button value={option}>{option}</Button>
+					))}
+				</ToggleButtonGroup>
+			)}
+			<Stack direction="row" flexWrap="wrap" gap={1}>
+				{props.entry.actions.map((action) => (
+					<Button
+						disabled={startTime === undefined}
+						sx={{ minHeight: { xs: "4rem", md: "auto" } }}
+						onClick={() => {
+							if (startTime !== undefined) {
+								setEvents([
+									...events,
+									{
+										action,
+										time_seconds: (Date.now() - startTime) / 1000,
+										location,
+									},
+								]);
+							}
+						}}
+					>
+						<p className="button-text">{action}</p>
+					</Button>
+				))}
+			</Stack>
+			{events.map((event, idx) => (
+				<Stack direction="row" spacing={1} alignItems="center">
+					<Button
+						size="sm"
+						color="danger"
+						onClick={() =>
+							setEvents(events.filter((_, other) => other !== idx))
+						}
+					>
+						⊗
+					</Button>
+					<Typography>
+						{formatMatchTime(event.time_seconds)} {event.action}
+						{event.location !== null ? ` from ${event.location}` : ""}
+					</Typography>
+				</Stack>
+			))}
+		</Stack>
+	);
+}
+
+interface FieldPositionEntryProps {
+	scout: string;
+	value: MatchEntryValue | undefined;
+	setValue: (value: OptionalEntryValue) => void;
+	entry: FieldPositionMetric;
+	fieldImage: string | null;
+}
+
+/**
+ *	An entry for positions, which are tapped on the field image and removed by tapping them again
+ */
+function FieldPositionEntry(props: FieldPositionEntryProps) {
+	const positions =
+		props.value?.type === "field_position" ? props.value.positions : [];
+	const setPositions = (newPositions: Array<FieldPoint>) => {
+		props.setValue({
+			type: "field_position",
+			positions: newPositions,
+			timestamp_ms: Date.now(),
+			scout: props.scout,
+		});
+	};
+	if (!props.fieldImage) {
+		return (
+			<Typography>There is no field image set up for this game</Typography>
+		);
+	}
+	return (
+		<Box sx={{ position: "relative", maxWidth: "40rem" }}>
+			<img
+				src={props.fieldImage}
+				width="100%"
+				style={{ display: "block" }}
+				onClick={(ev) => {
+					const bounds = (
+						ev.target as HTMLImageElement
+					).getBoundingClientRect();
+					const point = {
+						x: (ev.clientX - bounds.left) / bounds.width,
+						y: (ev.clientY - bounds.top) / bounds.height,
+					};
+					setPositions(props.entry.multiple ? [...positions, point] : [point]);
+				}}
+			></img>
+			{positions.map((point, idx) => (
+				<Box
+					onClick={() =>
+						setPositions(positions.filter((_, other) => other !== idx))
+					}
+					sx={{
+						position: "absolute",
+						left: `${point.x * 100}%`,
+						top: `${point.y * 100}%`,
+						width: "1.5rem",
+						height: "1.5rem",
+						borderRadius: "50%",
+						transform: "translate(-50%, -50%)",
+						backgroundColor: "#f1c40f",
+						border: "2px solid black",
+						cursor: "pointer",
+					}}
+				/>
+			))}
+		</Box>
+	);
+}
+
+interface RatingEntryProps {
+	scout: string;
+	value: MatchEntryValue | undefined;
+	setValue: (value: OptionalEntryValue) => void;
+	entry: RatingMetric;
+}
+
+/**
+ *	An entry for ratings on a scale
+ */
+function RatingEntry(props: RatingEntryProps) {
+	const value = props.value?.type === "rating" ? props.value.value : undefined;
+	return (
+		<Stack direction="row" spacing={3} alignItems="center" paddingX={2}>
+			<Slider
+				min={props.entry.min}
+				max={props.entry.max}
+				step={props.entry.step}
+				marks={props.entry.labels.map((label) => ({
+					value: label.value,
+					label: label.label,
+				}))}
+				color={value === undefined ? "neutral" : "primary"}
+				value={value ?? props.entry.min}
+				onChange={(_, newValue) => {
+					props.setValue({
+						type: "rating",
+						value: newValue as number,
+						timestamp_ms: Date.now(),
+						scout: props.scout,
+					});
+				}}
+			/>
+			<Typography level="h4" sx={{ minWidth: "6rem" }}>
+				{value === undefined
+					? "Not rated"
+					: props.entry.labels.find((label) => label.value === value)
+							?.label ?? value}
+			</Typography>
+		</Stack>
+	);
+}
+
+interface MultiSelectEntryProps {
+	scout: string;
+	value: MatchEntryValue | undefined;
+	setValue: (value: OptionalEntryValue) => void;
+	entry: MultiSelectMetric;
+}
+
+/**
+ *	An entry for picking any number of options
+ */
+function MultiSelectEntry(props: MultiSelectEntryProps) {
+	const selected =
+		props.value?.type === "multi_select" ? props.value.values : [];
+	return (
+		<Stack direction="row" flexWrap="wrap" gap={1}>
+			{props.entry.options.map((option) => {
+				const isSelected = selected.includes(option);
+				return (
+					<Button
+						variant={isSelected ? "solid" : "outlined"}
+						sx={{ minHeight: { xs: "4rem", md: "auto" } }}
+						onClick={() => {
+							props.setValue({
+								type: "multi_select",
+								// Keep the options in the order they're listed
+								values: props.entry.options.filter((other) =>
+									other === option ? !isSelected : selected.includes(other),
+								),
+								timestamp_ms: Date.now(),
+								scout: props.scout,
+							});
+						}}
+					>
+						<p className="button-text">{option}</p>
+					</Button>
+				);
+			})}
+		</Stack>
+	);
+}
+
 interface TextFieldEntryProps {
 	scout: string;
 	value: MatchEntryValue | undefined;
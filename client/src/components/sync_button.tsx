@@ -1,4 +1,13 @@
-import { Button } from "@mui/joy";
+import {
+	Button,
+	DialogTitle,
+	List,
+	ListItem,
+	Modal,
+	ModalClose,
+	ModalDialog,
+	Typography,
+} from "@mui/joy";
 import { useAtom, useSetAtom } from "jotai/react";
 import { useState } from "preact/hooks";
 import { useEffect } from "react";
@@ -27,10 +36,81 @@ import {
 } from "../data/entries";
 import { DriverEntryIdData } from "../generated/DriverEntryIdData";
 import { DriverEntryTimedId } from "../generated/DriverEntryTimedId";
+import { FullEntryData } from "../generated/FullEntryData";
 import { MatchEntryIdData } from "../generated/MatchEntryIdData";
 import { MatchEntryTimedId } from "../generated/MatchEntryTimedId";
 import { PitEntryIdData } from "../generated/PitEntryIdData";
 import { PitEntryTimedId } from "../generated/PitEntryTimedId";
+import { ValidationReport } from "../generated/ValidationReport";
+
+interface SavedEntry {
+	match_id?: string;
+	team_id: string;
+	data: FullEntryData;
+}
+
+/**
+ *	Gets the reports for the entries the server didn't save
+ *	@param response the response from saving entries
+ *	@returns the reports for the rejected entries
+ */
+async function getRejected(
+	response: Response,
+): Promise<Array<ValidationReport>> {
+	const reports = (await response.json()) as Array<ValidationReport>;
+	return reports.filter((report) => !report.saved);
+}
+
+/**
+ *	Gets the time of the newest value in an entry
+ *	@param entry the entry
+ *	@returns the timestamp in milliseconds
+ */
+function getLatestTimestamp(entry: SavedEntry): number {
+	return Object.values(entry.data.entries).reduce(
+		(max_timestamp, value) => Math.max(max_timestamp, value.timestamp_ms ?? 0),
+		0,
+	);
+}
+
+/**
+ *	Gets the time everything has been saved up to. This stays before any
+ *	rejected entry, so they are sent again on the next sync.
+ *	@param saveTime when saving started
+ *	@param entries the entries that were sent
+ *	@param rejected the reports for the entries that weren't saved
+ *	@returns the time to mark as saved
+ */
+function getSavedUntil(
+	saveTime: number,
+	entries: Array<SavedEntry>,
+	rejected: Array<ValidationReport>,
+): number {
+	return entries
+		.filter((entry) =>
+			rejected.some(
+				(report) =>
+					report.team_id === entry.team_id &&
+					report.match_id === (entry.match_id ?? null),
+			),
+		)
+		.reduce(
+			(savedUntil, entry) =>
+				Math.min(savedUntil, getLatestTimestamp(entry) - 1),
+			saveTime,
+		);
+}
+
+/**
+ *	Gets a readable name for the entry a report is about
+ *	@param report the report
+ *	@returns the name of the entry
+ */
+function getReportName(report: ValidationReport): string {
+	return report.match_id === null
+		? `Team ${report.team_id} pit entry`
+		: `Team ${report.team_id} in ${report.match_id}`;
+}
 
 /**
  *	A button that when clicked syncs important data from/to localStorage to/from the server
@@ -40,6 +120,7 @@ export function SyncButton() {
 	const [loadingState, setLoadingState] = useState<"saved" | "saving">(
 		"saved",
 	);
+	const [rejected, setRejected] = useState<Array<ValidationReport>>([]);
 	const [lastMatchSave, setLastMatchSave] = useAtom(lastMatchSaveAtom);
 	const [lastPitSave, setLastPitSave] = useAtom(lastPitSaveAtom);
 	const [lastDriverSave, setLastDriverSave] = useAtom(lastDriverSaveAtom);
@@ -58,6 +139,7 @@ export function SyncButton() {
 			return;
 		}
 		setLoadingState("saving");
+		const newRejected: Array<ValidationReport> = [];
 
 		try {
 			await fetch("/api/driver_entry/fields")
@@ -101,9 +183,13 @@ export function SyncButton() {
 					method: "PUT",
 					headers: { "Content-Type": "application/json" },
 					body: JSON.stringify(matchesToSave),
-				}).then((response) => {
+				}).then(async (response) => {
 					if (response.ok) {
-						setLastMatchSave(matchSaveTime);
+						const rejectedEntries = await getRejected(response);
+						newRejected.push(...rejectedEntries);
+						setLastMatchSave(
+							getSavedUntil(matchSaveTime, matchesToSave, rejectedEntries),
+						);
 					}
 				});
 			} else {
@@ -130,9 +216,13 @@ export function SyncButton() {
 					method: "PUT",
 					headers: { "Content-Type": "application/json" },
 					body: JSON.stringify(pitEntriesToSave),
-				}).then((response) => {
+				}).then(async (response) => {
 					if (response.ok) {
-						setLastPitSave(pitSaveTime);
+						const rejectedEntries = await getRejected(response);
+						newRejected.push(...rejectedEntries);
+						setLastPitSave(
+							getSavedUntil(pitSaveTime, pitEntriesToSave, rejectedEntries),
+						);
 					}
 				});
 			} else {
@@ -155,13 +245,21 @@ export function SyncButton() {
 					getDriverKey(driverEntry.match_id, driverEntry.team_id),
 				);
 
-				fetch("/api/driver_entry/data/all", {
+				await fetch("/api/driver_entry/data/all", {
 					method: "PUT",
 					headers: { "Content-Type": "application/json" },
 					body: JSON.stringify(driverEntriesToSave),
-				}).then((response) => {
+				}).then(async (response) => {
 					if (response.ok) {
-						setLastDriverSave(driverSaveTime);
+						const rejectedEntries = await getRejected(response);
+						newRejected.push(...rejectedEntries);
+						setLastDriverSave(
+							getSavedUntil(
+								driverSaveTime,
+								driverEntriesToSave,
+								rejectedEntries,
+							),
+						);
 					}
 				});
 			} else {
@@ -254,6 +352,7 @@ export function SyncButton() {
 		} catch (error) {
 			console.error("Failed to save data.", error);
 		} finally {
+			setRejected(newRejected);
 			setLoadingState("saved");
 		}
 	}
@@ -265,8 +364,35 @@ export function SyncButton() {
 	}, [loadingState, matchList]);
 
 	return (
-		<Button loading={loadingState === "saving"} onClick={doSync}>
-			Save Data
-		</Button>
+		<>
+			<Button loading={loadingState === "saving"} onClick={doSync}>
+				Save Data
+			</Button>
+			<Modal open={rejected.length > 0} onClose={() => setRejected([])}>
+				<ModalDialog>
+					<ModalClose />
+					<DialogTitle>Some entries were not saved</DialogTitle>
+					<Typography>
+						Fix these entries, they will be saved on the next sync.
+					</Typography>
+					<List>
+						{rejected.map((report, index) => (
+							<ListItem key={index}>
+								<div>
+									<Typography level="title-sm">
+										{getReportName(report)}
+									</Typography>
+									{report.issues.map((issue) => (
+										<Typography level="body-sm">
+											{issue.field}: {issue.problem}
+										</Typography>
+									))}
+								</div>
+							</ListItem>
+						))}
+					</List>
+				</ModalDialog>
+			</Modal>
+		</>
 	);
 }
@@ -2,6 +2,7 @@ import { useEffect, useState } from "preact/hooks";
 
 import { DriverEntryIdData } from "../generated/DriverEntryIdData";
 import { FullEntryData } from "../generated/FullEntryData";
+import { ImageUploadStatus } from "../generated/ImageUploadStatus";
 import { MatchEntryIdData } from "../generated/MatchEntryIdData";
 import { MatchEntryValue } from "../generated/MatchEntryValue";
 import { PitEntryIdData } from "../generated/PitEntryIdData";
@@ -94,7 +95,7 @@ export async function saveImageData<T extends AnyEntryId>(
 	getKey: (entry: T) => string,
 ): Promise<boolean> {
 	for (const entry of entries) {
-		for (const value of Object.values(entry.data.entries)) {
+		for (const [field, value] of Object.entries(entry.data.entries)) {
 			if (value.type === "image") {
 				for (const image of value.images) {
 					if ((image as unknown as { local: true | undefined }).local) {
@@ -102,13 +103,23 @@ export async function saveImageData<T extends AnyEntryId>(
 						try {
 							const imageData = await getImage(image.image_id);
 
-							const response = await fetch("/api/images", {
-								method: "PUT",
-								headers: { "Content-Type": "application/json" },
-								body: JSON.stringify([imageData]),
-							});
-
-							if (response.ok) {
+							const ok = image.image_mime.startsWith("video/")
+								? imageData !== undefined &&
+									(await uploadVideo(
+										image.image_id,
+										image.image_mime,
+										field,
+										imageData,
+									))
+								: (
+										await fetch("/api/images", {
+											method: "PUT",
+											headers: { "Content-Type": "application/json" },
+											body: JSON.stringify([imageData]),
+										})
+									).ok;
+
+							if (ok) {
 								(
 									image as unknown as { local: boolean | undefined }
 								).local = false;
@@ -158,6 +169,53 @@ export async function saveImageData<T extends AnyEntryId>(
 	return true;
 }
 
+/** How much of a video is sent in each request */
+const VIDEO_CHUNK_BYTES = 1024 * 1024;
+
+/**
+ * Sends a video in chunks, continuing from wherever an earlier attempt got to.
+ */
+async function uploadVideo(
+	imageId: string,
+	imageMime: string,
+	field: string,
+	data: ArrayBuffer,
+): Promise<boolean> {
+	const statusResponse = await fetch(`/api/images/uploads/${imageId}`);
+	if (!statusResponse.ok) {
+		return false;
+	}
+	let received = ((await statusResponse.json()) as ImageUploadStatus).received;
+	while (received < data.byteLength) {
+		const response = await fetch(
+			`/api/images/uploads/${imageId}?offset=${received}`,
+			{
+				method: "PUT",
+				headers: { "Content-Type": "application/octet-stream" },
+				body: data.slice(received, received + VIDEO_CHUNK_BYTES),
+			},
+		);
+		if (!response.ok) {
+			return false;
+		}
+		received = ((await response.json()) as ImageUploadStatus).received;
+	}
+
+	const hash = new Uint8Array(await crypto.subtle.digest("SHA-256", data));
+	const response = await fetch(`/api/images/uploads/${imageId}/complete`, {
+		method: "POST",
+		headers: { "Content-Type": "application/json" },
+		body: JSON.stringify({
+			image_mime: imageMime,
+			field,
+			sha256: Array.from(hash, (byte) =>
+				byte.toString(16).padStart(2, "0"),
+			).join(""),
+		}),
+	});
+	return response.ok;
+}
+
 /**
  * Get all match entires stored in local storage.
  * Note that these will be missing the image data.
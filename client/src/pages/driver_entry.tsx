@@ -71,6 +71,7 @@ export function DriverEntry() {
 							entries={fields.entries}
 							setEntry={setEntry}
 							allEntries={dataEntries}
+							fieldImage={fields.field_image}
 						></MatchPage>
 					))
 				) : (
@@ -66,6 +66,7 @@ export function MatchEntry() {
 						entries={fields.entries}
 						setEntry={setEntry}
 						allEntries={dataEntries}
+						fieldImage={fields.field_image}
 					></MatchPage>
 				))}
 		</Box>
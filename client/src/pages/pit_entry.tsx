@@ -79,6 +79,7 @@ export function PitEntry(props: PitEntryProps) {
 							entries={fields.entries}
 							setEntry={setEntry}
 							allEntries={dataEntries}
+							fieldImage={fields.field_image}
 						></MatchPage>
 					))}
 			</Box>
@@ -1,4 +1,6 @@
-use std::collections::HashMap;
+pub mod report;
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 
 use futures_util::future;
 use log::{info, warn};
@@ -6,8 +8,16 @@ use poem_openapi::{Enum, Object, Union};
 use serde::{Deserialize, Serialize};
 use ts_rs::TS;
 
-use crate::api::data::{ImageEntryItem, MatchBoolEntry};
-use crate::config::FilteredMetric;
+use crate::api::data::{
+	FieldPoint, ImageEntryItem, MatchAbilityEntry, MatchAbilityValue, MatchBoolEntry,
+};
+use crate::config::formula::Formula;
+use crate::config::{
+	CollectedMetric, CollectedMetricType, EventLogMetric, FilteredMetric, FormulaMetric,
+	MultiSelectMetric, NumericStat,
+};
+use crate::data_validation::{apply_implications, remove_uncounted};
+use crate::pick_list::{PickList, PickListCategory};
 use crate::tba::TeamInfo;
 use crate::{
 	api::data::{DriverEntryIdData, FullEntryData, MatchEntryIdData, MatchEntryValue},
@@ -53,6 +63,9 @@ pub enum TeamInfoGraphic {
 	PieChart(PieChartEntry),
 	MultiText(MultiTextEntry),
 	Images(ImagesEntry),
+	EventLog(EventLogSummary),
+	Heatmap(HeatmapEntry),
+	OptionFrequency(OptionFrequencyEntry),
 }
 
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize, Enum, TS)]
@@ -83,10 +96,25 @@ pub struct TeamInfoNumericEntry {
 	pub number: f32,
 	pub collected_std_dev: Option<f32>,
 	pub collected_min_max: Option<[f32; 2]>,
+	pub collected_stats: Option<NumericStats>,
 	pub compare_other_numbers: Option<MinMaxAvg>,
 	pub is_time: bool,
 }
 
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, Object, TS)]
+#[ts(export, export_to = "../../client/src/generated/")]
+pub struct NumericStats {
+	pub median: f32,
+	pub percentile_25: f32,
+	pub percentile_75: f32,
+	/// The average over only the most recent matches
+	pub recent_average: f32,
+	/// How much the value changes per match, on average
+	pub trend: f32,
+	/// From 0 to 1, how close every match is to the average
+	pub consistency: f32,
+}
+
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize, Object, TS)]
 #[ts(export, export_to = "../../client/src/generated/")]
 pub struct MinMaxAvg {
@@ -100,6 +128,8 @@ pub struct MinMaxAvg {
 #[ts(export, export_to = "../../client/src/generated/")]
 pub struct PieChartEntry {
 	options: Vec<PieChartOption>,
+	/// The average number of points per match, for metrics with point values
+	expected_points: Option<f32>,
 }
 
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize, Object, TS)]
@@ -109,6 +139,53 @@ pub struct PieChartOption {
 	value: f32,
 }
 
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, Object, TS)]
+#[ts(export, export_to = "../../client/src/generated/")]
+pub struct EventLogSummary {
+	/// The average number of actions per match in each phase of the match
+	phase_counts: Vec<PhaseCount>,
+	/// The average number of scoring actions per match
+	average_scores: f32,
+	/// The average time between one score and the next, in seconds
+	average_cycle_seconds: Option<f32>,
+	/// The shortest time between one score and the next, in seconds
+	best_cycle_seconds: Option<f32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, Object, TS)]
+#[ts(export, export_to = "../../client/src/generated/")]
+pub struct PhaseCount {
+	name: String,
+	average: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, Object, TS)]
+#[ts(export, export_to = "../../client/src/generated/")]
+pub struct HeatmapEntry {
+	columns: usize,
+	rows: usize,
+	/// The fraction of positions in each cell of the field image, row by row from the top left
+	cells: Vec<f32>,
+	/// Groups of nearby positions, most common first
+	clusters: Vec<PositionCluster>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, Object, TS)]
+#[ts(export, export_to = "../../client/src/generated/")]
+pub struct PositionCluster {
+	/// The average position in the group
+	center: FieldPoint,
+	/// The fraction of all positions that are in this group
+	fraction: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, Object, TS)]
+#[ts(export, export_to = "../../client/src/generated/")]
+pub struct OptionFrequencyEntry {
+	/// For each option, the fraction of entries it was selected in
+	options: Vec<PieChartOption>,
+}
+
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize, Object, TS)]
 #[ts(export, export_to = "../../client/src/generated/")]
 pub struct MultiTextEntry {
@@ -129,12 +206,33 @@ pub struct SingleTeamInfo {
 	team_name: String,
 	team_icon_uri: Option<String>,
 	data: Vec<InfoEntryWithSource>,
+	/// The value in every match for each numeric metric, to show how the team changed over time
+	match_series: Vec<MetricSeries>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, Object, TS)]
+#[ts(export, export_to = "../../client/src/generated/")]
+pub struct MetricSeries {
+	name: NameAndSource,
+	/// In the order the matches were played
+	points: Vec<MetricSeriesPoint>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, Object, TS)]
+#[ts(export, export_to = "../../client/src/generated/")]
+pub struct MetricSeriesPoint {
+	match_id: String,
+	/// Start time in milliseconds since the unix epoch, if we know when the match was
+	#[ts(type = "number | null")]
+	start_time: Option<u64>,
+	value: f32,
+	text: String,
 }
 
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize, Object, TS)]
 #[ts(export, export_to = "../../client/src/generated/")]
 pub struct NameAndSource {
-	name: String,
+	pub name: String,
 	page: String,
 	source: DataSource,
 }
@@ -165,6 +263,17 @@ pub struct MatchAnalysisScorePart {
 	score: f32,
 }
 
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, Object, TS)]
+#[ts(export, export_to = "../../client/src/generated/")]
+pub struct PickRecommendation {
+	team_number: u32,
+	team_name: String,
+	team_icon_uri: Option<String>,
+	category: Option<PickListCategory>,
+	score: f32,
+	score_parts: Vec<MatchAnalysisScorePart>,
+}
+
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize, Object, TS)]
 #[ts(export, export_to = "../../client/src/generated/")]
 pub struct TeamInfoList {
@@ -177,31 +286,453 @@ pub const BASE_PREFIX: &str = "base-";
 pub const SB_PREFIX: &str = "statbotics-";
 pub const TBA_PREFIX: &str = "tba-";
 
-fn get_pie_chart(
+/// The value of a single data point when used in a formula
+fn formula_value(value: &MatchEntryValue) -> f32 {
+	match value {
+		MatchEntryValue::Counter(c) => c.count as f32,
+		MatchEntryValue::Timer(t) => t.time_seconds,
+		MatchEntryValue::AttemptCounter(a) => a.successes as f32,
+		MatchEntryValue::EventLog(e) => e.events.len() as f32,
+		MatchEntryValue::FieldPosition(f) => f.positions.len() as f32,
+		MatchEntryValue::Rating(r) => r.value,
+		MatchEntryValue::MultiSelect(m) => m.values.len() as f32,
+		MatchEntryValue::Bool(MatchBoolEntry { value: true, .. })
+		| MatchEntryValue::Ability(MatchAbilityEntry {
+			value: MatchAbilityValue::Succeeded,
+			..
+		}) => 1.0,
+		_ => 0.0,
+	}
+}
+
+/// Calculates a formula for every match a team played, using all of the data we have for each match.
+/// The values are in match order, along with the id of the match.
+fn formula_match_values<'a>(
+	config: &GameConfigs,
+	formula: &Formula,
+	formula_metric: &FormulaMetric,
+	match_entries: &[&'a MatchEntryIdData],
+) -> Vec<(&'a str, f32)> {
+	let mut matches: BTreeMap<(u32, &str), HashMap<&str, &MatchEntryValue>> = BTreeMap::new();
+	for match_entry in match_entries {
+		matches
+			.entry((
+				match_order(&match_entry.match_id),
+				match_entry.match_id.as_str(),
+			))
+			.or_default()
+			.extend(
+				match_entry
+					.data
+					.entries
+					.iter()
+					.map(|(k, v)| (k.as_str(), v)),
+			);
+	}
+
+	matches
+		.into_iter()
+		.map(|((_, match_id), entries)| {
+			let value = formula.evaluate(
+				&|metric| {
+					entries
+						.get(metric)
+						.map(|v| {
+							get_entry_type(config, metric)
+								.and_then(|entry_type| point_value(entry_type, v))
+								.unwrap_or_else(|| formula_value(v))
+						})
+						.unwrap_or_default()
+				},
+				&|table, metric| {
+					entries
+						.get(metric)
+						.and_then(|v| v.option_label())
+						.and_then(|label| {
+							formula_metric
+								.tables
+								.get(table)
+								.and_then(|table| table.get(&label))
+						})
+						.copied()
+						.unwrap_or_default()
+				},
+			);
+			(match_id, value)
+		})
+		.collect()
+}
+
+fn formula_entry(
+	config: &GameConfigs,
+	formula: &Formula,
+	formula_metric: &FormulaMetric,
+	match_entries: &[&MatchEntryIdData],
+) -> TeamInfoEntry {
+	let values = formula_match_values(config, formula, formula_metric, match_entries);
+
+	if values.is_empty() {
+		return TeamInfoEntry {
+			text: String::new(),
+			pit_value: None,
+			sort_value: -420.0,
+			colour: [255, 255, 255],
+			graphic: None,
+		};
+	}
+
+	number_entry(
+		values.into_iter().map(|(_, value)| value).collect(),
+		formula_metric.is_time,
+		config.game_config.display.recent_matches,
+	)
+}
+
+/// The number of points a data point is worth, if its metric has point values configured
+fn point_value(entry_type: &MatchEntryType, value: &MatchEntryValue) -> Option<f32> {
+	match (entry_type, value) {
+		(MatchEntryType::Enum(enum_metric), MatchEntryValue::Enum(enum_entry))
+			if !enum_metric.points.is_empty() =>
+		{
+			Some(
+				enum_metric
+					.points
+					.get(&enum_entry.value)
+					.copied()
+					.unwrap_or_default(),
+			)
+		}
+		(MatchEntryType::Ability(ability_metric), MatchEntryValue::Ability(ability_entry)) => {
+			ability_metric.points.map(|points| {
+				if ability_entry.value == MatchAbilityValue::Succeeded {
+					points
+				} else {
+					0.0
+				}
+			})
+		}
+		(
+			MatchEntryType::AttemptCounter(attempt_metric),
+			MatchEntryValue::AttemptCounter(attempt_entry),
+		) => attempt_metric
+			.points
+			.map(|points| points * attempt_entry.successes as f32),
+		_ => None,
+	}
+}
+
+/// Uses the expected points per match as the value for a pie chart, if the metric has point values
+fn with_expected_points(
+	mut entry: TeamInfoEntry,
+	entry_type: &MatchEntryType,
+	data_points: &[&MatchEntryValue],
+) -> TeamInfoEntry {
+	let points = data_points
+		.iter()
+		.filter_map(|dp| point_value(entry_type, dp))
+		.collect::<Vec<_>>();
+	if points.is_empty() {
+		return entry;
+	}
+	let expected_points = points.iter().sum::<f32>() / points.len() as f32;
+	entry.text = format!("{expected_points:.1} pts");
+	entry.sort_value = expected_points;
+	if let Some(TeamInfoGraphic::PieChart(pie_chart)) = &mut entry.graphic {
+		pie_chart.expected_points = Some(expected_points);
+	}
+	entry
+}
+
+/// Shows how often attempts succeed along with how many attempts there are per match
+fn attempt_counter_entry(
 	data_points: &[&MatchEntryValue],
 	pit_data_point: Option<&MatchEntryValue>,
-	option_values: &[(&str, Option<f32>)],
-	get_text: fn(&[PieChartOption], f32) -> String,
 ) -> TeamInfoEntry {
-	let mut actual_values = data_points
+	let (attempts, successes) = data_points
 		.iter()
-		.map(|dp| match dp {
-			MatchEntryValue::Enum(ab) => ab.value.to_string(),
-			MatchEntryValue::Ability(ab) => {
-				format!("{:?}", ab.value)
+		.fold((0, 0), |(attempts, successes), dp| {
+			if let MatchEntryValue::AttemptCounter(ac) = dp {
+				(attempts + ac.attempts, successes + ac.successes)
+			} else {
+				panic!("Invalid data type of {dp:?} for attempt counter match entry");
 			}
-			MatchEntryValue::Bool(bl) => {
-				if bl.value {
-					"Yes".to_string()
-				} else {
-					"No".to_string()
+		});
+	if data_points.is_empty() {
+		return TeamInfoEntry {
+			text: String::new(),
+			pit_value: match pit_data_point {
+				Some(MatchEntryValue::AttemptCounter(ac)) => {
+					Some(format!("{}/{}", ac.successes, ac.attempts))
+				}
+				_ => None,
+			},
+			sort_value: -420.0,
+			colour: [255, 255, 255],
+			graphic: None,
+		};
+	}
+
+	let match_count = data_points.len() as f32;
+	let average_attempts = attempts as f32 / match_count;
+	let average_successes = successes as f32 / match_count;
+	let success_rate = if attempts == 0 {
+		0.0
+	} else {
+		successes as f32 / attempts as f32
+	};
+	TeamInfoEntry {
+		text: format!("{:.0}% of {average_attempts:.1}", success_rate * 100.0),
+		pit_value: None,
+		// Sort by successes per match, so that a few lucky attempts aren't ranked highest
+		sort_value: average_successes,
+		colour: [255, 255, 255],
+		graphic: Some(TeamInfoGraphic::PieChart(PieChartEntry {
+			options: vec![
+				PieChartOption {
+					label: "Succeeded".to_string(),
+					value: average_successes,
+				},
+				PieChartOption {
+					label: "Missed".to_string(),
+					value: average_attempts - average_successes,
+				},
+			],
+			expected_points: None,
+		})),
+	}
+}
+
+/// Splits up timestamped actions by match phase, and finds how long it takes to cycle between scores
+fn event_log_entry(
+	config: &GameConfigs,
+	event_log_metric: &EventLogMetric,
+	data_points: &[&MatchEntryValue],
+) -> TeamInfoEntry {
+	if data_points.is_empty() {
+		return TeamInfoEntry {
+			text: String::new(),
+			pit_value: None,
+			sort_value: -420.0,
+			colour: [255, 255, 255],
+			graphic: None,
+		};
+	}
+
+	let match_count = data_points.len() as f32;
+	let mut phase_totals = vec![0; config.game_config.phases.len()];
+	let mut score_count = 0;
+	let mut cycles = Vec::new();
+	for dp in data_points {
+		let MatchEntryValue::EventLog(event_log) = dp else {
+			panic!("Invalid data type of {dp:?} for event log match entry");
+		};
+		for event in &event_log.events {
+			for (total, phase) in phase_totals.iter_mut().zip(&config.game_config.phases) {
+				if event.time_seconds >= phase.start_seconds
+					&& event.time_seconds < phase.end_seconds
+				{
+					*total += 1;
 				}
 			}
-			value => {
-				panic!("Invalid MatchEntryValue type found: {:?}", value)
+		}
+		let mut score_times = event_log
+			.events
+			.iter()
+			.filter(|event| event_log_metric.is_scoring(&event.action))
+			.map(|event| event.time_seconds)
+			.collect::<Vec<_>>();
+		score_times.sort_by(|a, b| a.total_cmp(b));
+		score_count += score_times.len();
+		cycles.extend(score_times.windows(2).map(|times| times[1] - times[0]));
+	}
+
+	let average_scores = score_count as f32 / match_count;
+	let average_cycle_seconds =
+		(!cycles.is_empty()).then(|| cycles.iter().sum::<f32>() / cycles.len() as f32);
+	TeamInfoEntry {
+		text: match average_cycle_seconds {
+			Some(cycle) => format!("{average_scores:.1} ({cycle:.1}s cycles)"),
+			None => format!("{average_scores:.1}"),
+		},
+		pit_value: None,
+		sort_value: average_scores,
+		colour: [255, 255, 255],
+		graphic: Some(TeamInfoGraphic::EventLog(EventLogSummary {
+			phase_counts: config
+				.game_config
+				.phases
+				.iter()
+				.zip(phase_totals)
+				.map(|(phase, total)| PhaseCount {
+					name: phase.name.clone(),
+					average: total as f32 / match_count,
+				})
+				.collect(),
+			average_scores,
+			average_cycle_seconds,
+			best_cycle_seconds: cycles.iter().copied().reduce(f32::min),
+		})),
+	}
+}
+
+/// Finds how often each option is selected, using both match and pit data
+fn multi_select_entry(
+	multi_select_metric: &MultiSelectMetric,
+	data_points: &[&MatchEntryValue],
+	pit_data_point: Option<&MatchEntryValue>,
+) -> TeamInfoEntry {
+	let selections = data_points
+		.iter()
+		.copied()
+		.chain(pit_data_point)
+		.map(|dp| {
+			if let MatchEntryValue::MultiSelect(multi_select) = dp {
+				&multi_select.values
+			} else {
+				panic!("Invalid data type of {dp:?} for multi-select match entry");
+			}
+		})
+		.collect::<Vec<_>>();
+	if selections.is_empty() {
+		return TeamInfoEntry {
+			text: String::new(),
+			pit_value: None,
+			sort_value: -420.0,
+			colour: [255, 255, 255],
+			graphic: None,
+		};
+	}
+
+	let entry_count = selections.len() as f32;
+	let options = multi_select_metric
+		.options
+		.iter()
+		.map(|option| PieChartOption {
+			label: option.clone(),
+			value: selections
+				.iter()
+				.filter(|values| values.contains(option))
+				.count() as f32
+				/ entry_count,
+		})
+		.collect::<Vec<_>>();
+	let usual_options = options
+		.iter()
+		.filter(|option| option.value >= 0.5)
+		.map(|option| option.label.as_str())
+		.collect::<Vec<_>>();
+	TeamInfoEntry {
+		text: if usual_options.is_empty() {
+			"None".to_string()
+		} else {
+			usual_options.join(", ")
+		},
+		pit_value: match pit_data_point {
+			Some(MatchEntryValue::MultiSelect(multi_select)) => {
+				Some(multi_select.values.join(", "))
+			}
+			_ => None,
+		},
+		// Teams that can do more things sort higher
+		sort_value: selections.iter().map(|values| values.len()).sum::<usize>() as f32
+			/ entry_count,
+		colour: [255, 255, 255],
+		graphic: Some(TeamInfoGraphic::OptionFrequency(OptionFrequencyEntry {
+			options,
+		})),
+	}
+}
+
+const HEATMAP_COLUMNS: usize = 12;
+const HEATMAP_ROWS: usize = 6;
+/// How close a position has to be to a cluster to be part of it, as a fraction of the field image
+const CLUSTER_RADIUS: f32 = 0.1;
+
+/// Summarizes where on the field a team has been as a heatmap, along with the most common spots
+fn field_position_entry(data_points: &[&MatchEntryValue]) -> TeamInfoEntry {
+	let positions = data_points
+		.iter()
+		.flat_map(|dp| {
+			if let MatchEntryValue::FieldPosition(field_position) = dp {
+				field_position.positions.iter()
+			} else {
+				panic!("Invalid data type of {dp:?} for field position match entry");
 			}
 		})
 		.collect::<Vec<_>>();
+	if positions.is_empty() {
+		return TeamInfoEntry {
+			text: String::new(),
+			pit_value: None,
+			sort_value: -420.0,
+			colour: [255, 255, 255],
+			graphic: None,
+		};
+	}
+
+	let fraction = 1.0 / positions.len() as f32;
+	let mut cells = vec![0.0; HEATMAP_COLUMNS * HEATMAP_ROWS];
+	// Each cluster is the sum of its positions and how many there are
+	let mut clusters: Vec<(f32, f32, usize)> = Vec::new();
+	for position in positions {
+		let column = ((position.x * HEATMAP_COLUMNS as f32) as usize).min(HEATMAP_COLUMNS - 1);
+		let row = ((position.y * HEATMAP_ROWS as f32) as usize).min(HEATMAP_ROWS - 1);
+		cells[row * HEATMAP_COLUMNS + column] += fraction;
+
+		let nearby = clusters.iter_mut().find(|(x, y, count)| {
+			let count = *count as f32;
+			(x / count - position.x).hypot(y / count - position.y) <= CLUSTER_RADIUS
+		});
+		match nearby {
+			Some((x, y, count)) => {
+				*x += position.x;
+				*y += position.y;
+				*count += 1;
+			}
+			None => clusters.push((position.x, position.y, 1)),
+		}
+	}
+	clusters.sort_by_key(|(_, _, count)| std::cmp::Reverse(*count));
+	let clusters = clusters
+		.into_iter()
+		.map(|(x, y, count)| PositionCluster {
+			center: FieldPoint {
+				x: x / count as f32,
+				y: y / count as f32,
+			},
+			fraction: count as f32 * fraction,
+		})
+		.collect::<Vec<_>>();
+
+	let top_fraction = clusters[0].fraction;
+	TeamInfoEntry {
+		text: format!("{:.0}% in one spot", top_fraction * 100.0),
+		pit_value: None,
+		// Sorts by how consistent they are, which is what matters for things like starting positions
+		sort_value: top_fraction,
+		colour: [255, 255, 255],
+		graphic: Some(TeamInfoGraphic::Heatmap(HeatmapEntry {
+			columns: HEATMAP_COLUMNS,
+			rows: HEATMAP_ROWS,
+			cells,
+			clusters,
+		})),
+	}
+}
+
+fn get_pie_chart(
+	data_points: &[&MatchEntryValue],
+	pit_data_point: Option<&MatchEntryValue>,
+	option_values: &[(&str, Option<f32>)],
+	get_text: fn(&[PieChartOption], f32) -> String,
+) -> TeamInfoEntry {
+	let mut actual_values = data_points
+		.iter()
+		.map(|dp| {
+			dp.option_label()
+				.unwrap_or_else(|| panic!("Invalid MatchEntryValue type found: {:?}", dp))
+		})
+		.collect::<Vec<_>>();
 
 	actual_values.sort();
 
@@ -256,24 +787,68 @@ fn get_pie_chart(
 		graphic: if options.iter().all(|option| option.value == 0.0) {
 			None
 		} else {
-			Some(TeamInfoGraphic::PieChart(PieChartEntry { options }))
+			Some(TeamInfoGraphic::PieChart(PieChartEntry {
+				options,
+				expected_points: None,
+			}))
 		},
 	}
 }
 
-fn number_entry(numbers: Vec<f32>, is_time: bool) -> TeamInfoEntry {
-	let value = numbers.iter().sum::<f32>() / numbers.len() as f32;
-	let std_dev = (numbers.iter().sum::<f32>() / numbers.len() as f32).sqrt();
-	let min = numbers
-		.iter()
-		.copied()
-		.reduce(|a, b| a.min(b))
-		.unwrap_or_default();
-	let max = numbers
-		.iter()
-		.copied()
-		.reduce(|a, b| a.max(b))
-		.unwrap_or_default();
+/// Gets a percentile (from 0 to 1) out of sorted numbers, interpolating between the closest two
+fn percentile(sorted: &[f32], percentile: f32) -> f32 {
+	if sorted.is_empty() {
+		return 0.0;
+	}
+	let rank = percentile * (sorted.len() - 1) as f32;
+	let lower = sorted[rank.floor() as usize];
+	let upper = sorted[rank.ceil() as usize];
+	lower + (upper - lower) * rank.fract()
+}
+
+/// Calculates the statistics for a set of numbers, which must be in match order
+fn number_entry(numbers: Vec<f32>, is_time: bool, recent_matches: usize) -> TeamInfoEntry {
+	let count = numbers.len() as f32;
+	let value = numbers.iter().sum::<f32>() / count;
+	let std_dev = if numbers.len() > 1 {
+		(numbers.iter().map(|n| (n - value).powi(2)).sum::<f32>() / (count - 1.0)).sqrt()
+	} else {
+		0.0
+	};
+	let mut sorted = numbers.clone();
+	sorted.sort_by(|a, b| a.total_cmp(b));
+	let min = sorted.first().copied().unwrap_or_default();
+	let max = sorted.last().copied().unwrap_or_default();
+
+	let recent = &numbers[numbers.len().saturating_sub(recent_matches.max(1))..];
+	let recent_average = recent.iter().sum::<f32>() / recent.len() as f32;
+	// Least squares slope of the values against the match order
+	let trend = if numbers.len() > 1 {
+		let index_mean = (count - 1.0) / 2.0;
+		let (covariance, variance) =
+			numbers
+				.iter()
+				.enumerate()
+				.fold((0.0, 0.0), |(covariance, variance), (idx, number)| {
+					let index_diff = idx as f32 - index_mean;
+					(
+						covariance + index_diff * (number - value),
+						variance + index_diff.powi(2),
+					)
+				});
+		covariance / variance
+	} else {
+		0.0
+	};
+	// 1 means every match was the same, 0 means the standard deviation is as big as the average
+	let consistency = if value.abs() > 0.0 {
+		(1.0 - std_dev / value.abs()).clamp(0.0, 1.0)
+	} else if std_dev == 0.0 {
+		1.0
+	} else {
+		0.0
+	};
+
 	TeamInfoEntry {
 		text: format!("{value:.2}"),
 		pit_value: None,
@@ -283,12 +858,62 @@ fn number_entry(numbers: Vec<f32>, is_time: bool) -> TeamInfoEntry {
 			number: value,
 			collected_std_dev: Some(std_dev),
 			collected_min_max: Some([min, max]),
+			collected_stats: Some(NumericStats {
+				median: percentile(&sorted, 0.5),
+				percentile_25: percentile(&sorted, 0.25),
+				percentile_75: percentile(&sorted, 0.75),
+				recent_average,
+				trend,
+				consistency,
+			}),
 			compare_other_numbers: None,
 			is_time,
 		})),
 	}
 }
 
+/// Switches a numeric entry to show the selected statistic instead of the average
+fn select_stat(mut entry: TeamInfoEntry, stat: NumericStat) -> TeamInfoEntry {
+	let Some(TeamInfoGraphic::Numeric(numeric)) = &mut entry.graphic else {
+		return entry;
+	};
+	let value = match (stat, &numeric.collected_stats) {
+		(NumericStat::Mean, _) => return entry,
+		(NumericStat::StdDev, _) => numeric.collected_std_dev,
+		(NumericStat::Min, _) => numeric.collected_min_max.map(|[min, _]| min),
+		(NumericStat::Max, _) => numeric.collected_min_max.map(|[_, max]| max),
+		(_, None) => None,
+		(NumericStat::Median, Some(stats)) => Some(stats.median),
+		(NumericStat::Percentile25, Some(stats)) => Some(stats.percentile_25),
+		(NumericStat::Percentile75, Some(stats)) => Some(stats.percentile_75),
+		(NumericStat::RecentAverage, Some(stats)) => Some(stats.recent_average),
+		(NumericStat::Trend, Some(stats)) => Some(stats.trend),
+		(NumericStat::Consistency, Some(stats)) => Some(stats.consistency),
+	};
+	match value {
+		Some(value) => {
+			numeric.number = value;
+			entry.sort_value = value;
+			entry.text = if stat == NumericStat::Consistency {
+				format!("{:.0}%", value * 100.0)
+			} else {
+				format!("{value:.2}")
+			};
+		}
+		None => {
+			entry.text = String::new();
+			entry.sort_value = -420.0;
+			entry.graphic = None;
+		}
+	}
+	entry
+}
+
+/// Used to put data in match order, since match ids are just the qualification match number
+fn match_order(match_id: &str) -> u32 {
+	match_id.parse().unwrap_or(u32::MAX)
+}
+
 fn single_team_impl(
 	config: &GameConfigs,
 	match_entries: &[MatchEntryIdData],
@@ -304,27 +929,33 @@ fn single_team_impl(
 		.team_row
 		.iter()
 		.map(|column| match column {
-			DisplayColumn::Single(metric) => get_single_metric(
-				config,
-				match_entries,
-				driver_entries,
-				pit_entry,
-				statbotics,
-				team,
-				tba_data.team_infos.get(&team),
-				&metric.metric,
-				None,
+			DisplayColumn::Single(metric) => select_stat(
+				get_single_metric(
+					config,
+					match_entries,
+					driver_entries,
+					pit_entry,
+					statbotics,
+					team,
+					tba_data.team_infos.get(&team),
+					&metric.metric,
+					None,
+				),
+				metric.stat,
 			),
-			DisplayColumn::Filtered(metric) => get_single_metric(
-				config,
-				match_entries,
-				driver_entries,
-				pit_entry,
-				statbotics,
-				team,
-				tba_data.team_infos.get(&team),
-				&metric.metric,
-				Some(&metric.filter_by),
+			DisplayColumn::Filtered(metric) => select_stat(
+				get_single_metric(
+					config,
+					match_entries,
+					driver_entries,
+					pit_entry,
+					statbotics,
+					team,
+					tba_data.team_infos.get(&team),
+					&metric.metric,
+					Some(&metric.filter_by),
+				),
+				metric.stat,
 			),
 			DisplayColumn::TeamName(_) => {
 				let team_info = tba_data.get_team_info(team);
@@ -348,8 +979,19 @@ fn single_team_impl(
 		.collect()
 }
 
+/// Finds how a metric is collected, checking match, then pit, then drive team fields
+fn get_entry_type<'a>(config: &'a GameConfigs, metric: &str) -> Option<&'a MatchEntryType> {
+	config
+		.match_entry_fields
+		.entries
+		.get(metric)
+		.or_else(|| config.pit_entry_fields.entries.get(metric))
+		.or_else(|| config.driver_entry_fields.entries.get(metric))
+		.map(|e| &e.entry)
+}
+
 #[allow(clippy::too_many_arguments)]
-fn get_single_metric(
+pub fn get_single_metric(
 	config: &GameConfigs,
 	match_entries: &[MatchEntryIdData],
 	driver_entries: &[DriverEntryIdData],
@@ -360,7 +1002,7 @@ fn get_single_metric(
 	metric: &str,
 	filter_metric: Option<&str>,
 ) -> TeamInfoEntry {
-	let data_points: Vec<_> = match_entries
+	let team_match_entries: Vec<_> = match_entries
 		.iter()
 		.filter(|match_entry| match_entry.team_id.parse::<u32>().unwrap() == team_number)
 		.filter(|match_entry| {
@@ -373,14 +1015,31 @@ fn get_single_metric(
 				true
 			}
 		})
-		.filter_map(|match_entry| match_entry.data.entries.get(metric))
+		.collect();
+	let mut data_points: Vec<_> = team_match_entries
+		.iter()
+		.filter_map(|match_entry| {
+			match_entry
+				.data
+				.entries
+				.get(metric)
+				.map(|dp| (match_order(&match_entry.match_id), dp))
+		})
 		.chain(
 			driver_entries
 				.iter()
 				.filter(|match_entry| match_entry.team_id.parse::<u32>().unwrap() == team_number)
-				.filter_map(|match_entry| match_entry.data.entries.get(metric)),
+				.filter_map(|match_entry| {
+					match_entry
+						.data
+						.entries
+						.get(metric)
+						.map(|dp| (match_order(&match_entry.match_id), dp))
+				}),
 		)
 		.collect();
+	data_points.sort_by_key(|(order, _)| *order);
+	let data_points: Vec<_> = data_points.into_iter().map(|(_, dp)| dp).collect();
 	let pit_data_point = pit_entry.and_then(|pe| pe.entries.get(metric)).cloned();
 
 	if let Some(real_metric) = metric.strip_prefix(BASE_PREFIX) {
@@ -403,6 +1062,7 @@ fn get_single_metric(
 								value: team_info.ties as f32,
 							},
 						],
+						expected_points: None,
 					},
 					if played_games == 0 {
 						-420.0
@@ -437,7 +1097,21 @@ fn get_single_metric(
 						team_info.ranking_points as f32 / played_games as f32
 					},
 				)),
-				_ => None,
+				// Lower ranks are better, so they're negated to sort and weigh like everything else
+				"rank" => team_info.rank.map(|rank| (rank as f32, -(rank as f32))),
+				"rp-average" => team_info
+					.rp_average
+					.or_else(|| {
+						(played_games > 0)
+							.then(|| team_info.ranking_points as f32 / played_games as f32)
+					})
+					.map(|rp_average| (rp_average, rp_average)),
+				"dqs" => Some((team_info.dqs as f32, team_info.dqs as f32)),
+				sort_order => sort_order
+					.strip_prefix("sort-")
+					.and_then(|n| n.parse::<usize>().ok())
+					.and_then(|n| team_info.sort_orders.get(n.checked_sub(1)?))
+					.map(|sort_order| (sort_order.value, sort_order.value)),
 			} {
 				TeamInfoEntry {
 					text: format!("{display_value:.2}"),
@@ -448,10 +1122,21 @@ fn get_single_metric(
 						number: display_value,
 						collected_std_dev: None,
 						collected_min_max: None,
+						collected_stats: None,
 						compare_other_numbers: None,
 						is_time: false,
 					})),
 				}
+			} else if matches!(real_metric, "rank" | "rp-average")
+				|| real_metric.starts_with("sort-")
+			{
+				TeamInfoEntry {
+					text: "Unranked".to_string(),
+					pit_value: None,
+					sort_value: -420.0,
+					colour: [255, 255, 255],
+					graphic: None,
+				}
 			} else {
 				TeamInfoEntry {
 					text: format!("ERROR: Invalid metric '{metric}'"),
@@ -479,6 +1164,7 @@ fn get_single_metric(
 								value: sb.record.ties as f32,
 							},
 						],
+						expected_points: None,
 					},
 					if sb.record.count == 0 {
 						-420.0
@@ -507,6 +1193,9 @@ fn get_single_metric(
 				"ties" => Some(sb.record.ties as f32),
 				"games" => Some(sb.record.count as f32),
 				"rps" => Some(sb.record.rps as f32),
+				"rp-average" if sb.record.count > 0 => {
+					Some(sb.record.rps as f32 / sb.record.count as f32)
+				}
 				_ => None,
 			} {
 				TeamInfoEntry {
@@ -518,6 +1207,7 @@ fn get_single_metric(
 						number: value,
 						collected_std_dev: None,
 						collected_min_max: None,
+						collected_stats: None,
 						compare_other_numbers: None,
 						is_time: false,
 					})),
@@ -571,6 +1261,7 @@ fn get_single_metric(
 							None
 						},
 						collected_min_max: None,
+						collected_stats: None,
 						compare_other_numbers: None,
 						is_time: false,
 					})),
@@ -593,46 +1284,44 @@ fn get_single_metric(
 				graphic: None,
 			}
 		}
+	} else if let Some((
+		formula,
+		(
+			_,
+			CollectedMetric {
+				metric: CollectedMetricType::Formula(formula_metric),
+				..
+			},
+		),
+	)) = config
+		.formulas
+		.get(metric)
+		.zip(config.get_collected_metric(metric))
+	{
+		formula_entry(config, formula, formula_metric, &team_match_entries)
 	} else {
-		match config
-			.match_entry_fields
-			.entries
-			.get(metric)
-			.as_ref()
-			.map(|e| &e.entry)
-			.or_else(|| {
-				config
-					.pit_entry_fields
-					.entries
-					.get(metric)
-					.as_ref()
-					.map(|e| &e.entry)
-			})
-			.or_else(|| {
-				config
-					.driver_entry_fields
-					.entries
-					.get(metric)
-					.as_ref()
-					.map(|e| &e.entry)
-			}) {
-			Some(MatchEntryType::Ability(_)) => get_pie_chart(
+		match get_entry_type(config, metric) {
+			Some(entry_type @ MatchEntryType::Ability(_)) => with_expected_points(
+				get_pie_chart(
+					&data_points,
+					pit_data_point.as_ref(),
+					&[
+						("Nothing", None),
+						("Attempted", Some(0.0)),
+						("Succeeded", Some(1.0)),
+					],
+					|options, pct| {
+						if options.iter().all(|option| option.value == 0.0) {
+							"".to_string()
+						} else {
+							format!("{:.0}%", pct * 100.0)
+						}
+					},
+				),
+				entry_type,
 				&data_points,
-				pit_data_point.as_ref(),
-				&[
-					("Nothing", None),
-					("Attempted", Some(0.0)),
-					("Succeeded", Some(1.0)),
-				],
-				|options, pct| {
-					if options.iter().all(|option| option.value == 0.0) {
-						"".to_string()
-					} else {
-						format!("{:.0}%", pct * 100.0)
-					}
-				},
 			),
-			Some(MatchEntryType::Enum(enum_metric)) => {
+			Some(entry_type @ MatchEntryType::Enum(enum_metric)) => {
 				let count = enum_metric.options.len() as f32;
 				let option_values = enum_metric
 					.options
@@ -640,36 +1329,40 @@ fn get_single_metric(
 					.enumerate()
 					.map(|(i, option)| (option.as_str(), Some((i as f32) / count)))
 					.collect::<Vec<_>>();
-				get_pie_chart(
-					&data_points,
-					pit_data_point.as_ref(),
-					&option_values,
-					|options, _| {
-						let option_sum = options.iter().map(|option| option.value).sum::<f32>();
-						if option_sum == 0.0 {
-							"".to_string()
-						} else {
-							let non_zero_items = options
-								.iter()
-								.filter(|option| option.value > 0.0)
-								.collect::<Vec<_>>();
-							if non_zero_items.len() == 1 {
-								non_zero_items[0].label.to_string()
+				with_expected_points(
+					get_pie_chart(
+						&data_points,
+						pit_data_point.as_ref(),
+						&option_values,
+						|options, _| {
+							let option_sum = options.iter().map(|option| option.value).sum::<f32>();
+							if option_sum == 0.0 {
+								"".to_string()
 							} else {
-								let top_item = non_zero_items
+								let non_zero_items = options
 									.iter()
-									.max_by(|option_a, option_b| {
-										option_a.value.total_cmp(&option_b.value)
-									})
-									.unwrap();
-								format!(
-									"{:.0}% {}",
-									top_item.value / option_sum * 100.0,
-									top_item.label
-								)
+									.filter(|option| option.value > 0.0)
+									.collect::<Vec<_>>();
+								if non_zero_items.len() == 1 {
+									non_zero_items[0].label.to_string()
+								} else {
+									let top_item = non_zero_items
+										.iter()
+										.max_by(|option_a, option_b| {
+											option_a.value.total_cmp(&option_b.value)
+										})
+										.unwrap();
+									format!(
+										"{:.0}% {}",
+										top_item.value / option_sum * 100.0,
+										top_item.label
+									)
+								}
 							}
-						}
-					},
+						},
+					),
+					entry_type,
+					&data_points,
 				)
 			}
 			Some(MatchEntryType::Bool(_)) => get_pie_chart(
@@ -709,6 +1402,7 @@ fn get_single_metric(
 							})
 							.collect(),
 						true,
+						config.game_config.display.recent_matches,
 					)
 				}
 			}
@@ -737,6 +1431,53 @@ fn get_single_metric(
 							})
 							.collect(),
 						false,
+						config.game_config.display.recent_matches,
+					)
+				}
+			}
+			Some(entry_type @ MatchEntryType::AttemptCounter(_)) => with_expected_points(
+				attempt_counter_entry(&data_points, pit_data_point.as_ref()),
+				entry_type,
+				&data_points,
+			),
+			Some(MatchEntryType::EventLog(event_log_metric)) => {
+				event_log_entry(config, event_log_metric, &data_points)
+			}
+			Some(MatchEntryType::FieldPosition(_)) => field_position_entry(&data_points),
+			Some(MatchEntryType::MultiSelect(multi_select_metric)) => {
+				multi_select_entry(multi_select_metric, &data_points, pit_data_point.as_ref())
+			}
+			Some(MatchEntryType::Rating(rating_metric)) => {
+				if data_points.is_empty() {
+					TeamInfoEntry {
+						text: String::new(),
+						pit_value: match pit_data_point {
+							Some(MatchEntryValue::Rating(r)) => Some(
+								rating_metric
+									.get_label(r.value)
+									.map(|label| label.to_string())
+									.unwrap_or_else(|| r.value.to_string()),
+							),
+							_ => None,
+						},
+						sort_value: -420.0,
+						colour: [255, 255, 255],
+						graphic: None,
+					}
+				} else {
+					number_entry(
+						data_points
+							.iter()
+							.map(|dp| {
+								if let MatchEntryValue::Rating(r) = dp {
+									r.value
+								} else {
+									panic!("Invalid data type of {dp:?} for rating match entry");
+								}
+							})
+							.collect(),
+						false,
+						config.game_config.display.recent_matches,
 					)
 				}
 			}
@@ -823,8 +1564,14 @@ fn table_labels(config: &GameConfigs) -> Vec<NameAndSource> {
 		.team_row
 		.iter()
 		.map(|column| match column {
-			DisplayColumn::Single(SingleMetric { metric, .. })
-			| DisplayColumn::Filtered(FilteredMetric { metric, .. }) => get_metric_name(config, metric),
+			DisplayColumn::Single(SingleMetric { metric, stat, .. })
+			| DisplayColumn::Filtered(FilteredMetric { metric, stat, .. }) => {
+				let mut name = get_metric_name(config, metric);
+				if let Some(stat_name) = stat.name() {
+					name.name = format!("{} ({stat_name})", name.name);
+				}
+				name
+			}
 			DisplayColumn::TeamName(_) => NameAndSource {
 				name: "Team Name".to_string(),
 				page: "N/A".to_string(),
@@ -839,7 +1586,7 @@ fn table_labels(config: &GameConfigs) -> Vec<NameAndSource> {
 		.collect()
 }
 
-fn get_metric_name(config: &GameConfigs, metric: &str) -> NameAndSource {
+pub fn get_metric_name(config: &GameConfigs, metric: &str) -> NameAndSource {
 	if metric.starts_with(BASE_PREFIX) {
 		NameAndSource {
 			name: match metric.trim_start_matches(BASE_PREFIX) {
@@ -848,7 +1595,13 @@ fn get_metric_name(config: &GameConfigs, metric: &str) -> NameAndSource {
 				"losses" => "Losses".to_string(),
 				"ties" => "Ties".to_string(),
 				"rps" => "RPs".to_string(),
-				_ => "Unknown Base Stat".to_string(),
+				"rank" => "Rank".to_string(),
+				"rp-average" => "RP Average".to_string(),
+				"dqs" => "DQs".to_string(),
+				stat => match stat.strip_prefix("sort-") {
+					Some(n) => format!("Sort Order {n}"),
+					None => "Unknown Base Stat".to_string(),
+				},
 			},
 			page: "Record".to_string(),
 			source: DataSource::Tba,
@@ -938,13 +1691,19 @@ fn default_display(config: &GameConfigs) -> Vec<usize> {
 		.collect()
 }
 
-fn get_match_entries(
+pub fn get_match_entries(
 	database: &Database,
+	config: &GameConfigs,
 	year: u32,
 	event: &str,
 	tba_data: &EventInfo,
 ) -> Vec<MatchEntryIdData> {
 	let mut match_entries = database.get_all_match_entries(year, event);
+	for match_entry in &mut match_entries {
+		remove_uncounted(&mut match_entry.data, &config.match_entry_fields);
+		// Implied values aren't saved, so they follow the values they come from when those change
+		apply_implications(&mut match_entry.data, &config.match_entry_fields);
+	}
 	for match_info in &tba_data.match_infos {
 		if let MatchId::Qualification(num) = &match_info.id {
 			for (team, custom_entries) in match_info
@@ -976,6 +1735,185 @@ fn get_match_entries(
 	match_entries
 }
 
+/// Gets every driver entry for the event, with only the values that count and everything they imply
+pub fn get_driver_entries(
+	database: &Database,
+	config: &GameConfigs,
+	year: u32,
+	event: &str,
+) -> Vec<DriverEntryIdData> {
+	let mut driver_entries = database.get_all_driver_entries(year, event);
+	for driver_entry in &mut driver_entries {
+		remove_uncounted(&mut driver_entry.data, &config.driver_entry_fields);
+		apply_implications(&mut driver_entry.data, &config.driver_entry_fields);
+	}
+	driver_entries
+}
+
+/// Gets every pit entry for the event by team, with only the values that count and everything
+/// they imply
+pub fn get_pit_entries(
+	database: &Database,
+	config: &GameConfigs,
+	year: u32,
+	event: &str,
+) -> HashMap<String, FullEntryData> {
+	let mut pit_entries = database.get_all_pit_entries(year, event);
+	for pit_entry in pit_entries.values_mut() {
+		remove_uncounted(pit_entry, &config.pit_entry_fields);
+		apply_implications(pit_entry, &config.pit_entry_fields);
+	}
+	pit_entries
+}
+
+/// Gets a team's pit entry, with only the values that count and everything they imply
+pub fn get_pit_entry(
+	database: &Database,
+	config: &GameConfigs,
+	year: u32,
+	event: &str,
+	team: u32,
+) -> Option<FullEntryData> {
+	let mut pit_entry = database
+		.get_pit_entry_data(year, event, &team.to_string())
+		.unwrap();
+	if let Some(pit_entry) = &mut pit_entry {
+		remove_uncounted(pit_entry, &config.pit_entry_fields);
+		apply_implications(pit_entry, &config.pit_entry_fields);
+	}
+	pit_entry
+}
+
+/// Gets the value of every numeric metric in each match a team played, in match order
+fn get_match_series(
+	config: &GameConfigs,
+	match_entries: &[MatchEntryIdData],
+	tba_data: &EventInfo,
+	team: u32,
+) -> Vec<MetricSeries> {
+	let start_times: HashMap<String, u64> = tba_data
+		.match_infos
+		.iter()
+		.filter_map(|match_info| match &match_info.id {
+			MatchId::Qualification(num) => Some((num.num.to_string(), match_info.start_time)),
+			_ => None,
+		})
+		.collect();
+	let mut team_match_entries: Vec<_> = match_entries
+		.iter()
+		.filter(|match_entry| match_entry.team_id.parse::<u32>().unwrap() == team)
+		.collect();
+	team_match_entries.sort_by_key(|match_entry| match_order(&match_entry.match_id));
+
+	config
+		.all_metrics
+		.iter()
+		.filter_map(|metric| {
+			let points: Vec<_> = if let Some(formula) = config.formulas.get(metric) {
+				let Some((
+					_,
+					CollectedMetric {
+						metric: CollectedMetricType::Formula(formula_metric),
+						..
+					},
+				)) = config.get_collected_metric(metric)
+				else {
+					return None;
+				};
+				formula_match_values(config, formula, formula_metric, &team_match_entries)
+					.into_iter()
+					.map(|(match_id, value)| MetricSeriesPoint {
+						match_id: match_id.to_string(),
+						start_time: start_times.get(match_id).copied(),
+						value,
+						text: if formula_metric.is_time {
+							format!("{value:.1}s")
+						} else {
+							format!("{value:.2}")
+						},
+					})
+					.collect()
+			} else {
+				team_match_entries
+					.iter()
+					.filter_map(|match_entry| {
+						let (value, text) = match match_entry.data.entries.get(metric)? {
+							MatchEntryValue::Counter(c) => (c.count as f32, c.count.to_string()),
+							MatchEntryValue::Timer(t) => {
+								(t.time_seconds, format!("{}s", t.time_seconds))
+							}
+							MatchEntryValue::AttemptCounter(a) => (
+								a.successes as f32,
+								format!("{}/{}", a.successes, a.attempts),
+							),
+							MatchEntryValue::Rating(r) => (
+								r.value,
+								get_entry_type(config, metric)
+									.and_then(|entry_type| match entry_type {
+										MatchEntryType::Rating(rating_metric) => {
+											rating_metric.get_label(r.value)
+										}
+										_ => None,
+									})
+									.map(|label| label.to_string())
+									.unwrap_or_else(|| r.value.to_string()),
+							),
+							MatchEntryValue::EventLog(e) => {
+								(e.events.len() as f32, e.events.len().to_string())
+							}
+							ability @ MatchEntryValue::Ability(_) => (
+								get_entry_type(config, metric)
+									.and_then(|entry_type| point_value(entry_type, ability))
+									.unwrap_or_else(|| formula_value(ability)),
+								ability.option_label()?,
+							),
+							_ => return None,
+						};
+						Some(MetricSeriesPoint {
+							match_id: match_entry.match_id.clone(),
+							start_time: start_times.get(&match_entry.match_id).copied(),
+							value,
+							text,
+						})
+					})
+					.collect()
+			};
+			(!points.is_empty()).then(|| MetricSeries {
+				name: get_metric_name(config, metric),
+				points,
+			})
+		})
+		.collect()
+}
+
+/// The current event from TBA. If TBA doesn't have it and it hasn't been saved, this has every
+/// team that has been scouted and no matches, so analysis still works on scouting data alone.
+pub async fn get_event_info(tba: &Tba, database: &Database, team_config: &TeamConfig) -> EventInfo {
+	let (year, event) = (team_config.current_year, team_config.current_event.as_str());
+	if let Some(event_info) = tba.get_event(year, event).await {
+		return event_info;
+	}
+	warn!("There's no TBA data for {event}, only using scouting data");
+	let teams = database
+		.get_all_pit_entries(year, event)
+		.into_keys()
+		.chain(
+			database
+				.get_all_match_entries(year, event)
+				.into_iter()
+				.map(|match_entry| match_entry.team_id),
+		)
+		.chain(
+			database
+				.get_all_driver_entries(year, event)
+				.into_iter()
+				.map(|driver_entry| driver_entry.team_id),
+		)
+		.filter_map(|team| team.parse::<u32>().ok())
+		.collect::<BTreeSet<_>>();
+	EventInfo::without_tba(year, event, teams)
+}
+
 pub async fn get_single_team_analysis(
 	tba: &Tba,
 	statbotics: &StatboticsCache,
@@ -986,14 +1924,16 @@ pub async fn get_single_team_analysis(
 ) -> SingleTeamInfo {
 	info!("Loading team analysis for {team}");
 
-	let driver_entries =
-		database.get_all_driver_entries(team_config.current_year, &team_config.current_event);
-	let tba_data = tba
-		.get_event(team_config.current_year, &team_config.current_event)
-		.await
-		.unwrap();
+	let driver_entries = get_driver_entries(
+		database,
+		config,
+		team_config.current_year,
+		&team_config.current_event,
+	);
+	let tba_data = get_event_info(tba, database, team_config).await;
 	let match_entries = get_match_entries(
 		database,
+		config,
 		team_config.current_year,
 		&team_config.current_event,
 		&tba_data,
@@ -1013,14 +1953,14 @@ pub async fn get_single_team_analysis(
 					config,
 					&match_entries,
 					&driver_entries,
-					database
-						.get_pit_entry_data(
-							team_config.current_year,
-							&team_config.current_event,
-							&team.to_string(),
-						)
-						.unwrap()
-						.as_ref(),
+					get_pit_entry(
+						database,
+						config,
+						team_config.current_year,
+						&team_config.current_event,
+						team,
+					)
+					.as_ref(),
 					statbotics_team.as_deref(),
 					team,
 					tba_data.team_infos.get(&team),
@@ -1029,6 +1969,21 @@ pub async fn get_single_team_analysis(
 				),
 			})
 			.collect(),
+		match_series: get_match_series(config, &match_entries, &tba_data, team),
+	}
+}
+
+/// The number to compare between teams when colouring the team list, if there is one
+fn comparable_number(entry: &TeamInfoEntry) -> Option<f32> {
+	match &entry.graphic {
+		Some(TeamInfoGraphic::Numeric(TeamInfoNumericEntry { number, .. })) => Some(*number),
+		Some(TeamInfoGraphic::PieChart(PieChartEntry {
+			expected_points, ..
+		})) => *expected_points,
+		Some(TeamInfoGraphic::EventLog(EventLogSummary { average_scores, .. })) => {
+			Some(*average_scores)
+		}
+		_ => None,
 	}
 }
 
@@ -1039,16 +1994,22 @@ pub async fn get_analysis_list(
 	team_config: &TeamConfig,
 	config: &GameConfigs,
 ) -> TeamInfoList {
-	let driver_entries =
-		database.get_all_driver_entries(team_config.current_year, &team_config.current_event);
-	let pit_entries =
-		database.get_all_pit_entries(team_config.current_year, &team_config.current_event);
-	let tba_data = tba
-		.get_event(team_config.current_year, &team_config.current_event)
-		.await
-		.unwrap();
+	let driver_entries = get_driver_entries(
+		database,
+		config,
+		team_config.current_year,
+		&team_config.current_event,
+	);
+	let pit_entries = get_pit_entries(
+		database,
+		config,
+		team_config.current_year,
+		&team_config.current_event,
+	);
+	let tba_data = get_event_info(tba, database, team_config).await;
 	let match_entries = get_match_entries(
 		database,
+		config,
 		team_config.current_year,
 		&team_config.current_event,
 		&tba_data,
@@ -1082,26 +2043,25 @@ pub async fn get_analysis_list(
 	for row in 0..til.heading.len() {
 		let (mut min, mut max, mut avg, mut cnt) = (f32::MAX, f32::MIN, 0.0, 0.0);
 		for team in &til.list {
-			if let Some(TeamInfoGraphic::Numeric(TeamInfoNumericEntry { number, .. })) =
-				&team.info[row].graphic
-			{
-				min = min.min(*number);
-				max = max.max(*number);
-				avg += *number;
+			if let Some(number) = comparable_number(&team.info[row]) {
+				min = min.min(number);
+				max = max.max(number);
+				avg += number;
 				cnt += 1.0;
 			}
 		}
 		avg /= cnt;
 		let mut stddev = 0.0;
 		for team in &til.list {
-			if let Some(TeamInfoGraphic::Numeric(TeamInfoNumericEntry { number, .. })) =
-				&team.info[row].graphic
-			{
-				stddev += (*number - avg).powi(2);
+			if let Some(number) = comparable_number(&team.info[row]) {
+				stddev += (number - avg).powi(2);
 			}
 		}
 		stddev = (stddev / cnt).sqrt();
 		for team in &mut til.list {
+			let Some(number) = comparable_number(&team.info[row]) else {
+				continue;
+			};
 			if let Some(TeamInfoGraphic::Numeric(entry)) = &mut team.info[row].graphic {
 				entry.compare_other_numbers = Some(MinMaxAvg {
 					min,
@@ -1109,13 +2069,13 @@ pub async fn get_analysis_list(
 					avg,
 					stddev,
 				});
-				let colour_value = ((entry.number - avg) / (2.0 * stddev)).clamp(-1.0, 1.0);
-				team.info[row].colour = [
-					((1.0 - colour_value).min(1.0) * 255.9) as u8,
-					((colour_value + 1.0).min(1.0) * 255.9) as u8,
-					((1.0 - colour_value.abs()) * 255.9) as u8,
-				];
 			}
+			let colour_value = ((number - avg) / (2.0 * stddev)).clamp(-1.0, 1.0);
+			team.info[row].colour = [
+				((1.0 - colour_value).min(1.0) * 255.9) as u8,
+				((colour_value + 1.0).min(1.0) * 255.9) as u8,
+				((1.0 - colour_value.abs()) * 255.9) as u8,
+			];
 		}
 	}
 	til
@@ -1131,14 +2091,16 @@ pub async fn get_match_analysis(
 ) -> MatchAnalysisInfo {
 	info!("Loading match preview for {match_id:?}");
 
-	let driver_entries =
-		database.get_all_driver_entries(team_config.current_year, &team_config.current_event);
-	let tba_data = tba
-		.get_event(team_config.current_year, &team_config.current_event)
-		.await
-		.unwrap();
+	let driver_entries = get_driver_entries(
+		database,
+		config,
+		team_config.current_year,
+		&team_config.current_event,
+	);
+	let tba_data = get_event_info(tba, database, team_config).await;
 	let match_entries = get_match_entries(
 		database,
+		config,
 		team_config.current_year,
 		&team_config.current_event,
 		&tba_data,
@@ -1246,13 +2208,13 @@ fn get_single_team_match_preview(
 		.map(|team| (team.num, team.name.clone(), team.get_icon_url()))
 		.unwrap_or((*team, "".to_string(), None));
 
-	let pit_entry = database
-		.get_pit_entry_data(
-			team_config.current_year,
-			&team_config.current_event,
-			&team.to_string(),
-		)
-		.unwrap();
+	let pit_entry = get_pit_entry(
+		database,
+		config,
+		team_config.current_year,
+		&team_config.current_event,
+		*team,
+	);
 
 	let other_data = pre_match_display
 		.metrics
@@ -1312,3 +2274,202 @@ fn get_single_team_match_preview(
 		other_data,
 	}
 }
+
+/// Scores every team that is still available by how well it complements our own robot. Each
+/// weighted metric is normalized across the event, and metrics where we are weak count for up to
+/// twice as much as metrics where we are already strong.
+pub async fn get_pick_recommendations(
+	tba: &Tba,
+	statbotics: &StatboticsCache,
+	database: &Database,
+	team_config: &TeamConfig,
+	config: &GameConfigs,
+	pick_list: &PickList,
+) -> Vec<PickRecommendation> {
+	let driver_entries = get_driver_entries(
+		database,
+		config,
+		team_config.current_year,
+		&team_config.current_event,
+	);
+	let pit_entries = get_pit_entries(
+		database,
+		config,
+		team_config.current_year,
+		&team_config.current_event,
+	);
+	let tba_data = get_event_info(tba, database, team_config).await;
+	let match_entries = get_match_entries(
+		database,
+		config,
+		team_config.current_year,
+		&team_config.current_event,
+		&tba_data,
+	);
+	let weights = &config.game_config.display.pick_weights;
+
+	let statbotics_teams = future::join_all(
+		tba_data
+			.team_infos
+			.keys()
+			.map(|team| async move { (*team, statbotics.get(*team).await) }),
+	)
+	.await;
+	let team_values = statbotics_teams
+		.into_iter()
+		.map(|(team, sb)| {
+			let values = weights
+				.iter()
+				.map(|weight| {
+					let entry = get_single_metric(
+						config,
+						&match_entries,
+						&driver_entries,
+						pit_entries.get(&team.to_string()),
+						sb.as_deref(),
+						team,
+						tba_data.team_infos.get(&team),
+						&weight.metric,
+						None,
+					);
+					// -420 is what all the metrics use to say there's no data
+					(entry.sort_value != -420.0).then_some(entry.sort_value)
+				})
+				.collect::<Vec<_>>();
+			(team, values)
+		})
+		.collect::<HashMap<_, _>>();
+
+	let ranges = (0..weights.len())
+		.map(|idx| {
+			team_values
+				.values()
+				.filter_map(|values| values[idx])
+				.fold((f32::MAX, f32::MIN), |(min, max), value| {
+					(min.min(value), max.max(value))
+				})
+		})
+		.collect::<Vec<_>>();
+	let normalize = |idx: usize, value: f32| {
+		let (min, max) = ranges[idx];
+		if max > min {
+			(value - min) / (max - min)
+		} else {
+			1.0
+		}
+	};
+	let our_values = team_values.get(&team_config.team);
+
+	let mut recommendations = team_values
+		.iter()
+		.filter(|(team, _)| {
+			**team != team_config.team
+				&& !pick_list.is_picked(**team)
+				&& pick_list.get_category(**team) != Some(PickListCategory::DoNotPick)
+		})
+		.map(|(team, values)| {
+			let score_parts = weights
+				.iter()
+				.enumerate()
+				.map(|(idx, weight)| {
+					let complement = match our_values.and_then(|ours| ours[idx]) {
+						Some(ours) => 1.0 - normalize(idx, ours),
+						None => 0.5,
+					};
+					MatchAnalysisScorePart {
+						name: get_metric_name(config, &weight.metric).name,
+						score: values[idx]
+							.map(|value| weight.weight * normalize(idx, value) * (1.0 + complement))
+							.unwrap_or_default(),
+					}
+				})
+				.collect::<Vec<_>>();
+			let team_info = tba_data.get_team_info(*team);
+			PickRecommendation {
+				team_number: *team,
+				team_name: team_info.name,
+				team_icon_uri: team_info.icon_uri,
+				category: pick_list.get_category(*team),
+				score: score_parts.iter().map(|part| part.score).sum(),
+				score_parts,
+			}
+		})
+		.collect::<Vec<_>>();
+	recommendations.sort_by(|a, b| b.score.total_cmp(&a.score));
+	recommendations
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn assert_close(actual: f32, expected: f32) {
+		assert!(
+			(actual - expected).abs() < 1e-4,
+			"expected {expected}, got {actual}"
+		);
+	}
+
+	fn numeric_stats(
+		numbers: Vec<f32>,
+		recent_matches: usize,
+	) -> (TeamInfoNumericEntry, NumericStats) {
+		let Some(TeamInfoGraphic::Numeric(numeric)) =
+			number_entry(numbers, false, recent_matches).graphic
+		else {
+			panic!("not a numeric entry");
+		};
+		let stats = numeric.collected_stats.clone().unwrap();
+		(numeric, stats)
+	}
+
+	#[test]
+	fn percentiles_interpolate() {
+		let sorted = [1.0, 2.0, 4.0, 8.0];
+		assert_close(percentile(&sorted, 0.0), 1.0);
+		assert_close(percentile(&sorted, 1.0), 8.0);
+		// Halfway between the second and third numbers
+		assert_close(percentile(&sorted, 0.5), 3.0);
+		// A quarter of the way from the third number to the fourth
+		assert_close(percentile(&sorted, 0.75), 5.0);
+		assert_close(percentile(&[5.0], 0.25), 5.0);
+		assert_close(percentile(&[], 0.5), 0.0);
+	}
+
+	#[test]
+	fn number_stats() {
+		let (numeric, stats) = numeric_stats(vec![2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0], 3);
+		assert_close(numeric.number, 5.0);
+		// The squared differences from the average add up to 32, over 7 degrees of freedom
+		assert_close(numeric.collected_std_dev.unwrap(), (32.0f32 / 7.0).sqrt());
+		assert_eq!(numeric.collected_min_max, Some([2.0, 9.0]));
+		assert_close(stats.median, 4.5);
+		assert_close(stats.percentile_25, 4.0);
+		assert_close(stats.percentile_75, 5.5);
+		assert_close(stats.recent_average, 7.0);
+		// Covariance with the match index is 34, and the index's variance is 42
+		assert_close(stats.trend, 34.0 / 42.0);
+		assert_close(stats.consistency, 1.0 - (32.0f32 / 7.0).sqrt() / 5.0);
+	}
+
+	#[test]
+	fn number_stats_edge_cases() {
+		let (numeric, stats) = numeric_stats(vec![3.0], 5);
+		assert_close(numeric.collected_std_dev.unwrap(), 0.0);
+		assert_close(stats.recent_average, 3.0);
+		assert_close(stats.trend, 0.0);
+		assert_close(stats.consistency, 1.0);
+
+		// A steady decline, with fewer matches than the recent window
+		let (_, stats) = numeric_stats(vec![6.0, 4.0, 2.0], 5);
+		assert_close(stats.recent_average, 4.0);
+		assert_close(stats.trend, -2.0);
+		assert_close(stats.consistency, 0.5);
+
+		let (_, stats) = numeric_stats(vec![0.0, 0.0], 1);
+		assert_close(stats.consistency, 1.0);
+		let (_, stats) = numeric_stats(vec![-1.0, 1.0], 1);
+		assert_close(stats.recent_average, 1.0);
+		assert_close(stats.consistency, 0.0);
+	}
+}
@@ -0,0 +1,485 @@
+use log::warn;
+
+use super::{
+	InfoEntryWithSource, MatchAnalysisInfo, MatchAnalysisTeamInfo, MetricSeries, SingleTeamInfo,
+	TeamInfoEntry, TeamInfoGraphic,
+};
+use crate::database::{Database, ImageSize};
+use crate::media;
+use crate::pdf::{self, ImageRef, PdfDocument, PAGE_HEIGHT, PAGE_WIDTH};
+use crate::robot_card::TeamImage;
+
+const MARGIN: f32 = 36.0;
+const CONTENT_WIDTH: f32 = PAGE_WIDTH - MARGIN * 2.0;
+const TEXT_SIZE: f32 = 10.0;
+const LINE_HEIGHT: f32 = 13.0;
+const PHOTO_WIDTH: f32 = 220.0;
+const PHOTO_HEIGHT: f32 = 165.0;
+const CHART_HEIGHT: f32 = 70.0;
+
+const BLACK: [u8; 3] = [0, 0, 0];
+const GREY: [u8; 3] = [110, 110, 110];
+const RULE: [u8; 3] = [200, 200, 200];
+const RED: [u8; 3] = [200, 40, 40];
+const BLUE: [u8; 3] = [40, 80, 200];
+/// Colours for the parts of stacked bars
+const PALETTE: [[u8; 3]; 8] = [
+	[66, 133, 244],
+	[219, 68, 55],
+	[244, 180, 0],
+	[15, 157, 88],
+	[171, 71, 188],
+	[0, 172, 193],
+	[255, 112, 67],
+	[158, 157, 36],
+];
+
+/// Lays out a report from the top of the page down, starting new pages as it fills up
+struct ReportWriter {
+	doc: PdfDocument,
+	y: f32,
+}
+
+impl ReportWriter {
+	fn new() -> Self {
+		let mut doc = PdfDocument::new();
+		doc.add_page();
+		Self { doc, y: MARGIN }
+	}
+
+	/// Start a new page if there isn't enough space left for something this tall
+	fn reserve(&mut self, height: f32) {
+		if self.y + height > PAGE_HEIGHT - MARGIN {
+			self.doc.add_page();
+			self.y = MARGIN;
+		}
+	}
+
+	fn title(&mut self, title: &str, subtitle: &str) {
+		self.doc.text(MARGIN, self.y, 20.0, true, BLACK, title);
+		self.y += 26.0;
+		self.doc
+			.text(MARGIN, self.y, TEXT_SIZE, false, GREY, subtitle);
+		self.y += LINE_HEIGHT + 8.0;
+	}
+
+	fn heading(&mut self, heading: &str, colour: [u8; 3]) {
+		// Keep headings with at least a couple of lines after them
+		self.reserve(18.0 + LINE_HEIGHT * 3.0);
+		self.y += 6.0;
+		self.doc.text(MARGIN, self.y, 13.0, true, colour, heading);
+		self.y += 16.0;
+		self.doc.rect(MARGIN, self.y, CONTENT_WIDTH, 0.75, colour);
+		self.y += 4.0;
+	}
+
+	fn paragraph(&mut self, text: &str, indent: f32) {
+		for line in wrap_text(text, TEXT_SIZE, CONTENT_WIDTH - indent) {
+			self.reserve(LINE_HEIGHT);
+			self.doc
+				.text(MARGIN + indent, self.y, TEXT_SIZE, false, BLACK, &line);
+			self.y += LINE_HEIGHT;
+		}
+	}
+
+	/// A metric name and its value, with the colour the value is shown with in the app
+	fn value_row(&mut self, name: &str, entry: &TeamInfoEntry) {
+		let name_width = CONTENT_WIDTH * 0.45;
+		let value_x = MARGIN + name_width + 14.0;
+		let lines = wrap_text(
+			entry.pit_value.as_deref().unwrap_or(&entry.text),
+			TEXT_SIZE,
+			CONTENT_WIDTH - name_width - 14.0,
+		);
+		self.reserve(LINE_HEIGHT * lines.len() as f32);
+		self.doc.text(
+			MARGIN,
+			self.y,
+			TEXT_SIZE,
+			true,
+			BLACK,
+			&truncate_text(name, TEXT_SIZE, name_width),
+		);
+		self.doc
+			.rect(value_x - 11.0, self.y + 1.0, 7.0, 7.0, entry.colour);
+		for line in lines {
+			self.doc
+				.text(value_x, self.y, TEXT_SIZE, false, BLACK, &line);
+			self.y += LINE_HEIGHT;
+		}
+		self.doc
+			.rect(MARGIN, self.y - 2.0, CONTENT_WIDTH, 0.5, RULE);
+	}
+
+	/// A bar split into parts sized by their values, with a legend underneath
+	fn stacked_bar(&mut self, x: f32, width: f32, total: f32, parts: &[(String, f32)]) {
+		self.reserve(12.0 + LINE_HEIGHT * 2.0);
+		let total = total.max(parts.iter().map(|(_, value)| value.max(0.0)).sum());
+		let mut part_x = x;
+		for (index, (_, value)) in parts.iter().enumerate() {
+			if total <= 0.0 || *value <= 0.0 {
+				continue;
+			}
+			let part_width = width * value / total;
+			self.doc.rect(
+				part_x,
+				self.y,
+				part_width,
+				10.0,
+				PALETTE[index % PALETTE.len()],
+			);
+			part_x += part_width;
+		}
+		self.y += 13.0;
+
+		let mut legend_x = x;
+		for (index, (label, value)) in parts.iter().enumerate() {
+			let label = format!("{label}: {}", format_number(*value));
+			let label_width = pdf::text_width(&label, 8.0) + 18.0;
+			if legend_x + label_width > x + width && legend_x > x {
+				legend_x = x;
+				self.y += 11.0;
+				self.reserve(11.0);
+			}
+			self.doc.rect(
+				legend_x,
+				self.y + 1.0,
+				6.0,
+				6.0,
+				PALETTE[index % PALETTE.len()],
+			);
+			self.doc
+				.text(legend_x + 9.0, self.y, 8.0, false, GREY, &label);
+			legend_x += label_width;
+		}
+		self.y += LINE_HEIGHT;
+	}
+
+	/// A bar for every match, in the order they were played
+	fn series_chart(&mut self, series: &MetricSeries) {
+		let label_height = 10.0;
+		self.reserve(LINE_HEIGHT + CHART_HEIGHT + label_height + 6.0);
+		self.doc
+			.text(MARGIN, self.y, TEXT_SIZE, true, BLACK, &series.name.name);
+		self.y += LINE_HEIGHT;
+
+		let max = series
+			.points
+			.iter()
+			.map(|point| point.value)
+			.fold(0.0f32, f32::max);
+		let axis_width = 30.0;
+		let chart_x = MARGIN + axis_width;
+		let chart_width = CONTENT_WIDTH - axis_width;
+		self.doc
+			.text(MARGIN, self.y, 7.0, false, GREY, &format_number(max));
+		self.doc
+			.text(MARGIN, self.y + CHART_HEIGHT - 7.0, 7.0, false, GREY, "0");
+		let slot = chart_width / series.points.len().max(1) as f32;
+		for (index, point) in series.points.iter().enumerate() {
+			let x = chart_x + slot * index as f32;
+			if max > 0.0 && point.value > 0.0 {
+				let height = CHART_HEIGHT * point.value / max;
+				self.doc.rect(
+					x + slot * 0.15,
+					self.y + CHART_HEIGHT - height,
+					slot * 0.7,
+					height,
+					PALETTE[0],
+				);
+			}
+			let label = &point.match_id;
+			if pdf::text_width(label, 6.0) <= slot {
+				let label_x = x + (slot - pdf::text_width(label, 6.0)) / 2.0;
+				self.doc.text(
+					label_x,
+					self.y + CHART_HEIGHT + 2.0,
+					6.0,
+					false,
+					GREY,
+					label,
+				);
+			}
+		}
+		self.doc
+			.rect(chart_x, self.y + CHART_HEIGHT, chart_width, 0.5, GREY);
+		self.y += CHART_HEIGHT + label_height + 6.0;
+	}
+}
+
+/// A report on a team with every metric, graphs of how they did each match, notes from the
+/// scouts, and a photo of their robot
+pub fn team_report(
+	database: &Database,
+	info: &SingleTeamInfo,
+	photo: Option<&TeamImage>,
+) -> Vec<u8> {
+	let mut writer = ReportWriter::new();
+	let top = writer.y;
+	writer.title(
+		&format!("Team {} - {}", info.team_number, info.team_name),
+		"Scouting report",
+	);
+	if let Some(photo) = photo.and_then(|photo| team_photo(database, &mut writer.doc, photo)) {
+		let (width, height) = writer.doc.image_size(photo);
+		let scale = (PHOTO_WIDTH / width as f32).min(PHOTO_HEIGHT / height as f32);
+		let (width, height) = (width as f32 * scale, height as f32 * scale);
+		writer
+			.doc
+			.image(photo, PAGE_WIDTH - MARGIN - width, top, width, height);
+		writer.y = writer.y.max(top + height + 8.0);
+	}
+
+	// The metrics are grouped by the page of the form they come from
+	let mut pages = Vec::<(&str, Vec<&InfoEntryWithSource>)>::new();
+	for metric in &info.data {
+		if matches!(
+			metric.entry.graphic,
+			Some(
+				TeamInfoGraphic::TeamName(_)
+					| TeamInfoGraphic::MultiText(_)
+					| TeamInfoGraphic::Images(_)
+			)
+		) {
+			continue;
+		}
+		match pages.iter_mut().find(|(page, _)| *page == metric.name.page) {
+			Some((_, metrics)) => metrics.push(metric),
+			None => pages.push((&metric.name.page, vec![metric])),
+		}
+	}
+	for (page, metrics) in pages {
+		writer.heading(page, BLACK);
+		for metric in metrics {
+			writer.value_row(&metric.name.name, &metric.entry);
+			if let Some(TeamInfoGraphic::PieChart(pie_chart)) = &metric.entry.graphic {
+				let options = pie_chart
+					.options
+					.iter()
+					.map(|option| (option.label.clone(), option.value))
+					.collect::<Vec<_>>();
+				writer.stacked_bar(MARGIN, CONTENT_WIDTH, 0.0, &options);
+			}
+		}
+	}
+
+	let series = info
+		.match_series
+		.iter()
+		.filter(|series| !series.points.is_empty())
+		.collect::<Vec<_>>();
+	if !series.is_empty() {
+		writer.heading("Each match", BLACK);
+		for series in series {
+			writer.series_chart(series);
+		}
+	}
+
+	let notes = info
+		.data
+		.iter()
+		.filter_map(|metric| match &metric.entry.graphic {
+			Some(TeamInfoGraphic::MultiText(text)) if !text.strings.is_empty() => {
+				Some((&metric.name.name, &text.strings))
+			}
+			_ => None,
+		})
+		.collect::<Vec<_>>();
+	if !notes.is_empty() {
+		writer.heading("Notes", BLACK);
+		for (name, strings) in notes {
+			writer.reserve(LINE_HEIGHT * 2.0);
+			writer
+				.doc
+				.text(MARGIN, writer.y, TEXT_SIZE, true, BLACK, name);
+			writer.y += LINE_HEIGHT;
+			for string in strings {
+				writer.paragraph(&format!("- {string}"), 8.0);
+			}
+			writer.y += 4.0;
+		}
+	}
+
+	writer.doc.to_bytes()
+}
+
+/// A brief for a match with what each alliance is expected to score and how
+pub fn match_report(info: &MatchAnalysisInfo, title: &str) -> Vec<u8> {
+	let mut writer = ReportWriter::new();
+	writer.title(title, "Match brief");
+	if info.red_teams.is_empty() && info.blue_teams.is_empty() {
+		writer.paragraph("The teams in this match aren't known yet.", 0.0);
+		return writer.doc.to_bytes();
+	}
+
+	let expected_red = info
+		.red_teams
+		.iter()
+		.map(|team| team.expected_score)
+		.sum::<f32>();
+	let expected_blue = info
+		.blue_teams
+		.iter()
+		.map(|team| team.expected_score)
+		.sum::<f32>();
+	let max_team_score = info
+		.red_teams
+		.iter()
+		.chain(info.blue_teams.iter())
+		.map(|team| team.expected_score)
+		.fold(0.0f32, f32::max);
+
+	writer.heading("Expected score", BLACK);
+	let bar_width = CONTENT_WIDTH - 90.0;
+	let max_score = expected_red.max(expected_blue);
+	for (name, score, colour) in [("Red", expected_red, RED), ("Blue", expected_blue, BLUE)] {
+		writer.doc.text(
+			MARGIN,
+			writer.y,
+			TEXT_SIZE,
+			true,
+			colour,
+			&format!("{name}: {}", format_number(score)),
+		);
+		if max_score > 0.0 {
+			writer.doc.rect(
+				MARGIN + 90.0,
+				writer.y,
+				bar_width * score.max(0.0) / max_score,
+				10.0,
+				colour,
+			);
+		}
+		writer.y += LINE_HEIGHT + 2.0;
+	}
+
+	for (name, teams, colour) in [
+		("Red alliance", &info.red_teams, RED),
+		("Blue alliance", &info.blue_teams, BLUE),
+	] {
+		writer.heading(name, colour);
+		for team in teams {
+			alliance_team(&mut writer, team, &info.other_data_names, max_team_score);
+		}
+	}
+
+	writer.doc.to_bytes()
+}
+
+fn alliance_team(
+	writer: &mut ReportWriter,
+	team: &MatchAnalysisTeamInfo,
+	other_data_names: &[String],
+	max_team_score: f32,
+) {
+	writer.reserve(LINE_HEIGHT * 4.0);
+	writer.doc.text(
+		MARGIN,
+		writer.y,
+		12.0,
+		true,
+		BLACK,
+		&format!("{} - {}", team.team_number, team.team_name),
+	);
+	let expected = format!("Expected {}", format_number(team.expected_score));
+	writer.doc.text(
+		PAGE_WIDTH - MARGIN - pdf::text_width(&expected, 12.0) * 1.05,
+		writer.y,
+		12.0,
+		true,
+		BLACK,
+		&expected,
+	);
+	writer.y += 16.0;
+
+	let parts = team
+		.expected_score_parts
+		.iter()
+		.map(|part| (part.name.clone(), part.score))
+		.collect::<Vec<_>>();
+	if !parts.is_empty() {
+		writer.stacked_bar(MARGIN, CONTENT_WIDTH, max_team_score, &parts);
+	}
+	for (name, entry) in other_data_names.iter().zip(team.other_data.iter()) {
+		writer.value_row(name, entry);
+	}
+	writer.y += 8.0;
+}
+
+/// Add a photo of the team's robot to the report
+fn team_photo(database: &Database, doc: &mut PdfDocument, photo: &TeamImage) -> Option<ImageRef> {
+	let image_data = match database.get_image(&photo.image_id, ImageSize::Full, false) {
+		Ok(Some(image_data)) => image_data,
+		Ok(None) => return None,
+		Err(err) => {
+			warn!("Couldn't load image {} for a report: {err}", photo.image_id);
+			return None;
+		}
+	};
+	let image = media::decode_image(&image_data.image_data, &image_data.mime_type)
+		.and_then(|(image, _)| doc.add_image(&image));
+	match image {
+		Ok(image) => Some(image),
+		Err(err) => {
+			warn!("Couldn't add image {} to a report: {err}", photo.image_id);
+			None
+		}
+	}
+}
+
+/// Split text into lines that fit in a width, breaking between words where possible
+fn wrap_text(text: &str, size: f32, width: f32) -> Vec<String> {
+	let mut lines = Vec::new();
+	for paragraph in text.lines() {
+		let mut line = String::new();
+		for word in paragraph.split_whitespace() {
+			let candidate = if line.is_empty() {
+				word.to_string()
+			} else {
+				format!("{line} {word}")
+			};
+			if pdf::text_width(&candidate, size) <= width {
+				line = candidate;
+				continue;
+			}
+			if !line.is_empty() {
+				lines.push(std::mem::take(&mut line));
+			}
+			// Words too long for a whole line are broken anywhere
+			for c in word.chars() {
+				if !line.is_empty() && pdf::text_width(&format!("{line}{c}"), size) > width {
+					lines.push(std::mem::take(&mut line));
+				}
+				line.push(c);
+			}
+		}
+		lines.push(line);
+	}
+	if lines.is_empty() {
+		lines.push(String::new());
+	}
+	lines
+}
+
+/// Shorten text to fit in a width, ending it with `...` if anything was cut off
+fn truncate_text(text: &str, size: f32, width: f32) -> String {
+	if pdf::text_width(text, size) <= width {
+		return text.to_string();
+	}
+	let mut truncated = String::new();
+	for c in text.chars() {
+		if pdf::text_width(&format!("{truncated}{c}..."), size) > width {
+			break;
+		}
+		truncated.push(c);
+	}
+	truncated + "..."
+}
+
+fn format_number(value: f32) -> String {
+	if value.fract().abs() < 0.05 {
+		format!("{value:.0}")
+	} else {
+		format!("{value:.1}")
+	}
+}
@@ -1,31 +1,79 @@
 pub mod data;
 
 use std::collections::HashMap;
-use std::io::Cursor;
 use std::sync::Arc;
 
-use image::{ImageFormat, ImageReader};
-use log::warn;
+use image::{DynamicImage, ImageFormat};
+use log::{info, warn};
 use poem::http::StatusCode;
-use poem_openapi::param::Path;
-use poem_openapi::payload::Json;
-use poem_openapi::OpenApi;
+use poem_openapi::param::{Path, Query};
+use poem_openapi::payload::{Attachment, AttachmentType, Binary, Html, Json, PlainText};
+use poem_openapi::{ApiRequest, Object, OpenApi};
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
 
-use crate::analysis::{self, MatchAnalysisInfo, SingleTeamInfo, TeamInfoList};
+use crate::analysis::{self, MatchAnalysisInfo, PickRecommendation, SingleTeamInfo, TeamInfoList};
 use crate::api::data::{
-	DriverEntryIdData, DriverEntryTimedId, FullEntryData, ImageEntryData, MatchEntryTimedId,
-	PitEntryTimedId,
+	DriverEntryIdData, DriverEntryTimedId, FullEntryData, ImageEdit, ImageEntryData,
+	ImageUploadComplete, ImageUploadStatus, MatchEntryTimedId, PitEntryTimedId,
 };
-use crate::config::match_entry::MatchEntryFields;
+use crate::config::match_entry::{MatchEntryFields, MatchEntryType};
 use crate::config::{ConfigManager, GameConfig, TeamConfig};
-use crate::data_validation::validate_match;
-use crate::database::Database;
+use crate::data_validation::{
+	apply_implications, check_required, validate_match, FieldIssue, IssueAction, ValidationReport,
+};
+use crate::database::{Database, DbError, ImageData, ImageSize, OriginalImage};
 use crate::leaderboard::{self, LeaderboardInfo};
+use crate::manual_event::{self, ManualEvent, ManualEventMode};
+use crate::media;
+use crate::pick_list::PickList;
+use crate::robot_card::{self, RobotCard, TeamGallery};
+use crate::snapshot::{self, DataSnapshot, SnapshotImport};
 use crate::statbotics::StatboticsCache;
+use crate::storage::{self, StorageCleanup, StorageReport};
 use crate::tba::{EventInfo, MatchId, SetMatch, Tba};
+use crate::upstream::HealthReport;
 
 use self::data::{MatchEntryIdData, PitEntryIdData};
 
+/// The body of every error response from the API
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, Object, TS)]
+#[ts(export, export_to = "../../client/src/generated/")]
+pub struct ApiError {
+	/// The HTTP status code
+	pub status: u16,
+	pub message: String,
+}
+
+impl ApiError {
+	pub fn into_response(self) -> poem::Response {
+		poem::Response::builder()
+			.status(StatusCode::from_u16(self.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR))
+			.content_type("application/json")
+			.body(serde_json::to_vec(&self).unwrap_or_default())
+	}
+}
+
+/// A team list or schedule entered by hand
+#[derive(ApiRequest)]
+pub enum EventDataUpload {
+	#[oai(content_type = "text/csv")]
+	Csv(PlainText<String>),
+	/// The JSON from the FRC Events API
+	Json(Json<serde_json::Value>),
+}
+
+/// Only the reports for entries that had problems. Entries that weren't saved are marked in their
+/// report, the rest of the batch is still saved.
+fn reports_with_issues(reports: Vec<ValidationReport>) -> Json<Vec<ValidationReport>> {
+	Json(
+		reports
+			.into_iter()
+			.filter(|report| !report.issues.is_empty())
+			.collect(),
+	)
+}
+
 #[derive(Debug)]
 pub struct Api {
 	config: ConfigManager,
@@ -90,7 +138,7 @@ impl Api {
 			)
 			.map_err(|e| poem::Error::new(e, StatusCode::INTERNAL_SERVER_ERROR))?;
 		let fields = &self.config.get_current_game_config().match_entry_fields;
-		Ok(Json(data.map(|data| validate_match(data, fields))))
+		Ok(Json(data.map(|data| validate_match(data, fields).0)))
 	}
 	/// Get filtered scouting data. All matches that are not specified as known
 	/// or any that are newer than the known timestamp are returned.
@@ -126,27 +174,41 @@ impl Api {
 		match_id: &str,
 		team: &str,
 		data: FullEntryData,
-	) -> poem::Result<()> {
+	) -> poem::Result<ValidationReport> {
 		let server_config = self.config.get_server_config();
 		if data.year != server_config.current_year || data.event != server_config.current_event {
 			warn!(
 				"Ignoring data for invalid year/event; expected {}:{}, got {}:{}",
 				server_config.current_year, server_config.current_event, data.year, data.event
 			);
-			return Ok(());
+			return Ok(ValidationReport::new(
+				Some(match_id),
+				team,
+				vec![FieldIssue {
+					field: "event".to_string(),
+					problem: format!(
+						"Entry is for {}:{}, not the current event",
+						data.year, data.event
+					),
+					action: IssueAction::Rejected,
+				}],
+			));
 		}
 		let fields = &self.config.get_current_game_config().match_entry_fields;
-		let data = validate_match(data, fields);
-		self.database
-			.set_match_entry_data(
-				server_config.current_year,
-				&server_config.current_event,
-				match_id,
-				team,
-				data,
-			)
-			.map_err(|e| poem::Error::new(e, StatusCode::INTERNAL_SERVER_ERROR))?;
-		Ok(())
+		let (data, mut issues) = validate_match(data, fields);
+		issues.extend(
+			self.database
+				.set_match_entry_data(
+					server_config.current_year,
+					&server_config.current_event,
+					match_id,
+					team,
+					data,
+					|merged| check_required(merged, fields),
+				)
+				.map_err(|e| poem::Error::new(e, StatusCode::INTERNAL_SERVER_ERROR))?,
+		);
+		Ok(ValidationReport::new(Some(match_id), team, issues))
 	}
 	/// Set scouting data for a particular match
 	#[oai(path = "/match_entry/data/:match_id/:team", method = "put")]
@@ -155,23 +217,26 @@ impl Api {
 		match_id: Path<String>,
 		team: Path<String>,
 		data: Json<FullEntryData>,
-	) -> poem::Result<()> {
-		self.match_entry_set_data_inner(&match_id, &team, data.0)
+	) -> poem::Result<Json<Vec<ValidationReport>>> {
+		Ok(reports_with_issues(vec![
+			self.match_entry_set_data_inner(&match_id, &team, data.0)?
+		]))
 	}
 	/// Set scouting data for multiple matches
 	#[oai(path = "/match_entry/data/all", method = "put")]
 	pub async fn match_entry_set_multiple(
 		&self,
 		data: Json<Vec<MatchEntryIdData>>,
-	) -> poem::Result<()> {
+	) -> poem::Result<Json<Vec<ValidationReport>>> {
+		let mut reports = Vec::new();
 		for match_entry in data.0 {
-			self.match_entry_set_data_inner(
+			reports.push(self.match_entry_set_data_inner(
 				&match_entry.match_id,
 				&match_entry.team_id,
 				match_entry.data,
-			)?;
+			)?);
 		}
-		Ok(())
+		Ok(reports_with_issues(reports))
 	}
 
 	/// Get the fields to gather from drive team
@@ -196,7 +261,7 @@ impl Api {
 			)
 			.map_err(|e| poem::Error::new(e, StatusCode::INTERNAL_SERVER_ERROR))?;
 		let fields = &self.config.get_current_game_config().driver_entry_fields;
-		Ok(Json(data.map(|data| validate_match(data, fields))))
+		Ok(Json(data.map(|data| validate_match(data, fields).0)))
 	}
 	/// Get filtered driver data. All matches that are not specified as known
 	/// or any that are newer than the known timestamp are returned.
@@ -232,27 +297,41 @@ impl Api {
 		match_id: &str,
 		team: &str,
 		data: FullEntryData,
-	) -> poem::Result<()> {
+	) -> poem::Result<ValidationReport> {
 		let server_config = self.config.get_server_config();
 		if data.year != server_config.current_year || data.event != server_config.current_event {
 			warn!(
 				"Ignoring data for invalid year/event; expected {}:{}, got {}:{}",
 				server_config.current_year, server_config.current_event, data.year, data.event
 			);
-			return Ok(());
+			return Ok(ValidationReport::new(
+				Some(match_id),
+				team,
+				vec![FieldIssue {
+					field: "event".to_string(),
+					problem: format!(
+						"Entry is for {}:{}, not the current event",
+						data.year, data.event
+					),
+					action: IssueAction::Rejected,
+				}],
+			));
 		}
 		let fields = &self.config.get_current_game_config().driver_entry_fields;
-		let data = validate_match(data, fields);
-		self.database
-			.set_driver_entry_data(
-				server_config.current_year,
-				&server_config.current_event,
-				match_id,
-				team,
-				data,
-			)
-			.map_err(|e| poem::Error::new(e, StatusCode::INTERNAL_SERVER_ERROR))?;
-		Ok(())
+		let (data, mut issues) = validate_match(data, fields);
+		issues.extend(
+			self.database
+				.set_driver_entry_data(
+					server_config.current_year,
+					&server_config.current_event,
+					match_id,
+					team,
+					data,
+					|merged| check_required(merged, fields),
+				)
+				.map_err(|e| poem::Error::new(e, StatusCode::INTERNAL_SERVER_ERROR))?,
+		);
+		Ok(ValidationReport::new(Some(match_id), team, issues))
 	}
 	/// Set drive team data for a particular match
 	#[oai(path = "/driver_entry/data/:match_id/:team", method = "put")]
@@ -261,23 +340,26 @@ impl Api {
 		match_id: Path<String>,
 		team: Path<String>,
 		data: Json<FullEntryData>,
-	) -> poem::Result<()> {
-		self.driver_entry_set_data_inner(&match_id, &team, data.0)
+	) -> poem::Result<Json<Vec<ValidationReport>>> {
+		Ok(reports_with_issues(vec![
+			self.driver_entry_set_data_inner(&match_id, &team, data.0)?
+		]))
 	}
 	/// Set drive team data for multiple matches
 	#[oai(path = "/driver_entry/data/all", method = "put")]
 	pub async fn driver_entry_set_multiple(
 		&self,
 		data: Json<Vec<DriverEntryIdData>>,
-	) -> poem::Result<()> {
+	) -> poem::Result<Json<Vec<ValidationReport>>> {
+		let mut reports = Vec::new();
 		for driver_entry in data.0 {
-			self.driver_entry_set_data_inner(
+			reports.push(self.driver_entry_set_data_inner(
 				&driver_entry.match_id,
 				&driver_entry.team_id,
 				driver_entry.data,
-			)?;
+			)?);
 		}
-		Ok(())
+		Ok(reports_with_issues(reports))
 	}
 
 	/// Get the fields to gather per team for pit scouting
@@ -300,7 +382,7 @@ impl Api {
 			)
 			.map_err(|e| poem::Error::new(e, StatusCode::INTERNAL_SERVER_ERROR))?;
 		let fields = &self.config.get_current_game_config().pit_entry_fields;
-		Ok(Json(data.map(|data| validate_match(data, fields))))
+		Ok(Json(data.map(|data| validate_match(data, fields).0)))
 	}
 	/// Get filtered driver data. All matches that are not specified as known
 	/// or any that are newer than the known timestamp are returned.
@@ -338,26 +420,44 @@ impl Api {
 			.collect::<Vec<_>>();
 		Ok(Json(data))
 	}
-	fn pit_entry_set_data_inner(&self, team: &str, data: FullEntryData) -> poem::Result<()> {
+	fn pit_entry_set_data_inner(
+		&self,
+		team: &str,
+		data: FullEntryData,
+	) -> poem::Result<ValidationReport> {
 		let server_config = self.config.get_server_config();
 		if data.year != server_config.current_year || data.event != server_config.current_event {
 			warn!(
 				"Ignoring data for invalid year/event; expected {}:{}, got {}:{}",
 				server_config.current_year, server_config.current_event, data.year, data.event
 			);
-			return Ok(());
+			return Ok(ValidationReport::new(
+				None,
+				team,
+				vec![FieldIssue {
+					field: "event".to_string(),
+					problem: format!(
+						"Entry is for {}:{}, not the current event",
+						data.year, data.event
+					),
+					action: IssueAction::Rejected,
+				}],
+			));
 		}
 		let fields = &self.config.get_current_game_config().pit_entry_fields;
-		let data = validate_match(data, fields);
-		self.database
-			.set_pit_entry_data(
-				server_config.current_year,
-				&server_config.current_event,
-				team,
-				data,
-			)
-			.map_err(|e| poem::Error::new(e, StatusCode::INTERNAL_SERVER_ERROR))?;
-		Ok(())
+		let (data, mut issues) = validate_match(data, fields);
+		issues.extend(
+			self.database
+				.set_pit_entry_data(
+					server_config.current_year,
+					&server_config.current_event,
+					team,
+					data,
+					|merged| check_required(merged, fields),
+				)
+				.map_err(|e| poem::Error::new(e, StatusCode::INTERNAL_SERVER_ERROR))?,
+		);
+		Ok(ValidationReport::new(None, team, issues))
 	}
 	/// Set pit data for a particular team
 	#[oai(path = "/pit_entry/data/:team", method = "put")]
@@ -365,44 +465,344 @@ impl Api {
 		&self,
 		team: Path<String>,
 		data: Json<FullEntryData>,
-	) -> poem::Result<()> {
-		self.pit_entry_set_data_inner(&team, data.0)
+	) -> poem::Result<Json<Vec<ValidationReport>>> {
+		Ok(reports_with_issues(vec![
+			self.pit_entry_set_data_inner(&team, data.0)?
+		]))
 	}
 	/// Set pit data for multiple teams
 	#[oai(path = "/pit_entry/data/all", method = "put")]
 	pub async fn pit_entry_set_multiple(
 		&self,
 		data: Json<Vec<PitEntryIdData>>,
-	) -> poem::Result<()> {
+	) -> poem::Result<Json<Vec<ValidationReport>>> {
+		let mut reports = Vec::new();
 		for match_entry in data.0 {
-			self.pit_entry_set_data_inner(&match_entry.team_id, match_entry.data)?;
+			reports.push(self.pit_entry_set_data_inner(&match_entry.team_id, match_entry.data)?);
 		}
-		Ok(())
+		Ok(reports_with_issues(reports))
 	}
 
-	/// Saves a batch of images fro mthe client
+	/// Find entries where values contradict each other, e.g. a high climb without a low climb
+	#[oai(path = "/data_quality", method = "get")]
+	pub async fn data_quality(&self) -> Json<Vec<ValidationReport>> {
+		let server_config = self.config.get_server_config();
+		let game_config = self.config.get_current_game_config();
+		let mut reports = Vec::new();
+		for mut match_entry in self
+			.database
+			.get_all_match_entries(server_config.current_year, &server_config.current_event)
+		{
+			let issues = apply_implications(&mut match_entry.data, &game_config.match_entry_fields);
+			reports.push(ValidationReport::new(
+				Some(&match_entry.match_id),
+				&match_entry.team_id,
+				issues,
+			));
+		}
+		for mut driver_entry in self
+			.database
+			.get_all_driver_entries(server_config.current_year, &server_config.current_event)
+		{
+			let issues =
+				apply_implications(&mut driver_entry.data, &game_config.driver_entry_fields);
+			reports.push(ValidationReport::new(
+				Some(&driver_entry.match_id),
+				&driver_entry.team_id,
+				issues,
+			));
+		}
+		for (team, mut data) in self
+			.database
+			.get_all_pit_entries(server_config.current_year, &server_config.current_event)
+		{
+			let issues = apply_implications(&mut data, &game_config.pit_entry_fields);
+			reports.push(ValidationReport::new(None, &team, issues));
+		}
+		reports.retain(|report| !report.issues.is_empty());
+		Json(reports)
+	}
+
+	/// Saves a batch of images fro mthe client. Videos are too big to send this way, they have to
+	/// be sent as a chunked upload.
 	#[oai(path = "/images", method = "put")]
 	pub async fn save_images(&self, data: Json<Vec<ImageEntryData>>) -> poem::Result<()> {
 		for image_data in data.0 {
-			let mut image_reader = ImageReader::new(Cursor::new(image_data.image_data));
-			let image_result = match ImageFormat::from_mime_type(image_data.image_mime) {
-				Some(image_format) => {
-					image_reader.set_format(image_format);
-					image_reader.decode()
-				}
-				None => image_reader
-					.with_guessed_format()
-					.map_err(|err| poem::Error::new(err, StatusCode::BAD_REQUEST))?
-					.decode(),
-			};
-			let image =
-				image_result.map_err(|err| poem::Error::new(err, StatusCode::BAD_REQUEST))?;
+			if media::is_video(&image_data.image_mime) {
+				return Err(poem::Error::from_string(
+					format!(
+						"'{}' is a video, videos have to be sent to /images/uploads",
+						image_data.image_id
+					),
+					StatusCode::BAD_REQUEST,
+				));
+			}
+			self.save_media(image_data, None).await?;
+		}
+		Ok(())
+	}
 
+	/// Gets which of a list of image and video ids the server already has, so they don't need to
+	/// be uploaded again
+	#[oai(path = "/images/existing", method = "post")]
+	pub async fn existing_images(
+		&self,
+		image_ids: Json<Vec<String>>,
+	) -> poem::Result<Json<Vec<String>>> {
+		let mut existing = Vec::new();
+		for image_id in image_ids.0 {
+			if self
+				.database
+				.has_media(&image_id)
+				.map_err(|e| poem::Error::new(e, StatusCode::INTERNAL_SERVER_ERROR))?
+			{
+				existing.push(image_id);
+			}
+		}
+		Ok(Json(existing))
+	}
+
+	/// Gets how much of a chunked upload the server has, to resume it after losing connection
+	#[oai(path = "/images/uploads/:image_id", method = "get")]
+	pub async fn get_image_upload(
+		&self,
+		image_id: Path<String>,
+	) -> poem::Result<Json<ImageUploadStatus>> {
+		let upload = self
+			.database
+			.get_upload_info(&image_id)
+			.map_err(|e| poem::Error::new(e, StatusCode::INTERNAL_SERVER_ERROR))?;
+		Ok(Json(ImageUploadStatus {
+			received: upload.map(|upload| upload.received).unwrap_or_default(),
+		}))
+	}
+
+	/// Adds the next chunk of a large image or video, `offset` has to be the number of bytes the
+	/// server has received so far
+	#[oai(path = "/images/uploads/:image_id", method = "put")]
+	pub async fn append_image_upload(
+		&self,
+		image_id: Path<String>,
+		offset: Query<u64>,
+		data: Binary<Vec<u8>>,
+	) -> poem::Result<Json<ImageUploadStatus>> {
+		let image_config = &self.config.get_server_config().images;
+		let max_mb = image_config
+			.max_image_upload_mb
+			.max(image_config.max_video_upload_mb);
+		if *offset + data.len() as u64 > max_mb as u64 * 1024 * 1024 {
+			return Err(poem::Error::from_string(
+				format!("'{}' is bigger than the {max_mb} MB limit", *image_id),
+				StatusCode::PAYLOAD_TOO_LARGE,
+			));
+		}
+
+		let upload = self
+			.database
+			.append_upload(&image_id, *offset, &data)
+			.map_err(|e| match e {
+				DbError::UploadOffset { .. } => poem::Error::new(e, StatusCode::CONFLICT),
+				e => poem::Error::new(e, StatusCode::INTERNAL_SERVER_ERROR),
+			})?;
+		Ok(Json(ImageUploadStatus {
+			received: upload.received,
+		}))
+	}
+
+	/// Finishes a chunked upload, saving it the same as an image sent to `PUT /images`
+	#[oai(path = "/images/uploads/:image_id/complete", method = "post")]
+	pub async fn complete_image_upload(
+		&self,
+		image_id: Path<String>,
+		details: Json<ImageUploadComplete>,
+	) -> poem::Result<()> {
+		let Some(image_data) = self.database.get_upload(&image_id).map_err(|e| match e {
+			DbError::UploadIncomplete => poem::Error::new(e, StatusCode::BAD_REQUEST),
+			e => poem::Error::new(e, StatusCode::INTERNAL_SERVER_ERROR),
+		})?
+		else {
+			return Err(poem::Error::from_string(
+				format!("There is no upload for '{}'", *image_id),
+				StatusCode::NOT_FOUND,
+			));
+		};
+		if !details
+			.sha256
+			.eq_ignore_ascii_case(&media::content_hash(&image_data))
+		{
 			self.database
-				.write_image(&image, &image_data.image_id)
+				.remove_upload(&image_id)
 				.map_err(|e| poem::Error::new(e, StatusCode::INTERNAL_SERVER_ERROR))?;
+			return Err(poem::Error::from_string(
+				format!(
+					"The upload for '{}' was corrupted, it has to be sent again",
+					*image_id
+				),
+				StatusCode::BAD_REQUEST,
+			));
 		}
-		Ok(())
+		// Only removed once saved, so it can be completed again if saving fails
+		self.save_media(
+			ImageEntryData {
+				image_id: image_id.0.clone(),
+				image_mime: details.0.image_mime,
+				image_data,
+			},
+			details.0.field.as_deref(),
+		)
+		.await?;
+		self.database
+			.remove_upload(&image_id)
+			.map_err(|e| poem::Error::new(e, StatusCode::INTERNAL_SERVER_ERROR))
+	}
+
+	/// Rotates and/or crops a stored image, on top of any earlier edits
+	#[oai(path = "/images/:image_id/edits", method = "post")]
+	pub async fn edit_image(
+		&self,
+		image_id: Path<String>,
+		edit: Json<ImageEdit>,
+	) -> poem::Result<()> {
+		if edit.crop.as_ref().is_some_and(|crop| !crop.is_valid()) {
+			return Err(poem::Error::from_string(
+				"The crop has to be inside of the image",
+				StatusCode::BAD_REQUEST,
+			));
+		}
+		let mut original = self.get_original_image(&image_id)?;
+		original.edits.push(edit.0);
+		self.restore_image(&image_id, &original)
+	}
+
+	/// Undoes all of the edits to a stored image
+	#[oai(path = "/images/:image_id/edits", method = "delete")]
+	pub async fn reset_image_edits(&self, image_id: Path<String>) -> poem::Result<()> {
+		let mut original = self.get_original_image(&image_id)?;
+		original.edits.clear();
+		self.restore_image(&image_id, &original)
+	}
+
+	/// Gets how much space images and videos are using for each event
+	#[oai(path = "/storage", method = "get")]
+	pub async fn get_storage(&self) -> poem::Result<Json<StorageReport>> {
+		storage::get_storage_report(&self.database)
+			.map(Json)
+			.map_err(|e| poem::Error::new(e, StatusCode::INTERNAL_SERVER_ERROR))
+	}
+
+	/// Removes images and videos that aren't attached to any entries
+	#[oai(path = "/storage/cleanup", method = "post")]
+	pub async fn clean_up_storage(&self) -> poem::Result<Json<StorageCleanup>> {
+		storage::remove_unused_media(&self.database)
+			.map(Json)
+			.map_err(|e| poem::Error::new(e, StatusCode::INTERNAL_SERVER_ERROR))
+	}
+
+	/// Downloads everything saved from TBA and Statbotics, to import on a server that will be used
+	/// without internet
+	#[oai(path = "/snapshot", method = "get")]
+	pub async fn export_snapshot(&self) -> poem::Result<Attachment<Vec<u8>>> {
+		let snapshot = snapshot::export_snapshot(&self.database)
+			.map_err(|e| poem::Error::new(e, StatusCode::INTERNAL_SERVER_ERROR))?;
+		let data = serde_json::to_vec(&snapshot)
+			.map_err(|e| poem::Error::new(e, StatusCode::INTERNAL_SERVER_ERROR))?;
+		Ok(Attachment::new(data)
+			.attachment_type(AttachmentType::Attachment)
+			.filename(format!(
+				"infiniscouter-snapshot-{}.json",
+				self.config.get_server_config().current_event
+			)))
+	}
+
+	/// Imports a snapshot downloaded from another server, keeping anything newer this server has
+	/// already loaded
+	#[oai(path = "/snapshot", method = "post")]
+	pub async fn import_snapshot(
+		&self,
+		data: Binary<Vec<u8>>,
+	) -> poem::Result<Json<SnapshotImport>> {
+		let snapshot = serde_json::from_slice::<DataSnapshot>(&data)
+			.map_err(|e| poem::Error::new(e, StatusCode::BAD_REQUEST))?;
+		let import = snapshot::import_snapshot(&self.database, snapshot)
+			.map_err(|e| poem::Error::new(e, StatusCode::INTERNAL_SERVER_ERROR))?;
+		self.tba.clear_cache().await;
+		self.statbotics.clear_cache().await;
+		Ok(Json(import))
+	}
+
+	/// Gets whether TBA, Statbotics, and the FRC Events API can be reached, and whether saved data is being used instead
+	#[oai(path = "/health", method = "get")]
+	pub async fn health(&self) -> Json<HealthReport> {
+		let server_config = self.config.get_server_config();
+		let scouting_only = self
+			.tba
+			.get_loaded_event(server_config.current_year, &server_config.current_event)
+			.await
+			.is_none();
+		let mut upstreams = vec![self.tba.health(), self.statbotics.health()];
+		upstreams.extend(self.tba.frc_events_health());
+		Json(HealthReport {
+			upstreams,
+			scouting_only,
+		})
+	}
+
+	/// Gets the teams and matches entered by hand for the current event
+	#[oai(path = "/event/manual", method = "get")]
+	pub async fn manual_event(&self) -> poem::Result<Json<ManualEvent>> {
+		self.get_manual_event().map(Json)
+	}
+	/// Replaces the team list entered by hand. CSV has a team number and optionally a name on
+	/// each line.
+	#[oai(path = "/event/manual/teams", method = "put")]
+	pub async fn manual_event_teams(
+		&self,
+		upload: EventDataUpload,
+	) -> poem::Result<Json<ManualEvent>> {
+		let teams = match upload {
+			EventDataUpload::Csv(csv) => manual_event::parse_team_list_csv(&csv),
+			EventDataUpload::Json(json) => manual_event::parse_team_list_json(json.0),
+		}
+		.map_err(|e| poem::Error::from_string(e, StatusCode::BAD_REQUEST))?;
+		let mut manual_event = self.get_manual_event()?;
+		manual_event.teams = teams;
+		self.set_manual_event(manual_event).map(Json)
+	}
+	/// Replaces the schedule entered by hand. CSV has a qualification match number, three red
+	/// teams, three blue teams, and optionally a start time on each line. Start times that are
+	/// local to the event need `utc_offset`, the event's offset from UTC in minutes.
+	#[oai(path = "/event/manual/schedule", method = "put")]
+	pub async fn manual_event_schedule(
+		&self,
+		upload: EventDataUpload,
+		utc_offset: Query<Option<i32>>,
+	) -> poem::Result<Json<ManualEvent>> {
+		let matches = match upload {
+			EventDataUpload::Csv(csv) => manual_event::parse_schedule_csv(&csv, *utc_offset),
+			EventDataUpload::Json(json) => manual_event::parse_schedule_json(json.0, *utc_offset),
+		}
+		.map_err(|e| poem::Error::from_string(e, StatusCode::BAD_REQUEST))?;
+		let mut manual_event = self.get_manual_event()?;
+		manual_event.matches = matches;
+		self.set_manual_event(manual_event).map(Json)
+	}
+	/// Sets whether the teams and matches entered by hand are added to TBA's or replace them
+	#[oai(path = "/event/manual/mode", method = "put")]
+	pub async fn manual_event_mode(
+		&self,
+		mode: Json<ManualEventMode>,
+	) -> poem::Result<Json<ManualEvent>> {
+		let mut manual_event = self.get_manual_event()?;
+		manual_event.mode = mode.0;
+		self.set_manual_event(manual_event).map(Json)
+	}
+	/// Removes the teams and matches entered by hand, so only TBA's are used
+	#[oai(path = "/event/manual", method = "delete")]
+	pub async fn manual_event_remove(&self) -> poem::Result<()> {
+		let server_config = self.config.get_server_config();
+		self.database
+			.remove_manual_event(server_config.current_year, &server_config.current_event)
+			.map_err(|e| poem::Error::new(e, StatusCode::INTERNAL_SERVER_ERROR))
 	}
 
 	/// Get a list of all matches for the current event (as well as any teams involved)
@@ -451,31 +851,7 @@ impl Api {
 		num: Path<u32>,
 		set: Path<u32>,
 	) -> poem::Result<Json<MatchAnalysisInfo>> {
-		let match_id = match match_type.as_str() {
-			"practice" => MatchId::Practice(SetMatch {
-				set: *set,
-				num: *num,
-			}),
-			"qualification" => MatchId::Qualification(SetMatch {
-				set: *set,
-				num: *num,
-			}),
-			"quarterfinal" => MatchId::Quarterfinal(SetMatch {
-				set: *set,
-				num: *num,
-			}),
-			"semifinal" => MatchId::Semifinal(SetMatch {
-				set: *set,
-				num: *num,
-			}),
-			"final" => MatchId::Final(SetMatch {
-				set: *set,
-				num: *num,
-			}),
-			_ => {
-				return Err(poem::Error::from_status(StatusCode::BAD_REQUEST));
-			}
-		};
+		let match_id = parse_match_id(&match_type, *num, *set)?;
 		Ok(Json(
 			analysis::get_match_analysis(
 				&self.tba,
@@ -488,6 +864,33 @@ impl Api {
 			.await,
 		))
 	}
+	/// Gets every photo and video of a team, along with their avatar
+	#[oai(path = "/team/:team/images", method = "get")]
+	pub async fn team_images(&self, team: Path<u32>) -> Json<TeamGallery> {
+		let server_config = self.config.get_server_config();
+		let tba_data = self
+			.tba
+			.get_event(server_config.current_year, &server_config.current_event)
+			.await;
+		Json(robot_card::get_team_gallery(
+			&self.database,
+			server_config,
+			self.config.get_current_game_config(),
+			tba_data.as_ref(),
+			team.0,
+		))
+	}
+	/// Gets a summary of a robot for the drive team
+	#[oai(path = "/team/:team/card", method = "get")]
+	pub async fn team_card(&self, team: Path<u32>) -> Json<RobotCard> {
+		Json(self.robot_card(team.0).await)
+	}
+	/// Gets the summary of a robot as a page to print
+	#[oai(path = "/team/:team/card/print", method = "get")]
+	pub async fn team_card_print(&self, team: Path<u32>) -> Html<String> {
+		let card = self.robot_card(team.0).await;
+		Html(robot_card::robot_card_html(&card))
+	}
 	#[oai(path = "/leaderboard", method = "get")]
 	pub async fn get_leaderboard(&self) -> Json<LeaderboardInfo> {
 		Json(leaderboard::get_leaderboard(
@@ -495,4 +898,287 @@ impl Api {
 			self.config.get_server_config(),
 		))
 	}
+
+	/// Get the pick list for alliance selection at the current event
+	#[oai(path = "/pick_list", method = "get")]
+	pub async fn pick_list(&self) -> poem::Result<Json<PickList>> {
+		let pick_list = self
+			.database
+			.get_pick_list(
+				self.config.get_server_config().current_year,
+				&self.config.get_server_config().current_event,
+			)
+			.map_err(|e| poem::Error::new(e, StatusCode::INTERNAL_SERVER_ERROR))?;
+		Ok(Json(pick_list))
+	}
+	/// Apply changes to the pick list, returning the updated pick list. Only the teams that
+	/// changed need to be sent, and changes older than what the server has are ignored.
+	#[oai(path = "/pick_list", method = "put")]
+	pub async fn pick_list_update(&self, data: Json<PickList>) -> poem::Result<Json<PickList>> {
+		let pick_list = self
+			.database
+			.update_pick_list(
+				self.config.get_server_config().current_year,
+				&self.config.get_server_config().current_event,
+				data.0,
+			)
+			.map_err(|e| poem::Error::new(e, StatusCode::INTERNAL_SERVER_ERROR))?;
+		Ok(Json(pick_list))
+	}
+	/// Get the teams that are still available to pick, best recommendation first
+	#[oai(path = "/pick_list/recommendations", method = "get")]
+	pub async fn pick_list_recommendations(&self) -> poem::Result<Json<Vec<PickRecommendation>>> {
+		let pick_list = self
+			.database
+			.get_pick_list(
+				self.config.get_server_config().current_year,
+				&self.config.get_server_config().current_event,
+			)
+			.map_err(|e| poem::Error::new(e, StatusCode::INTERNAL_SERVER_ERROR))?;
+		Ok(Json(
+			analysis::get_pick_recommendations(
+				&self.tba,
+				&self.statbotics,
+				&self.database,
+				self.config.get_server_config(),
+				self.config.get_current_game_config(),
+				&pick_list,
+			)
+			.await,
+		))
+	}
+}
+
+fn check_upload_size(upload: &ImageEntryData, max_mb: u32) -> poem::Result<()> {
+	if upload.image_data.len() as u64 > max_mb as u64 * 1024 * 1024 {
+		Err(poem::Error::from_string(
+			format!("'{}' is bigger than the {max_mb} MB limit", upload.image_id),
+			StatusCode::PAYLOAD_TOO_LARGE,
+		))
+	} else {
+		Ok(())
+	}
+}
+
+/// The match a match type name from a URL and its numbers refer to
+pub fn parse_match_id(match_type: &str, num: u32, set: u32) -> poem::Result<MatchId> {
+	let set_match = SetMatch { set, num };
+	match match_type {
+		"practice" => Ok(MatchId::Practice(set_match)),
+		"qualification" => Ok(MatchId::Qualification(set_match)),
+		"quarterfinal" => Ok(MatchId::Quarterfinal(set_match)),
+		"semifinal" => Ok(MatchId::Semifinal(set_match)),
+		"final" => Ok(MatchId::Final(set_match)),
+		_ => Err(poem::Error::from_status(StatusCode::BAD_REQUEST)),
+	}
+}
+
+impl Api {
+	fn get_manual_event(&self) -> poem::Result<ManualEvent> {
+		let server_config = self.config.get_server_config();
+		self.database
+			.get_manual_event(server_config.current_year, &server_config.current_event)
+			.map(Option::unwrap_or_default)
+			.map_err(|e| poem::Error::new(e, StatusCode::INTERNAL_SERVER_ERROR))
+	}
+
+	fn set_manual_event(&self, manual_event: ManualEvent) -> poem::Result<ManualEvent> {
+		let server_config = self.config.get_server_config();
+		self.database
+			.set_manual_event(
+				server_config.current_year,
+				&server_config.current_event,
+				&manual_event,
+			)
+			.map_err(|e| poem::Error::new(e, StatusCode::INTERNAL_SERVER_ERROR))?;
+		info!(
+			"Saved {} teams and {} matches entered by hand",
+			manual_event.teams.len(),
+			manual_event.matches.len()
+		);
+		Ok(manual_event)
+	}
+
+	async fn robot_card(&self, team: u32) -> RobotCard {
+		let server_config = self.config.get_server_config();
+		let tba_data = analysis::get_event_info(&self.tba, &self.database, server_config).await;
+		let statbotics_team = self.statbotics.get(team).await;
+		robot_card::get_robot_card(
+			&self.database,
+			server_config,
+			self.config.get_current_game_config(),
+			&tba_data,
+			statbotics_team.as_deref(),
+			team,
+		)
+	}
+
+	fn get_original_image(&self, image_id: &str) -> poem::Result<OriginalImage> {
+		if let Some(original) = self
+			.database
+			.get_original_image(image_id)
+			.map_err(|e| poem::Error::new(e, StatusCode::INTERNAL_SERVER_ERROR))?
+		{
+			return Ok(original);
+		}
+		// Images uploaded before originals were kept only have their full version
+		match self
+			.database
+			.get_image(image_id, ImageSize::Full, false)
+			.map_err(|e| poem::Error::new(e, StatusCode::INTERNAL_SERVER_ERROR))?
+		{
+			Some(image) => Ok(OriginalImage {
+				image,
+				edits: Vec::new(),
+			}),
+			None => Err(poem::Error::from_string(
+				format!("There is no image '{image_id}' that can be edited"),
+				StatusCode::NOT_FOUND,
+			)),
+		}
+	}
+
+	/// Decode the original upload again and store it with its edits
+	fn restore_image(&self, image_id: &str, original: &OriginalImage) -> poem::Result<()> {
+		let (image, format) =
+			media::decode_image(&original.image.image_data, &original.image.mime_type)
+				.map_err(|e| poem::Error::new(e, StatusCode::INTERNAL_SERVER_ERROR))?;
+		self.store_image(image_id, image, format, original)
+	}
+
+	fn store_image(
+		&self,
+		image_id: &str,
+		image: DynamicImage,
+		format: ImageFormat,
+		original: &OriginalImage,
+	) -> poem::Result<()> {
+		let image = original.edits.iter().fold(image, media::apply_edit);
+		let stored_format = media::stored_format(format, &image);
+		let unchanged = (original.edits.is_empty() && stored_format == format)
+			.then_some(original.image.image_data.as_slice());
+		self.database
+			.write_image(
+				&image,
+				stored_format,
+				unchanged,
+				image_id,
+				&self.config.get_server_config().images,
+			)
+			.map_err(|e| poem::Error::new(e, StatusCode::INTERNAL_SERVER_ERROR))?;
+		self.database
+			.write_original_image(image_id, original)
+			.map_err(|e| poem::Error::new(e, StatusCode::INTERNAL_SERVER_ERROR))
+	}
+
+	/// Saves an image or video. Videos need the id of the image entry they are for.
+	async fn save_media(&self, upload: ImageEntryData, field: Option<&str>) -> poem::Result<()> {
+		let is_video = media::is_video(&upload.image_mime);
+		if is_video {
+			self.check_video_allowed(&upload.image_id, field)?;
+		}
+		let hash = media::content_hash(&upload.image_data);
+		let saved_hash = self
+			.database
+			.get_media_hash(&upload.image_id)
+			.map_err(|e| poem::Error::new(e, StatusCode::INTERNAL_SERVER_ERROR))?;
+		if saved_hash.as_ref() == Some(&hash) {
+			info!("Skipping upload of '{}' (already saved)", upload.image_id);
+			return Ok(());
+		}
+		// The same photo is often attached to several entries, or saved again under a new id
+		let existing_id = self
+			.database
+			.get_media_by_hash(&hash)
+			.map_err(|e| poem::Error::new(e, StatusCode::INTERNAL_SERVER_ERROR))?;
+		if let Some(existing_id) = existing_id {
+			if self
+				.database
+				.has_media(&existing_id)
+				.map_err(|e| poem::Error::new(e, StatusCode::INTERNAL_SERVER_ERROR))?
+				&& self
+					.database
+					.copy_media(&existing_id, &upload.image_id)
+					.map_err(|e| poem::Error::new(e, StatusCode::INTERNAL_SERVER_ERROR))?
+			{
+				info!(
+					"Copied '{existing_id}' for upload of '{}' (same content)",
+					upload.image_id
+				);
+				return Ok(());
+			}
+		}
+
+		let image_id = upload.image_id.clone();
+		if is_video {
+			self.save_video(upload).await?;
+		} else {
+			self.save_image(upload)?;
+		}
+		self.database
+			.set_media_hash(&image_id, &hash)
+			.map_err(|e| poem::Error::new(e, StatusCode::INTERNAL_SERVER_ERROR))
+	}
+
+	fn save_image(&self, image_data: ImageEntryData) -> poem::Result<()> {
+		check_upload_size(
+			&image_data,
+			self.config.get_server_config().images.max_image_upload_mb,
+		)?;
+		let (image, format) = media::decode_image(&image_data.image_data, &image_data.image_mime)
+			.map_err(|err| poem::Error::new(err, StatusCode::BAD_REQUEST))?;
+		let original = OriginalImage {
+			image: ImageData {
+				mime_type: format.to_mime_type().to_string(),
+				width: image.width(),
+				height: image.height(),
+				image_data: image_data.image_data,
+			},
+			edits: Vec::new(),
+		};
+		self.store_image(&image_data.image_id, image, format, &original)
+	}
+
+	fn check_video_allowed(&self, video_id: &str, field: Option<&str>) -> poem::Result<()> {
+		let Some(field) = field else {
+			return Err(poem::Error::from_string(
+				format!("'{video_id}' is a video but isn't for an entry"),
+				StatusCode::BAD_REQUEST,
+			));
+		};
+		let game_config = self.config.get_current_game_config();
+		let allow_video = [
+			&game_config.match_entry_fields,
+			&game_config.driver_entry_fields,
+			&game_config.pit_entry_fields,
+		]
+		.iter()
+		.filter_map(|fields| fields.entries.get(field))
+		.any(|entry| matches!(&entry.entry, MatchEntryType::Image(image) if image.allow_video));
+		if !allow_video {
+			return Err(poem::Error::from_string(
+				format!("Videos aren't allowed for '{field}'"),
+				StatusCode::BAD_REQUEST,
+			));
+		}
+		Ok(())
+	}
+
+	async fn save_video(&self, video_data: ImageEntryData) -> poem::Result<()> {
+		check_upload_size(
+			&video_data,
+			self.config.get_server_config().images.max_video_upload_mb,
+		)?;
+
+		let thumbnail = media::video_thumbnail(&video_data.image_data).await;
+		self.database
+			.write_video(
+				&video_data.image_data,
+				&video_data.image_mime,
+				&thumbnail,
+				&video_data.image_id,
+				&self.config.get_server_config().images,
+			)
+			.map_err(|e| poem::Error::new(e, StatusCode::INTERNAL_SERVER_ERROR))
+	}
 }
@@ -122,6 +122,16 @@ pub enum MatchEntryValue {
 	Bool(MatchBoolEntry),
 	/// A metric that represents an amount of things
 	Counter(CounterEntry),
+	/// An entry that counts attempts, and how many of those succeeded
+	AttemptCounter(AttemptCounterEntry),
+	/// An entry that records when each action happened during a match
+	EventLog(EventLogEntry),
+	/// An entry that records positions on the field image
+	FieldPosition(FieldPositionEntry),
+	/// An entry that represents a rating on a scale
+	Rating(RatingEntry),
+	/// An entry that represents any number of a selection of options
+	MultiSelect(MultiSelectEntry),
 	/// A text entry field, either single line or multi lined
 	TextEntry(TextFieldEntry),
 	/// An picture field, for example for pit scouting robot pictures
@@ -137,6 +147,11 @@ impl MatchEntryValue {
 			| MatchEntryValue::Enum(MatchEnumEntry { scout, .. })
 			| MatchEntryValue::Bool(MatchBoolEntry { scout, .. })
 			| MatchEntryValue::Counter(CounterEntry { scout, .. })
+			| MatchEntryValue::AttemptCounter(AttemptCounterEntry { scout, .. })
+			| MatchEntryValue::EventLog(EventLogEntry { scout, .. })
+			| MatchEntryValue::FieldPosition(FieldPositionEntry { scout, .. })
+			| MatchEntryValue::Rating(RatingEntry { scout, .. })
+			| MatchEntryValue::MultiSelect(MultiSelectEntry { scout, .. })
 			| MatchEntryValue::TextEntry(TextFieldEntry { scout, .. })
 			| MatchEntryValue::Image(ImageEntry { scout, .. })
 			| MatchEntryValue::Timer(MatchTimerEntry { scout, .. }) => scout,
@@ -149,12 +164,32 @@ impl MatchEntryValue {
 			| MatchEntryValue::Enum(MatchEnumEntry { timestamp_ms, .. })
 			| MatchEntryValue::Bool(MatchBoolEntry { timestamp_ms, .. })
 			| MatchEntryValue::Counter(CounterEntry { timestamp_ms, .. })
+			| MatchEntryValue::AttemptCounter(AttemptCounterEntry { timestamp_ms, .. })
+			| MatchEntryValue::EventLog(EventLogEntry { timestamp_ms, .. })
+			| MatchEntryValue::FieldPosition(FieldPositionEntry { timestamp_ms, .. })
+			| MatchEntryValue::Rating(RatingEntry { timestamp_ms, .. })
+			| MatchEntryValue::MultiSelect(MultiSelectEntry { timestamp_ms, .. })
 			| MatchEntryValue::TextEntry(TextFieldEntry { timestamp_ms, .. })
 			| MatchEntryValue::Image(ImageEntry { timestamp_ms, .. })
 			| MatchEntryValue::Timer(MatchTimerEntry { timestamp_ms, .. }) => *timestamp_ms,
 		}
 	}
 
+	/// The label of the option chosen for an enum-like value, as used in pie charts, formula tables
+	/// and conditions
+	pub fn option_label(&self) -> Option<String> {
+		match self {
+			MatchEntryValue::Enum(en) => Some(en.value.to_string()),
+			MatchEntryValue::Ability(ab) => Some(format!("{:?}", ab.value)),
+			MatchEntryValue::Bool(bl) => Some(if bl.value {
+				"Yes".to_string()
+			} else {
+				"No".to_string()
+			}),
+			_ => None,
+		}
+	}
+
 	pub fn is_different(&self, other: &MatchEntryValue) -> bool {
 		match (self, other) {
 			(
@@ -173,6 +208,38 @@ impl MatchEntryValue {
 				MatchEntryValue::Counter(CounterEntry { count: value1, .. }),
 				MatchEntryValue::Counter(CounterEntry { count: value2, .. }),
 			) => value1 != value2,
+			(
+				MatchEntryValue::AttemptCounter(AttemptCounterEntry {
+					attempts: attempts1,
+					successes: successes1,
+					..
+				}),
+				MatchEntryValue::AttemptCounter(AttemptCounterEntry {
+					attempts: attempts2,
+					successes: successes2,
+					..
+				}),
+			) => attempts1 != attempts2 || successes1 != successes2,
+			(
+				MatchEntryValue::EventLog(EventLogEntry { events: value1, .. }),
+				MatchEntryValue::EventLog(EventLogEntry { events: value2, .. }),
+			) => value1 != value2,
+			(
+				MatchEntryValue::FieldPosition(FieldPositionEntry {
+					positions: value1, ..
+				}),
+				MatchEntryValue::FieldPosition(FieldPositionEntry {
+					positions: value2, ..
+				}),
+			) => value1 != value2,
+			(
+				MatchEntryValue::Rating(RatingEntry { value: value1, .. }),
+				MatchEntryValue::Rating(RatingEntry { value: value2, .. }),
+			) => value1 != value2,
+			(
+				MatchEntryValue::MultiSelect(MultiSelectEntry { values: value1, .. }),
+				MatchEntryValue::MultiSelect(MultiSelectEntry { values: value2, .. }),
+			) => value1 != value2,
 			(
 				MatchEntryValue::TextEntry(TextFieldEntry { text: value1, .. }),
 				MatchEntryValue::TextEntry(TextFieldEntry { text: value2, .. }),
@@ -241,6 +308,22 @@ pub struct MatchEnumEntry {
 	pub timestamp_ms: u64,
 }
 
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, Object, TS)]
+#[ts(export, export_to = "../../../client/src/generated/")]
+pub struct MultiSelectEntry {
+	/// The selected options
+	pub values: Vec<String>,
+	/// The scout that recorded this value
+	#[serde(default)]
+	#[oai(default)]
+	pub scout: String,
+	/// The timestamp for this change (to ensure old changes don't overwrite new changes)
+	#[serde(default)]
+	#[oai(default)]
+	#[ts(type = "number")]
+	pub timestamp_ms: u64,
+}
+
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize, Object, TS)]
 #[ts(export, export_to = "../../../client/src/generated/")]
 pub struct MatchBoolEntry {
@@ -289,6 +372,93 @@ pub struct CounterEntry {
 	pub timestamp_ms: u64,
 }
 
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, Object, TS)]
+#[ts(export, export_to = "../../../client/src/generated/")]
+pub struct AttemptCounterEntry {
+	/// How many times it was attempted
+	pub attempts: u32,
+	/// How many of the attempts succeeded
+	pub successes: u32,
+	/// The scout that recorded this value
+	#[serde(default)]
+	#[oai(default)]
+	pub scout: String,
+	/// The timestamp for this change (to ensure old changes don't overwrite new changes)
+	#[serde(default)]
+	#[oai(default)]
+	#[ts(type = "number")]
+	pub timestamp_ms: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, Object, TS)]
+#[ts(export, export_to = "../../../client/src/generated/")]
+pub struct EventLogEntry {
+	/// Every action that happened, in the order they happened
+	pub events: Vec<LoggedEvent>,
+	/// The scout that recorded this value
+	#[serde(default)]
+	#[oai(default)]
+	pub scout: String,
+	/// The timestamp for this change (to ensure old changes don't overwrite new changes)
+	#[serde(default)]
+	#[oai(default)]
+	#[ts(type = "number")]
+	pub timestamp_ms: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, Object, TS)]
+#[ts(export, export_to = "../../../client/src/generated/")]
+pub struct LoggedEvent {
+	/// Which action happened
+	pub action: String,
+	/// When it happened, in seconds since the start of the match
+	pub time_seconds: f32,
+	/// Where on the field it happened, if locations are recorded for this metric
+	#[serde(default)]
+	#[oai(default)]
+	pub location: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, Object, TS)]
+#[ts(export, export_to = "../../../client/src/generated/")]
+pub struct FieldPositionEntry {
+	/// Every position that was recorded
+	pub positions: Vec<FieldPoint>,
+	/// The scout that recorded this value
+	#[serde(default)]
+	#[oai(default)]
+	pub scout: String,
+	/// The timestamp for this change (to ensure old changes don't overwrite new changes)
+	#[serde(default)]
+	#[oai(default)]
+	#[ts(type = "number")]
+	pub timestamp_ms: u64,
+}
+
+/// A position on the field image, from 0 to 1 across the width and height of the image
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize, Object, TS)]
+#[ts(export, export_to = "../../../client/src/generated/")]
+pub struct FieldPoint {
+	pub x: f32,
+	pub y: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, Object, TS)]
+#[ts(export, export_to = "../../../client/src/generated/")]
+pub struct RatingEntry {
+	/// The value
+	pub value: f32,
+	/// The scout that recorded this value
+	#[serde(default)]
+	#[oai(default)]
+	pub scout: String,
+	/// The timestamp for this change (to ensure old changes don't overwrite new changes)
+	#[serde(default)]
+	#[oai(default)]
+	#[ts(type = "number")]
+	pub timestamp_ms: u64,
+}
+
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize, Object, TS)]
 #[ts(export, export_to = "../../../client/src/generated/")]
 pub struct TextFieldEntry {
@@ -342,3 +512,82 @@ pub struct ImageEntryData {
 	/// would all work I'm sure)
 	pub image_data: Vec<u8>,
 }
+
+/// How much of a chunked upload the server has
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, Object, TS)]
+#[ts(export, export_to = "../../../client/src/generated/")]
+pub struct ImageUploadStatus {
+	/// The number of bytes received, which is where the next chunk starts
+	#[ts(type = "number")]
+	pub received: u64,
+}
+
+/// The details needed to finish a chunked upload
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, Object, TS)]
+#[ts(export, export_to = "../../../client/src/generated/")]
+pub struct ImageUploadComplete {
+	/// The format mime type of the image or video, e.g. `image/png`
+	pub image_mime: String,
+	/// The id of the image entry the upload is for, videos are only saved if it allows them
+	#[serde(default)]
+	#[oai(default)]
+	pub field: Option<String>,
+	/// The SHA-256 hash of the whole upload as hex, to check that nothing was lost
+	pub sha256: String,
+}
+
+/// A change to a stored image. Edits are kept and always applied to the original upload, so
+/// making several of them doesn't lose quality.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, Object, TS)]
+#[ts(export, export_to = "../../../client/src/generated/")]
+pub struct ImageEdit {
+	/// How far to turn the image
+	#[serde(default)]
+	#[oai(default)]
+	pub rotate: ImageRotation,
+	/// The part of the image to keep, after rotating it
+	#[serde(default)]
+	#[oai(default)]
+	pub crop: Option<ImageCrop>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, Serialize, Enum, TS)]
+#[ts(export, export_to = "../../../client/src/generated/")]
+#[serde(rename_all = "snake_case")]
+#[oai(rename_all = "snake_case")]
+pub enum ImageRotation {
+	#[default]
+	None,
+	/// A quarter turn clockwise
+	Clockwise,
+	HalfTurn,
+	/// A quarter turn counter-clockwise
+	CounterClockwise,
+}
+
+/// A rectangle of an image, from 0 to 1 across the width and height of the image
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, Object, TS)]
+#[ts(export, export_to = "../../../client/src/generated/")]
+pub struct ImageCrop {
+	/// Left edge of the rectangle
+	pub x: f32,
+	/// Top edge of the rectangle
+	pub y: f32,
+	pub width: f32,
+	pub height: f32,
+}
+
+impl ImageCrop {
+	/// Whether this is a rectangle with some area inside of the image
+	pub fn is_valid(&self) -> bool {
+		[self.x, self.y, self.width, self.height]
+			.iter()
+			.all(|n| n.is_finite())
+			&& self.x >= 0.0
+			&& self.y >= 0.0
+			&& self.width > 0.0
+			&& self.height > 0.0
+			&& self.x + self.width <= 1.0
+			&& self.y + self.height <= 1.0
+	}
+}
@@ -1,3 +1,4 @@
+pub mod formula;
 pub mod match_entry;
 
 use color_eyre::eyre::eyre;
@@ -12,6 +13,7 @@ use std::sync::Arc;
 use ts_rs::TS;
 
 use crate::analysis::TBA_PREFIX;
+use crate::config::formula::Formula;
 use crate::config::match_entry::{EntryType, MatchEntryFields};
 
 /// Global configuration for a "game" e.g. rapid react
@@ -32,6 +34,39 @@ pub struct GameConfig {
 	pub ranking_points: Vec<String>,
 	/// Configuration on how to display collected information
 	pub display: DisplayConfig,
+	/// The parts of a match, used to split up timestamped events. Uses the common config if empty.
+	#[serde(default)]
+	#[oai(default)]
+	pub phases: Vec<MatchPhase>,
+	/// The field image that positions are recorded on, if any metrics record positions
+	#[serde(default)]
+	#[oai(default)]
+	pub field: Option<FieldConfig>,
+	/// A bool metric for whether the robot showed up, when it didn't nothing else is kept for the
+	/// match. Uses the common config if empty.
+	#[serde(default)]
+	#[oai(default)]
+	pub presence_metric: Option<String>,
+}
+
+/// The image of the field for recording where things happen
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, Object, TS)]
+#[ts(export, export_to = "../../client/src/generated/")]
+pub struct FieldConfig {
+	/// The URL of the image, positions are recorded from 0 to 1 across the width and height of it
+	pub image: String,
+}
+
+/// A window of time within a match, e.g. auto
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, Object, TS)]
+#[ts(export, export_to = "../../client/src/generated/")]
+pub struct MatchPhase {
+	/// The display name for the phase
+	pub name: String,
+	/// When the phase starts, in seconds since the start of the match
+	pub start_seconds: f32,
+	/// When the phase ends (exclusive), in seconds since the start of the match
+	pub end_seconds: f32,
 }
 
 /// A category for metrics to collect
@@ -62,6 +97,24 @@ pub struct CollectedMetric {
 	pub collect: CollectionOption,
 	/// Config for what type of data to collect
 	pub metric: CollectedMetricType,
+	/// Whether an entry can't be submitted without this
+	#[serde(default)]
+	#[oai(default)]
+	pub required: bool,
+	/// Only show this when another metric has one of a set of values, e.g. climb level if they climbed
+	#[serde(default)]
+	#[oai(default)]
+	pub show_if: Option<FieldCondition>,
+}
+
+/// A condition on the value of another metric
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, Object, TS)]
+#[ts(export, export_to = "../../client/src/generated/")]
+pub struct FieldCondition {
+	/// The id of the metric to check
+	pub metric: String,
+	/// The values that meet the condition, e.g. `Yes` for a bool or an option of an enum
+	pub values: Vec<String>,
 }
 
 /// Where to get the data from
@@ -114,6 +167,16 @@ pub enum CollectedMetricType {
 	Timer(TimerMetric),
 	/// A metric that represents an amount of things
 	Counter(CounterMetric),
+	/// A metric that counts how many times something was attempted, and how many of those succeeded
+	AttemptCounter(AttemptCounterMetric),
+	/// A metric that records when each action happened during a match
+	EventLog(EventLogMetric),
+	/// A metric that records positions on the field image, e.g. the starting position
+	FieldPosition(FieldPositionMetric),
+	/// A metric that represents a rating on a scale, e.g. driver skill from 1 to 5
+	Rating(RatingMetric),
+	/// A metric that represents any number of a selection of options
+	MultiSelect(MultiSelectMetric),
 	/// A text entry field, either single line or multi lined
 	TextEntry(TextEntryMetric),
 	/// An picture field, for example for pit scouting robot pictures
@@ -122,6 +185,8 @@ pub enum CollectedMetricType {
 	BaseTeam(TeamPropertiesList),
 	/// A metric that represents data fetched from statbotics' team api
 	StatboticsTeam(TeamPropertiesList),
+	/// A metric that is calculated for each match from other metrics
+	Formula(FormulaMetric),
 }
 
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize, Object, TS)]
@@ -130,6 +195,10 @@ pub struct AbilityMetric {
 	/// If this implies another ability, or set of abilities
 	#[serde(default)]
 	pub implies: Vec<String>,
+	/// How many points succeeding at this is worth
+	#[serde(default)]
+	#[oai(default)]
+	pub points: Option<f32>,
 }
 
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize, Object, TS)]
@@ -137,6 +206,17 @@ pub struct AbilityMetric {
 pub struct EnumMetric {
 	/// Options for the enum
 	pub options: Vec<String>,
+	/// How many points each option is worth, options that are missing are worth nothing
+	#[serde(default)]
+	#[oai(default)]
+	pub points: HashMap<String, f32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, Object, TS)]
+#[ts(export, export_to = "../../client/src/generated/")]
+pub struct MultiSelectMetric {
+	/// Options that can be selected
+	pub options: Vec<String>,
 }
 
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize, Object, TS)]
@@ -150,26 +230,125 @@ pub struct TimerMetric {}
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize, Object, TS)]
 #[ts(export, export_to = "../../client/src/generated/")]
 pub struct CounterMetric {
-	limit_range: Option<CounterRange>,
+	pub limit_range: Option<CounterRange>,
 }
 
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize, Object, TS)]
 #[ts(export, export_to = "../../client/src/generated/")]
 pub struct CounterRange {
-	start: i32,
-	end_inclusive: i32,
+	pub start: i32,
+	pub end_inclusive: i32,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, Object, TS)]
+#[ts(export, export_to = "../../client/src/generated/")]
+pub struct AttemptCounterMetric {
+	/// How many points each success is worth
+	#[serde(default)]
+	#[oai(default)]
+	pub points: Option<f32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, Object, TS)]
+#[ts(export, export_to = "../../client/src/generated/")]
+pub struct EventLogMetric {
+	/// The actions that can be logged, e.g. `scored`
+	pub actions: Vec<String>,
+	/// The actions that count as scoring, for cycle times. Every action counts if this is empty.
+	#[serde(default)]
+	#[oai(default)]
+	pub scoring_actions: Vec<String>,
+	/// Where on the field an action can happen, if that should be recorded
+	#[serde(default)]
+	#[oai(default)]
+	pub locations: Vec<String>,
+}
+
+impl EventLogMetric {
+	pub fn is_scoring(&self, action: &str) -> bool {
+		self.scoring_actions.is_empty() || self.scoring_actions.iter().any(|a| a == action)
+	}
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, Object, TS)]
+#[ts(export, export_to = "../../client/src/generated/")]
+pub struct FieldPositionMetric {
+	/// Whether more than one position can be recorded each match, e.g. for shooting locations
+	#[serde(default)]
+	#[oai(default)]
+	pub multiple: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, Object, TS)]
+#[ts(export, export_to = "../../client/src/generated/")]
+pub struct RatingMetric {
+	/// The lowest rating
+	pub min: f32,
+	/// The highest rating
+	pub max: f32,
+	/// The difference between each possible rating
+	#[serde(default = "default_rating_step")]
+	#[oai(default = "default_rating_step")]
+	pub step: f32,
+	/// Names for some of the ratings, e.g. 1 is "Slow"
+	#[serde(default)]
+	#[oai(default)]
+	pub labels: Vec<RatingLabel>,
+}
+
+fn default_rating_step() -> f32 {
+	1.0
+}
+
+impl RatingMetric {
+	/// The closest rating on the scale that lands on a step
+	pub fn nearest(&self, value: f32) -> f32 {
+		let steps = ((value - self.min) / self.step).round();
+		(self.min + steps * self.step).clamp(self.min, self.max)
+	}
+
+	pub fn get_label(&self, value: f32) -> Option<&str> {
+		self.labels
+			.iter()
+			.find(|label| label.value == value)
+			.map(|label| label.label.as_str())
+	}
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, Object, TS)]
+#[ts(export, export_to = "../../client/src/generated/")]
+pub struct RatingLabel {
+	pub value: f32,
+	pub label: String,
 }
 
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize, Object, TS)]
 #[ts(export, export_to = "../../client/src/generated/")]
 pub struct TextEntryMetric {
-	multiline: bool,
+	pub multiline: bool,
 }
 
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize, Object, TS)]
 #[ts(export, export_to = "../../client/src/generated/")]
 pub struct ImageMetric {
-	allow_video: bool,
+	/// Whether short video clips can be attached as well as photos
+	pub allow_video: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, Object, TS)]
+#[ts(export, export_to = "../../client/src/generated/")]
+pub struct FormulaMetric {
+	/// The formula to calculate, e.g. `auto-hub * 2 + climb_points[climb-level]`.
+	/// Subtracting metrics needs spaces around the `-`, since metric ids can contain `-`.
+	pub formula: String,
+	/// Tables for looking up a value for each option of a metric, e.g. the points for a climb level
+	#[serde(default)]
+	#[oai(default)]
+	pub tables: HashMap<String, HashMap<String, f32>>,
+	/// Whether the result is an amount of time
+	#[serde(default)]
+	#[oai(default)]
+	pub is_time: bool,
 }
 
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize, Object, TS)]
@@ -235,6 +414,10 @@ pub enum MatchStatisticsPropType {
 pub struct TbaMatchPropOption {
 	pub id: String,
 	pub name: Option<String>,
+	/// How many points this option is worth
+	#[serde(default)]
+	#[oai(default)]
+	pub points: Option<f32>,
 }
 
 /// Configure how the data is processed and displayed
@@ -245,6 +428,28 @@ pub struct DisplayConfig {
 	pub team_row: Vec<DisplayColumn>,
 	/// Config for the pre-match display
 	pub pre_match: PreMatchDisplay,
+	/// How many of the most recent matches to use for recent averages
+	#[serde(default = "default_recent_matches")]
+	#[oai(default = "default_recent_matches")]
+	pub recent_matches: usize,
+	/// Weights for the metrics used to recommend teams during alliance selection
+	#[serde(default)]
+	#[oai(default)]
+	pub pick_weights: Vec<PickWeight>,
+}
+
+fn default_recent_matches() -> usize {
+	3
+}
+
+/// A weighted metric used when recommending teams for alliance selection
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, Object, TS)]
+#[ts(export, export_to = "../../client/src/generated/")]
+pub struct PickWeight {
+	/// The id of the metric
+	pub metric: String,
+	/// How much this metric counts towards the recommendation score
+	pub weight: f32,
 }
 
 /// A column for the alliance selection team-list table
@@ -275,6 +480,10 @@ pub struct SingleMetric {
 	#[serde(default)]
 	#[oai(default)]
 	pub display: bool,
+	/// Which statistic to show for numeric metrics
+	#[serde(default)]
+	#[oai(default)]
+	pub stat: NumericStat,
 }
 
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize, Object, TS)]
@@ -287,6 +496,52 @@ pub struct FilteredMetric {
 	#[serde(default)]
 	#[oai(default)]
 	pub display: bool,
+	/// Which statistic to show for numeric metrics
+	#[serde(default)]
+	#[oai(default)]
+	pub stat: NumericStat,
+}
+
+/// A statistic that can be shown for a numeric metric
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize, Enum, TS)]
+#[ts(export, export_to = "../../client/src/generated/")]
+#[serde(rename_all = "snake_case")]
+#[oai(rename_all = "snake_case")]
+pub enum NumericStat {
+	/// The average of all matches, the default
+	#[default]
+	Mean,
+	Median,
+	Min,
+	Max,
+	/// The sample standard deviation
+	StdDev,
+	Percentile25,
+	Percentile75,
+	/// The average of the most recent matches
+	RecentAverage,
+	/// How much the value changes per match
+	Trend,
+	/// From 0 to 1, how close every match is to the average
+	Consistency,
+}
+
+impl NumericStat {
+	/// The name to add to the column heading, if any
+	pub fn name(&self) -> Option<&'static str> {
+		match self {
+			NumericStat::Mean => None,
+			NumericStat::Median => Some("Median"),
+			NumericStat::Min => Some("Min"),
+			NumericStat::Max => Some("Max"),
+			NumericStat::StdDev => Some("Std Dev"),
+			NumericStat::Percentile25 => Some("25th Percentile"),
+			NumericStat::Percentile75 => Some("75th Percentile"),
+			NumericStat::RecentAverage => Some("Recent"),
+			NumericStat::Trend => Some("Trend"),
+			NumericStat::Consistency => Some("Consistency"),
+		}
+	}
 }
 
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize, Object, TS)]
@@ -336,6 +591,112 @@ pub struct TeamConfig {
 	#[oai(skip)]
 	#[ts(skip)]
 	pub tba_auth_key: String,
+	/// The secret for the TBA webhook, which results are sent to as soon as they're posted.
+	/// Messages aren't accepted if this isn't set.
+	#[doc(hidden)]
+	#[serde(default, skip_serializing)]
+	#[oai(skip)]
+	#[ts(skip)]
+	pub tba_webhook_secret: String,
+	/// How uploaded images are stored
+	#[serde(default)]
+	#[oai(default)]
+	pub images: ImageConfig,
+	/// Whether schedules and results are also loaded from the FRC Events API
+	#[serde(default)]
+	#[oai(default)]
+	pub frc_events: FrcEventsConfig,
+}
+
+/// Settings for the FRC Events API, which often has results before TBA does
+#[derive(Debug, Clone, PartialEq, Default, Deserialize, Serialize, Object, TS)]
+#[ts(export, export_to = "../../client/src/generated/")]
+pub struct FrcEventsConfig {
+	#[serde(default)]
+	#[oai(default)]
+	pub role: FrcEventsRole,
+	/// The username the API key was registered with
+	#[serde(default)]
+	#[oai(default)]
+	pub username: String,
+	/// FRC Events API key, not sent to the client for the same reason as the TBA one
+	#[doc(hidden)]
+	#[serde(default, skip_serializing)]
+	#[oai(skip)]
+	#[ts(skip)]
+	pub auth_key: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize, Serialize, Enum, TS)]
+#[ts(export, export_to = "../../client/src/generated/")]
+#[serde(rename_all = "snake_case")]
+#[oai(rename_all = "snake_case")]
+pub enum FrcEventsRole {
+	/// Only use TBA
+	#[default]
+	Disabled,
+	/// Load both, and use the FRC Events API's data when both have results for a match
+	Primary,
+	/// Only load from the FRC Events API when TBA can't be loaded, then fill in the results TBA's
+	/// saved data doesn't have yet from it. While TBA can be loaded the FRC Events API isn't used
+	/// at all, even for results TBA hasn't posted yet; use `primary` to always load both.
+	Fallback,
+}
+
+/// Settings for the versions of uploaded images that are stored, only used for images uploaded or edited
+/// after they're changed
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, Object, TS)]
+#[ts(export, export_to = "../../client/src/generated/")]
+pub struct ImageConfig {
+	/// The largest width or height of the small version of images, used for thumbnails
+	#[serde(default = "default_thumbnail_size")]
+	#[oai(default = "default_thumbnail_size")]
+	pub thumbnail_size: u32,
+	/// The largest width or height of the full version of images, the original upload is always kept
+	#[serde(default)]
+	#[oai(default)]
+	pub max_full_size: Option<u32>,
+	/// Whether to also store lossless WebP versions of PNG images, which are sent to browsers that
+	/// support them if they're smaller
+	#[serde(default = "default_webp")]
+	#[oai(default = "default_webp")]
+	pub webp: bool,
+	/// The largest photo that can be uploaded, in megabytes
+	#[serde(default = "default_max_image_upload_mb")]
+	#[oai(default = "default_max_image_upload_mb")]
+	pub max_image_upload_mb: u32,
+	/// The largest video that can be uploaded, in megabytes
+	#[serde(default = "default_max_video_upload_mb")]
+	#[oai(default = "default_max_video_upload_mb")]
+	pub max_video_upload_mb: u32,
+}
+
+impl Default for ImageConfig {
+	fn default() -> Self {
+		Self {
+			thumbnail_size: default_thumbnail_size(),
+			max_full_size: None,
+			webp: default_webp(),
+			max_image_upload_mb: default_max_image_upload_mb(),
+			max_video_upload_mb: default_max_video_upload_mb(),
+		}
+	}
+}
+
+fn default_thumbnail_size() -> u32 {
+	300
+}
+
+fn default_webp() -> bool {
+	true
+}
+
+fn default_max_image_upload_mb() -> u32 {
+	20
+}
+
+fn default_max_video_upload_mb() -> u32 {
+	64
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -350,6 +711,21 @@ pub struct GameConfigs {
 	pub pit_entry_fields: MatchEntryFields,
 	/// All the categories for all field types
 	pub all_metrics: Vec<String>,
+	/// The parsed formulas for every formula metric
+	pub formulas: HashMap<String, Formula>,
+}
+
+impl GameConfigs {
+	/// Finds the config for a metric, along with the category it's in
+	pub fn get_collected_metric(
+		&self,
+		metric: &str,
+	) -> Option<(&MetricCategory, &CollectedMetric)> {
+		self.game_config
+			.categories
+			.values()
+			.find_map(|category| category.metrics.get(metric).map(|m| (category, m)))
+	}
 }
 
 impl From<GameConfig> for GameConfigs {
@@ -373,6 +749,8 @@ impl From<GameConfig> for GameConfigs {
 							collect: CollectionOption::Never,
 							description: "N/A".to_string(),
 							metric: CollectedMetricType::Bool(BoolMetric {}),
+							required: false,
+							show_if: None,
 						},
 					)
 				})
@@ -380,55 +758,98 @@ impl From<GameConfig> for GameConfigs {
 		});
 		all_categories.sort_by_key(|c| c.order);
 
+		let all_metrics: Vec<String> = all_categories
+			.into_iter()
+			.flat_map(|category| {
+				let mut metrics = category
+					.metrics
+					.iter()
+					.map(|(metric_name, metric)| (metric_name.clone(), metric))
+					.collect::<Vec<_>>();
+				metrics.sort_by_key(|(_, m)| m.order);
+
+				metrics
+					.into_iter()
+					.flat_map(|(metric_name, metric)| {
+						if let CollectedMetricType::BaseTeam(props_list) = &metric.metric {
+							props_list
+								.props
+								.iter()
+								.map(|prop| format!("base-{prop}"))
+								.collect()
+						} else if let CollectedMetricType::StatboticsTeam(props_list) =
+							&metric.metric
+						{
+							props_list
+								.props
+								.iter()
+								.flat_map(|prop| {
+									if prop == "rp-all" {
+										value
+											.ranking_points
+											.iter()
+											.enumerate()
+											.map(|(i, _)| format!("statbotics-rp-{}", i + 1))
+											.collect()
+									} else {
+										vec![format!("statbotics-{prop}")]
+									}
+								})
+								.collect()
+						} else {
+							vec![metric_name]
+						}
+					})
+					.collect::<Vec<_>>()
+			})
+			.collect();
+
+		let formulas = value
+			.categories
+			.values()
+			.flat_map(|category| category.metrics.iter())
+			.filter_map(|(metric_id, metric)| {
+				if let CollectedMetricType::Formula(formula_metric) = &metric.metric {
+					match Formula::parse(&formula_metric.formula) {
+						Ok(formula) => {
+							for used_metric in formula.metrics() {
+								if !all_metrics.iter().any(|m| m == used_metric) {
+									log::warn!("Formula for metric '{metric_id}' uses unknown metric '{used_metric}'");
+								}
+							}
+							Some((metric_id.clone(), formula))
+						}
+						Err(err) => {
+							log::error!("Failed to parse formula for metric '{metric_id}': {err}");
+							None
+						}
+					}
+				} else {
+					None
+				}
+			})
+			.collect();
+
+		for (metric_id, metric) in value
+			.categories
+			.values()
+			.flat_map(|category| category.metrics.iter())
+		{
+			if let CollectedMetricType::Ability(ability) = &metric.metric {
+				for implied in &ability.implies {
+					if !all_metrics.iter().any(|m| m == implied) {
+						log::warn!("Ability '{metric_id}' implies unknown metric '{implied}'");
+					}
+				}
+			}
+		}
+
 		GameConfigs {
 			match_entry_fields: MatchEntryFields::from_game_config(&value, EntryType::Match),
 			driver_entry_fields: MatchEntryFields::from_game_config(&value, EntryType::DriveTeam),
 			pit_entry_fields: MatchEntryFields::from_game_config(&value, EntryType::Pit),
-			all_metrics: all_categories
-				.into_iter()
-				.flat_map(|category| {
-					let mut metrics = category
-						.metrics
-						.iter()
-						.map(|(metric_name, metric)| (metric_name.clone(), metric))
-						.collect::<Vec<_>>();
-					metrics.sort_by_key(|(_, m)| m.order);
-
-					metrics
-						.into_iter()
-						.flat_map(|(metric_name, metric)| {
-							if let CollectedMetricType::BaseTeam(props_list) = &metric.metric {
-								props_list
-									.props
-									.iter()
-									.map(|prop| format!("base-{prop}"))
-									.collect()
-							} else if let CollectedMetricType::StatboticsTeam(props_list) =
-								&metric.metric
-							{
-								props_list
-									.props
-									.iter()
-									.flat_map(|prop| {
-										if prop == "rp-all" {
-											value
-												.ranking_points
-												.iter()
-												.enumerate()
-												.map(|(i, _)| format!("statbotics-rp-{}", i + 1))
-												.collect()
-										} else {
-											vec![format!("statbotics-{prop}")]
-										}
-									})
-									.collect()
-							} else {
-								vec![metric_name]
-							}
-						})
-						.collect::<Vec<_>>()
-				})
-				.collect(),
+			all_metrics,
+			formulas,
 			game_config: value,
 		}
 	}
@@ -461,6 +882,15 @@ impl ConfigManager {
 		{
 			match serde_yaml::from_slice::<GameConfig>(&file.data) {
 				Ok(mut config) => {
+					if config.phases.is_empty() {
+						config.phases = common_config.phases.clone();
+					}
+					if config.field.is_none() {
+						config.field = common_config.field.clone();
+					}
+					if config.presence_metric.is_none() {
+						config.presence_metric = common_config.presence_metric.clone();
+					}
 					config.display.team_row = common_config
 						.display
 						.team_row
@@ -493,6 +923,7 @@ impl ConfigManager {
 								met.metric,
 								CollectedMetricType::StatboticsTeam(_)
 									| CollectedMetricType::BaseTeam(_)
+									| CollectedMetricType::Formula(_)
 							) {
 								met.collect = CollectionOption::Never;
 							}
@@ -529,4 +960,8 @@ impl ConfigManager {
 	pub fn get_tba_auth_key(&self) -> &str {
 		&self.config.tba_auth_key
 	}
+
+	pub fn get_tba_webhook_secret(&self) -> &str {
+		&self.config.tba_webhook_secret
+	}
 }
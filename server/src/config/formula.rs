@@ -0,0 +1,366 @@
+use std::iter::Peekable;
+use std::str::Chars;
+
+use thiserror::Error;
+
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum FormulaError {
+	#[error("Unexpected character '{0}'")]
+	UnexpectedChar(char),
+	#[error("Unexpected end of formula")]
+	UnexpectedEnd,
+	#[error("Expected '{expected}' but found '{found}'")]
+	Expected { expected: String, found: String },
+	#[error("Invalid number '{0}'")]
+	InvalidNumber(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+	Number(f32),
+	Ident(String),
+	Plus,
+	Minus,
+	Star,
+	Slash,
+	OpenParen,
+	CloseParen,
+	OpenBracket,
+	CloseBracket,
+}
+
+impl Token {
+	fn describe(&self) -> String {
+		match self {
+			Token::Number(number) => number.to_string(),
+			Token::Ident(ident) => ident.clone(),
+			Token::Plus => "+".to_string(),
+			Token::Minus => "-".to_string(),
+			Token::Star => "*".to_string(),
+			Token::Slash => "/".to_string(),
+			Token::OpenParen => "(".to_string(),
+			Token::CloseParen => ")".to_string(),
+			Token::OpenBracket => "[".to_string(),
+			Token::CloseBracket => "]".to_string(),
+		}
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum BinaryOp {
+	Add,
+	Subtract,
+	Multiply,
+	Divide,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+	Number(f32),
+	/// The numeric value of a metric
+	Metric(String),
+	/// Look up the value of a metric in a table, e.g. `climb_points[climb-level]`
+	Lookup {
+		table: String,
+		metric: String,
+	},
+	Negate(Box<Expr>),
+	Binary(BinaryOp, Box<Expr>, Box<Expr>),
+}
+
+/// A parsed formula for a derived metric, e.g. `auto-hub * 2 + climb_points[climb-level]`.
+///
+/// Metric ids may contain `-`, so subtracting one metric from another needs spaces around the `-`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Formula {
+	expr: Expr,
+}
+
+impl Formula {
+	pub fn parse(source: &str) -> Result<Formula, FormulaError> {
+		let tokens = tokenize(source)?;
+		let mut parser = Parser {
+			tokens: tokens.into_iter().peekable(),
+		};
+		let expr = parser.expr()?;
+		match parser.tokens.next() {
+			None => Ok(Formula { expr }),
+			Some(token) => Err(FormulaError::Expected {
+				expected: "end of formula".to_string(),
+				found: token.describe(),
+			}),
+		}
+	}
+
+	/// Evaluates the formula. `value` gets the numeric value of a metric, and `lookup` gets the
+	/// value from a table for whatever a metric is set to.
+	pub fn evaluate(&self, value: &dyn Fn(&str) -> f32, lookup: &dyn Fn(&str, &str) -> f32) -> f32 {
+		Self::evaluate_expr(&self.expr, value, lookup)
+	}
+
+	/// Every metric id this formula refers to
+	pub fn metrics(&self) -> Vec<&str> {
+		let mut metrics = Vec::new();
+		Self::collect_metrics(&self.expr, &mut metrics);
+		metrics
+	}
+
+	fn collect_metrics<'a>(expr: &'a Expr, metrics: &mut Vec<&'a str>) {
+		match expr {
+			Expr::Number(_) => {}
+			Expr::Metric(metric) | Expr::Lookup { metric, .. } => metrics.push(metric),
+			Expr::Negate(inner) => Self::collect_metrics(inner, metrics),
+			Expr::Binary(_, left, right) => {
+				Self::collect_metrics(left, metrics);
+				Self::collect_metrics(right, metrics);
+			}
+		}
+	}
+
+	fn evaluate_expr(
+		expr: &Expr,
+		value: &dyn Fn(&str) -> f32,
+		lookup: &dyn Fn(&str, &str) -> f32,
+	) -> f32 {
+		match expr {
+			Expr::Number(number) => *number,
+			Expr::Metric(metric) => value(metric),
+			Expr::Lookup { table, metric } => lookup(table, metric),
+			Expr::Negate(inner) => -Self::evaluate_expr(inner, value, lookup),
+			Expr::Binary(op, left, right) => {
+				let left = Self::evaluate_expr(left, value, lookup);
+				let right = Self::evaluate_expr(right, value, lookup);
+				match op {
+					BinaryOp::Add => left + right,
+					BinaryOp::Subtract => left - right,
+					BinaryOp::Multiply => left * right,
+					// Nobody wants an infinite number of points
+					BinaryOp::Divide if right == 0.0 => 0.0,
+					BinaryOp::Divide => left / right,
+				}
+			}
+		}
+	}
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>, FormulaError> {
+	let mut tokens = Vec::new();
+	let mut chars = source.chars().peekable();
+	while let Some(&c) = chars.peek() {
+		match c {
+			c if c.is_whitespace() => {
+				chars.next();
+			}
+			'0'..='9' | '.' => tokens.push(tokenize_number(&mut chars)?),
+			'a'..='z' | 'A'..='Z' | '_' => {
+				let mut ident = String::new();
+				while let Some(&c) = chars.peek() {
+					if c.is_ascii_alphanumeric() || c == '_' || c == '-' {
+						ident.push(c);
+						chars.next();
+					} else {
+						break;
+					}
+				}
+				tokens.push(Token::Ident(ident));
+			}
+			_ => {
+				tokens.push(match c {
+					'+' => Token::Plus,
+					'-' => Token::Minus,
+					'*' => Token::Star,
+					'/' => Token::Slash,
+					'(' => Token::OpenParen,
+					')' => Token::CloseParen,
+					'[' => Token::OpenBracket,
+					']' => Token::CloseBracket,
+					c => return Err(FormulaError::UnexpectedChar(c)),
+				});
+				chars.next();
+			}
+		}
+	}
+	Ok(tokens)
+}
+
+fn tokenize_number(chars: &mut Peekable<Chars>) -> Result<Token, FormulaError> {
+	let mut number = String::new();
+	while let Some(&c) = chars.peek() {
+		if c.is_ascii_digit() || c == '.' {
+			number.push(c);
+			chars.next();
+		} else {
+			break;
+		}
+	}
+	number
+		.parse()
+		.map(Token::Number)
+		.map_err(|_| FormulaError::InvalidNumber(number))
+}
+
+struct Parser {
+	tokens: Peekable<std::vec::IntoIter<Token>>,
+}
+
+impl Parser {
+	fn expr(&mut self) -> Result<Expr, FormulaError> {
+		let mut left = self.term()?;
+		loop {
+			let op = match self.tokens.peek() {
+				Some(Token::Plus) => BinaryOp::Add,
+				Some(Token::Minus) => BinaryOp::Subtract,
+				_ => return Ok(left),
+			};
+			self.tokens.next();
+			left = Expr::Binary(op, Box::new(left), Box::new(self.term()?));
+		}
+	}
+
+	fn term(&mut self) -> Result<Expr, FormulaError> {
+		let mut left = self.factor()?;
+		loop {
+			let op = match self.tokens.peek() {
+				Some(Token::Star) => BinaryOp::Multiply,
+				Some(Token::Slash) => BinaryOp::Divide,
+				_ => return Ok(left),
+			};
+			self.tokens.next();
+			left = Expr::Binary(op, Box::new(left), Box::new(self.factor()?));
+		}
+	}
+
+	fn factor(&mut self) -> Result<Expr, FormulaError> {
+		match self.tokens.next() {
+			Some(Token::Number(number)) => Ok(Expr::Number(number)),
+			Some(Token::Minus) => Ok(Expr::Negate(Box::new(self.factor()?))),
+			Some(Token::OpenParen) => {
+				let expr = self.expr()?;
+				self.expect(Token::CloseParen)?;
+				Ok(expr)
+			}
+			Some(Token::Ident(ident)) => {
+				if self.tokens.peek() == Some(&Token::OpenBracket) {
+					self.tokens.next();
+					let metric = match self.tokens.next() {
+						Some(Token::Ident(metric)) => metric,
+						Some(token) => {
+							return Err(FormulaError::Expected {
+								expected: "metric id".to_string(),
+								found: token.describe(),
+							})
+						}
+						None => return Err(FormulaError::UnexpectedEnd),
+					};
+					self.expect(Token::CloseBracket)?;
+					Ok(Expr::Lookup {
+						table: ident,
+						metric,
+					})
+				} else {
+					Ok(Expr::Metric(ident))
+				}
+			}
+			Some(token) => Err(FormulaError::Expected {
+				expected: "number, metric id or '('".to_string(),
+				found: token.describe(),
+			}),
+			None => Err(FormulaError::UnexpectedEnd),
+		}
+	}
+
+	fn expect(&mut self, expected: Token) -> Result<(), FormulaError> {
+		match self.tokens.next() {
+			Some(token) if token == expected => Ok(()),
+			Some(token) => Err(FormulaError::Expected {
+				expected: expected.describe(),
+				found: token.describe(),
+			}),
+			None => Err(FormulaError::UnexpectedEnd),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn evaluate(source: &str) -> f32 {
+		Formula::parse(source).unwrap().evaluate(
+			&|metric| match metric {
+				"auto-hub" => 4.0,
+				"teleop-hub" => 10.0,
+				_ => 0.0,
+			},
+			&|table, metric| match (table, metric) {
+				("climb_points", "climb-level") => 20.0,
+				_ => 0.0,
+			},
+		)
+	}
+
+	fn parse_error(source: &str) -> String {
+		Formula::parse(source).unwrap_err().to_string()
+	}
+
+	#[test]
+	fn precedence() {
+		assert_eq!(evaluate("1 + 2 * 3"), 7.0);
+		assert_eq!(evaluate("(1 + 2) * 3"), 9.0);
+		assert_eq!(evaluate("8 / 4 / 2"), 1.0);
+		assert_eq!(evaluate("10 - 4 - 3"), 3.0);
+		assert_eq!(evaluate("2 * 3 - 8 / 4"), 4.0);
+	}
+
+	#[test]
+	fn unary_minus() {
+		assert_eq!(evaluate("-3"), -3.0);
+		assert_eq!(evaluate("2 * -3"), -6.0);
+		assert_eq!(evaluate("--3"), 3.0);
+		assert_eq!(evaluate("-(1 + 2) * 2"), -6.0);
+		assert_eq!(evaluate("1 - -1"), 2.0);
+	}
+
+	#[test]
+	fn metrics_and_lookups() {
+		assert_eq!(evaluate("auto-hub * 2 + teleop-hub"), 18.0);
+		// Without spaces the `-` is part of the metric id
+		assert_eq!(evaluate("teleop-hub - auto-hub"), 6.0);
+		assert_eq!(evaluate("climb_points[climb-level] + 1"), 21.0);
+		assert_eq!(evaluate("unknown + 1"), 1.0);
+		assert_eq!(
+			Formula::parse("auto-hub + climb_points[climb-level] * auto-hub")
+				.unwrap()
+				.metrics(),
+			vec!["auto-hub", "climb-level", "auto-hub"]
+		);
+	}
+
+	#[test]
+	fn divide_by_zero() {
+		assert_eq!(evaluate("5 / 0"), 0.0);
+		assert_eq!(evaluate("5 / unknown"), 0.0);
+		assert_eq!(evaluate("1 + 5 / (2 - 2)"), 1.0);
+	}
+
+	#[test]
+	fn errors() {
+		assert_eq!(parse_error("1 + $"), "Unexpected character '$'");
+		assert_eq!(parse_error("1 +"), "Unexpected end of formula");
+		assert_eq!(parse_error("(1 + 2"), "Unexpected end of formula");
+		assert_eq!(parse_error("(1 + 2]"), "Expected ')' but found ']'");
+		assert_eq!(
+			parse_error("1 2"),
+			"Expected 'end of formula' but found '2'"
+		);
+		assert_eq!(
+			parse_error("* 2"),
+			"Expected 'number, metric id or '('' but found '*'"
+		);
+		assert_eq!(
+			parse_error("table[1]"),
+			"Expected 'metric id' but found '1'"
+		);
+		assert_eq!(parse_error("1.2.3"), "Invalid number '1.2.3'");
+	}
+}
@@ -1,8 +1,9 @@
 use crate::{
 	analysis::TBA_PREFIX,
 	config::{
-		AbilityMetric, BoolMetric, CollectedMetricType, CounterMetric, EnumMetric, GameConfig,
-		ImageMetric, TextEntryMetric, TimerMetric,
+		AbilityMetric, AttemptCounterMetric, BoolMetric, CollectedMetricType, CounterMetric,
+		EnumMetric, EventLogMetric, FieldCondition, FieldPositionMetric, GameConfig, ImageMetric,
+		MultiSelectMetric, RatingMetric, TextEntryMetric, TimerMetric,
 	},
 };
 use poem_openapi::{Object, Union};
@@ -33,6 +34,10 @@ pub struct MatchEntryFields {
 	pub pages: Vec<MatchEntryPage>,
 	/// List of entries paired with ids, which are referenced on the pages
 	pub entries: HashMap<String, MatchEntry>,
+	/// The id of the bool entry for whether the robot showed up, if it's collected here
+	pub presence_entry: Option<String>,
+	/// The URL of the field image that field position entries are recorded on
+	pub field_image: Option<String>,
 }
 
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize, Object, TS)]
@@ -57,6 +62,12 @@ pub struct MatchEntry {
 	pub page: String,
 	/// The type of entry along with any parameters (if present)
 	pub entry: MatchEntryType,
+	/// Whether this has to be filled in
+	pub required: bool,
+	/// Only show this entry when another entry has one of a set of values
+	pub show_if: Option<FieldCondition>,
+	/// Other entries that are implied by this one being done, e.g. a high climb implies a low climb
+	pub implies: Vec<String>,
 }
 
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize, Union, TS)]
@@ -72,6 +83,16 @@ pub enum MatchEntryType {
 	Bool(BoolMetric),
 	/// A metric that represents an amount of things
 	Counter(CounterMetric),
+	/// An entry that counts attempts, and how many of those succeeded
+	AttemptCounter(AttemptCounterMetric),
+	/// An entry that records when each action happened during a match
+	EventLog(EventLogMetric),
+	/// An entry that records positions on the field image
+	FieldPosition(FieldPositionMetric),
+	/// An entry that represents a rating on a scale
+	Rating(RatingMetric),
+	/// An entry that represents any number of a selection of options
+	MultiSelect(MultiSelectMetric),
 	/// A text entry field, either single line or multi lined
 	TextEntry(TextEntryMetric),
 	/// An picture field, for example for pit scouting robot pictures
@@ -91,9 +112,16 @@ impl MatchEntryType {
 			CollectedMetricType::Bool(b) => Self::Bool(b.clone()),
 			CollectedMetricType::Timer(t) => Self::Timer(t.clone()),
 			CollectedMetricType::Counter(c) => Self::Counter(c.clone()),
+			CollectedMetricType::AttemptCounter(a) => Self::AttemptCounter(a.clone()),
+			CollectedMetricType::EventLog(e) => Self::EventLog(e.clone()),
+			CollectedMetricType::FieldPosition(f) => Self::FieldPosition(f.clone()),
+			CollectedMetricType::Rating(r) => Self::Rating(r.clone()),
+			CollectedMetricType::MultiSelect(m) => Self::MultiSelect(m.clone()),
 			CollectedMetricType::TextEntry(t) => Self::TextEntry(t.clone()),
 			CollectedMetricType::Image(i) => Self::Image(i.clone()),
-			CollectedMetricType::BaseTeam(_) | CollectedMetricType::StatboticsTeam(_) => {
+			CollectedMetricType::BaseTeam(_)
+			| CollectedMetricType::StatboticsTeam(_)
+			| CollectedMetricType::Formula(_) => {
 				unimplemented!("These metrics aren't collectable per-match.");
 			}
 		}
@@ -132,78 +160,94 @@ impl MatchEntryFields {
 			.filter(|(_, page)| !page.layout.is_empty())
 			.collect::<Vec<_>>();
 		pages.sort_by_key(|(order, _)| *order);
-		Self {
-			entries: game_config
-				.categories
-				.values()
-				.flat_map(|category| {
-					category
-						.metrics
-						.iter()
-						.filter(|(_, metric)| match entry_type {
-							EntryType::DriveTeam => metric.collect.collect_from_drive(),
-							EntryType::Match => metric.collect.collect_in_match(),
-							EntryType::Pit => metric.collect.collect_in_pit(),
-						})
-						.map(|(metric_id, metric)| {
-							(
-								metric_id.clone(),
-								MatchEntry {
-									title: metric.name.clone(),
-									description: metric.description.clone(),
-									page: category.name.clone(),
-									entry: MatchEntryType::from(&metric.metric, entry_type),
+		let entries: HashMap<_, _> = game_config
+			.categories
+			.values()
+			.flat_map(|category| {
+				category
+					.metrics
+					.iter()
+					.filter(|(_, metric)| match entry_type {
+						EntryType::DriveTeam => metric.collect.collect_from_drive(),
+						EntryType::Match => metric.collect.collect_in_match(),
+						EntryType::Pit => metric.collect.collect_in_pit(),
+					})
+					.map(|(metric_id, metric)| {
+						(
+							metric_id.clone(),
+							MatchEntry {
+								title: metric.name.clone(),
+								description: metric.description.clone(),
+								page: category.name.clone(),
+								entry: MatchEntryType::from(&metric.metric, entry_type),
+								required: metric.required,
+								show_if: metric.show_if.clone(),
+								implies: match &metric.metric {
+									CollectedMetricType::Ability(ability) => {
+										ability.implies.clone()
+									}
+									_ => Vec::new(),
 								},
-							)
-						})
-				})
-				.chain(game_config.tba.props.iter().map(|(name, prop)| {
-					(
-						format!("{TBA_PREFIX}{name}"),
-						MatchEntry {
-							title: prop.name.clone(),
-							description: format!("{} from The Blue Alliance.", prop.name),
-							page: "The Blue Alliance".to_string(),
-							entry: match prop.ty {
-								MatchStatisticsPropType::Bool => {
-									MatchEntryType::Bool(BoolMetric {})
-								}
-								MatchStatisticsPropType::Sum => {
-									MatchEntryType::Counter(CounterMetric {
-										limit_range: Some(CounterRange {
-											start: 0,
-											end_inclusive: prop
-												.options
-												.as_ref()
-												.map(|o| o.len() as i32)
-												.unwrap_or_default(),
-										}),
-									})
-								}
-								MatchStatisticsPropType::Enum => {
-									let mut options = Vec::new();
-									let mut options_set = HashSet::new();
-									for option in prop
-										.options
-										.iter()
-										.flatten()
-										.map(|o| o.name.as_ref().unwrap_or(&o.id).clone())
-									{
-										if !options_set.contains(&option) {
-											options.push(option.clone());
-											options_set.insert(option);
-										}
+							},
+						)
+					})
+			})
+			.chain(game_config.tba.props.iter().map(|(name, prop)| {
+				(
+					format!("{TBA_PREFIX}{name}"),
+					MatchEntry {
+						title: prop.name.clone(),
+						description: format!("{} from The Blue Alliance.", prop.name),
+						page: "The Blue Alliance".to_string(),
+						entry: match prop.ty {
+							MatchStatisticsPropType::Bool => MatchEntryType::Bool(BoolMetric {}),
+							MatchStatisticsPropType::Sum => {
+								MatchEntryType::Counter(CounterMetric {
+									limit_range: Some(CounterRange {
+										start: 0,
+										end_inclusive: prop
+											.options
+											.as_ref()
+											.map(|o| o.len() as i32)
+											.unwrap_or_default(),
+									}),
+								})
+							}
+							MatchStatisticsPropType::Enum => {
+								let mut options = Vec::new();
+								let mut options_set = HashSet::new();
+								let mut points = HashMap::new();
+								for prop_option in prop.options.iter().flatten() {
+									let option =
+										prop_option.name.as_ref().unwrap_or(&prop_option.id);
+									if let Some(option_points) = prop_option.points {
+										points.insert(option.clone(), option_points);
+									}
+									if !options_set.contains(option) {
+										options.push(option.clone());
+										options_set.insert(option.clone());
 									}
-									MatchEntryType::Enum(EnumMetric { options })
-								}
-								MatchStatisticsPropType::Number => {
-									MatchEntryType::Counter(CounterMetric { limit_range: None })
 								}
-							},
+								MatchEntryType::Enum(EnumMetric { options, points })
+							}
+							MatchStatisticsPropType::Number => {
+								MatchEntryType::Counter(CounterMetric { limit_range: None })
+							}
 						},
-					)
-				}))
-				.collect(),
+						required: false,
+						show_if: None,
+						implies: Vec::new(),
+					},
+				)
+			}))
+			.collect();
+		Self {
+			presence_entry: game_config
+				.presence_metric
+				.clone()
+				.filter(|metric| entries.contains_key(metric)),
+			field_image: game_config.field.as_ref().map(|field| field.image.clone()),
+			entries,
 			pages: pages.into_iter().map(|(_, page)| page).collect(),
 		}
 	}
@@ -1,22 +1,547 @@
-use crate::api::data::{FullEntryData, MatchEntryValue};
+use std::collections::HashSet;
+
+use poem_openapi::{Enum, Object};
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::api::data::{
+	FullEntryData, MatchAbilityEntry, MatchAbilityValue, MatchBoolEntry, MatchEntryValue,
+};
 use crate::config::match_entry::{MatchEntryFields, MatchEntryType};
+use crate::config::FieldCondition;
+use crate::media;
+
+/// The most characters kept for a text entry, anything after is cut off
+const MAX_TEXT_LENGTH: usize = 10_000;
+/// The timestamp given to implied values. It is older than anything a scout records, so when entries
+/// are merged a value the scout set directly always replaces an implied one and never the reverse.
+const IMPLIED_TIMESTAMP_MS: u64 = 0;
+
+/// The problems found with an entry that was submitted
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, Object, TS)]
+#[ts(export, export_to = "../../client/src/generated/")]
+pub struct ValidationReport {
+	/// The match the entry is for, if it isn't a pit entry
+	pub match_id: Option<String>,
+	pub team_id: String,
+	/// Whether the entry was saved, with any problem values fixed or removed
+	pub saved: bool,
+	pub issues: Vec<FieldIssue>,
+}
 
-pub fn validate_match(mut data: FullEntryData, fields: &MatchEntryFields) -> FullEntryData {
-	data.entries.retain(|field_name, data| {
-		if let Some(entry) = fields.entries.get(field_name) {
-			matches!(
-				(&entry.entry, &data),
-				(MatchEntryType::Ability(_), MatchEntryValue::Ability(_))
-					| (MatchEntryType::Enum(_), MatchEntryValue::Enum(_))
-					| (MatchEntryType::Bool(_), MatchEntryValue::Bool(_))
-					| (MatchEntryType::Timer(_), MatchEntryValue::Timer(_))
-					| (MatchEntryType::Counter(_), MatchEntryValue::Counter(_))
-					| (MatchEntryType::TextEntry(_), MatchEntryValue::TextEntry(_))
-					| (MatchEntryType::Image(_), MatchEntryValue::Image(_))
-			)
-		} else {
-			false
+impl ValidationReport {
+	pub fn new(match_id: Option<&str>, team_id: &str, issues: Vec<FieldIssue>) -> Self {
+		ValidationReport {
+			match_id: match_id.map(|match_id| match_id.to_string()),
+			team_id: team_id.to_string(),
+			saved: issues
+				.iter()
+				.all(|issue| issue.action != IssueAction::Rejected),
+			issues,
+		}
+	}
+}
+
+/// A problem with a single field of an entry
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, Object, TS)]
+#[ts(export, export_to = "../../client/src/generated/")]
+pub struct FieldIssue {
+	/// The id of the field
+	pub field: String,
+	/// What was wrong with it
+	pub problem: String,
+	/// What was done about it
+	pub action: IssueAction,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, Enum, TS)]
+#[ts(export, export_to = "../../client/src/generated/")]
+#[serde(rename_all = "snake_case")]
+#[oai(rename_all = "snake_case")]
+pub enum IssueAction {
+	/// The value was changed to the closest valid value
+	Clamped,
+	/// The value was thrown away
+	Dropped,
+	/// The whole entry was not saved
+	Rejected,
+	/// The value was kept, but it doesn't agree with other values
+	Warning,
+}
+
+/// Checks every submitted value against its field, fixing or removing any values that aren't valid
+pub fn validate_match(
+	mut data: FullEntryData,
+	fields: &MatchEntryFields,
+) -> (FullEntryData, Vec<FieldIssue>) {
+	let mut issues = Vec::new();
+	data.entries.retain(|field_name, value| {
+		let result = match fields.entries.get(field_name) {
+			Some(entry) => check_value(&entry.entry, value),
+			None => Err("Unknown field".to_string()),
+		};
+		match result {
+			Ok(None) => true,
+			Ok(Some(problem)) => {
+				issues.push(FieldIssue {
+					field: field_name.clone(),
+					problem,
+					action: IssueAction::Clamped,
+				});
+				true
+			}
+			Err(problem) => {
+				issues.push(FieldIssue {
+					field: field_name.clone(),
+					problem,
+					action: IssueAction::Dropped,
+				});
+				false
+			}
 		}
 	});
-	data
+	issues.sort_by(|a, b| a.field.cmp(&b.field));
+	(data, issues)
+}
+
+/// Removes the values that don't count: everything but presence when the robot didn't show up,
+/// and values for fields hidden by what was entered after them. They're kept when saving, so
+/// changing the field they depend on back brings them back, and this is done when reading instead.
+pub fn remove_uncounted(data: &mut FullEntryData, fields: &MatchEntryFields) {
+	if let Some(presence_entry) = did_not_show(data, fields) {
+		let presence_entry = presence_entry.to_string();
+		data.entries
+			.retain(|field_name, _| *field_name == presence_entry);
+		return;
+	}
+	let hidden = data
+		.entries
+		.keys()
+		.filter(|field_name| !is_shown(field_name, data, fields, 0))
+		.cloned()
+		.collect::<Vec<_>>();
+	for field_name in hidden {
+		data.entries.remove(&field_name);
+	}
+}
+
+/// Fills in everything implied by what was done, e.g. a high climb means they can do a low climb.
+/// Gives a warning for each implied value that says it wasn't done, since those contradict.
+pub fn apply_implications(data: &mut FullEntryData, fields: &MatchEntryFields) -> Vec<FieldIssue> {
+	let mut issues = Vec::new();
+	let mut to_check = data
+		.entries
+		.iter()
+		.filter(|(_, value)| is_done(value))
+		.map(|(field_name, _)| field_name.clone())
+		.collect::<Vec<_>>();
+	let mut checked = HashSet::new();
+	while let Some(field_name) = to_check.pop() {
+		if !checked.insert(field_name.clone()) {
+			continue;
+		}
+		let (Some(entry), Some(source)) = (
+			fields.entries.get(&field_name),
+			data.entries.get(&field_name).cloned(),
+		) else {
+			continue;
+		};
+		for implied in &entry.implies {
+			match data.entries.get(implied) {
+				Some(value) if is_done(value) => {}
+				Some(_) => issues.push(FieldIssue {
+					field: implied.clone(),
+					problem: format!("Recorded as not done, but {field_name} implies it was"),
+					action: IssueAction::Warning,
+				}),
+				None => {
+					let implied_value = match fields.entries.get(implied).map(|e| &e.entry) {
+						Some(MatchEntryType::Ability(_)) => {
+							MatchEntryValue::Ability(MatchAbilityEntry {
+								value: MatchAbilityValue::Succeeded,
+								scout: source.get_scout().to_string(),
+								timestamp_ms: IMPLIED_TIMESTAMP_MS,
+							})
+						}
+						Some(MatchEntryType::Bool(_)) => MatchEntryValue::Bool(MatchBoolEntry {
+							value: true,
+							scout: source.get_scout().to_string(),
+							timestamp_ms: IMPLIED_TIMESTAMP_MS,
+						}),
+						_ => continue,
+					};
+					data.entries.insert(implied.clone(), implied_value);
+					to_check.push(implied.clone());
+				}
+			}
+		}
+	}
+	issues.sort_by(|a, b| a.field.cmp(&b.field));
+	issues
+}
+
+fn is_done(value: &MatchEntryValue) -> bool {
+	matches!(
+		value,
+		MatchEntryValue::Ability(MatchAbilityEntry {
+			value: MatchAbilityValue::Succeeded,
+			..
+		}) | MatchEntryValue::Bool(MatchBoolEntry { value: true, .. })
+	)
+}
+
+/// Finds every required field that is shown but hasn't been filled in. This has to be run on the
+/// entry after it is merged with what was saved before, since the submitted values are often only
+/// the changed ones.
+pub fn check_required(data: &FullEntryData, fields: &MatchEntryFields) -> Vec<FieldIssue> {
+	if did_not_show(data, fields).is_some() {
+		return Vec::new();
+	}
+	let mut missing = fields
+		.entries
+		.iter()
+		.filter(|(field_name, entry)| {
+			entry.required
+				&& !data.entries.contains_key(*field_name)
+				&& is_shown(field_name, data, fields, 0)
+		})
+		.map(|(field_name, _)| FieldIssue {
+			field: field_name.clone(),
+			problem: "Required field is missing".to_string(),
+			action: IssueAction::Rejected,
+		})
+		.collect::<Vec<_>>();
+	missing.sort_by(|a, b| a.field.cmp(&b.field));
+	missing
+}
+
+/// Gets the presence field if it says the robot didn't show up
+fn did_not_show<'a>(data: &FullEntryData, fields: &'a MatchEntryFields) -> Option<&'a str> {
+	fields.presence_entry.as_deref().filter(|presence_entry| {
+		matches!(
+			data.entries.get(*presence_entry),
+			Some(MatchEntryValue::Bool(MatchBoolEntry { value: false, .. }))
+		)
+	})
+}
+
+fn is_shown(
+	field_name: &str,
+	data: &FullEntryData,
+	fields: &MatchEntryFields,
+	depth: usize,
+) -> bool {
+	let Some(condition) = fields
+		.entries
+		.get(field_name)
+		.and_then(|entry| entry.show_if.as_ref())
+	else {
+		return true;
+	};
+	// Conditions that loop back on themselves can never be met
+	if depth > fields.entries.len() {
+		return false;
+	}
+	is_shown(&condition.metric, data, fields, depth + 1)
+		&& data
+			.entries
+			.get(&condition.metric)
+			.is_some_and(|value| is_condition_met(condition, value))
+}
+
+fn is_condition_met(condition: &FieldCondition, value: &MatchEntryValue) -> bool {
+	match value {
+		MatchEntryValue::MultiSelect(multi_select) => multi_select
+			.values
+			.iter()
+			.any(|selected| condition.values.contains(selected)),
+		_ => value
+			.option_label()
+			.is_some_and(|label| condition.values.contains(&label)),
+	}
+}
+
+fn type_name(entry_type: &MatchEntryType) -> &'static str {
+	match entry_type {
+		MatchEntryType::Ability(_) => "ability",
+		MatchEntryType::Enum(_) => "enum",
+		MatchEntryType::Bool(_) => "bool",
+		MatchEntryType::Counter(_) => "counter",
+		MatchEntryType::AttemptCounter(_) => "attempt counter",
+		MatchEntryType::EventLog(_) => "event log",
+		MatchEntryType::FieldPosition(_) => "field position",
+		MatchEntryType::Rating(_) => "rating",
+		MatchEntryType::MultiSelect(_) => "multi-select",
+		MatchEntryType::TextEntry(_) => "text entry",
+		MatchEntryType::Image(_) => "image",
+		MatchEntryType::Timer(_) => "timer",
+	}
+}
+
+/// Checks a single value, fixing it if possible. Gives the problem that was fixed if there was one,
+/// or an error if the value can't be kept.
+fn check_value(
+	entry_type: &MatchEntryType,
+	value: &mut MatchEntryValue,
+) -> Result<Option<String>, String> {
+	match (entry_type, value) {
+		(MatchEntryType::Enum(metric), MatchEntryValue::Enum(enum_entry)) => {
+			if metric.options.contains(&enum_entry.value) {
+				Ok(None)
+			} else {
+				Err(format!("'{}' is not one of the options", enum_entry.value))
+			}
+		}
+		(MatchEntryType::Counter(metric), MatchEntryValue::Counter(counter)) => {
+			let (start, end) = metric
+				.limit_range
+				.as_ref()
+				.map(|range| (range.start, range.end_inclusive))
+				.unwrap_or((0, i32::MAX));
+			if (start..=end).contains(&counter.count) {
+				Ok(None)
+			} else {
+				let problem = format!("{} is outside of {start} to {end}", counter.count);
+				counter.count = counter.count.clamp(start, end);
+				Ok(Some(problem))
+			}
+		}
+		(MatchEntryType::Timer(_), MatchEntryValue::Timer(timer)) => {
+			if !timer.time_seconds.is_finite() {
+				Err("Time is not a number".to_string())
+			} else if timer.time_seconds < 0.0 {
+				timer.time_seconds = 0.0;
+				Ok(Some("Time is negative".to_string()))
+			} else {
+				Ok(None)
+			}
+		}
+		(MatchEntryType::TextEntry(_), MatchEntryValue::TextEntry(text_entry)) => {
+			match text_entry.text.char_indices().nth(MAX_TEXT_LENGTH) {
+				Some((cut_off, _)) => {
+					text_entry.text.truncate(cut_off);
+					Ok(Some(format!(
+						"Text is longer than {MAX_TEXT_LENGTH} characters"
+					)))
+				}
+				None => Ok(None),
+			}
+		}
+		(MatchEntryType::AttemptCounter(_), MatchEntryValue::AttemptCounter(attempt_counter)) => {
+			// Every success had to be attempted
+			if attempt_counter.successes > attempt_counter.attempts {
+				attempt_counter.attempts = attempt_counter.successes;
+				Ok(Some("More successes than attempts".to_string()))
+			} else {
+				Ok(None)
+			}
+		}
+		(MatchEntryType::EventLog(metric), MatchEntryValue::EventLog(event_log)) => {
+			let event_count = event_log.events.len();
+			event_log.events.retain(|event| {
+				metric.actions.contains(&event.action)
+					&& event.time_seconds.is_finite()
+					&& event.time_seconds >= 0.0
+					&& event
+						.location
+						.as_ref()
+						.is_none_or(|location| metric.locations.contains(location))
+			});
+			event_log
+				.events
+				.sort_by(|a, b| a.time_seconds.total_cmp(&b.time_seconds));
+			let removed = event_count - event_log.events.len();
+			Ok((removed > 0).then(|| format!("Removed {removed} invalid events")))
+		}
+		(MatchEntryType::FieldPosition(metric), MatchEntryValue::FieldPosition(field_position)) => {
+			if !metric.multiple && field_position.positions.len() > 1 {
+				return Err("Only one position can be recorded".to_string());
+			}
+			if field_position
+				.positions
+				.iter()
+				.any(|point| !point.x.is_finite() || !point.y.is_finite())
+			{
+				return Err("Position is not a number".to_string());
+			}
+			let mut clamped = false;
+			for point in &mut field_position.positions {
+				let x = point.x.clamp(0.0, 1.0);
+				let y = point.y.clamp(0.0, 1.0);
+				clamped |= x != point.x || y != point.y;
+				point.x = x;
+				point.y = y;
+			}
+			Ok(clamped.then(|| "Position is off of the field".to_string()))
+		}
+		(MatchEntryType::Rating(metric), MatchEntryValue::Rating(rating)) => {
+			if !rating.value.is_finite() {
+				return Err("Rating is not a number".to_string());
+			}
+			let nearest = metric.nearest(rating.value);
+			if (nearest - rating.value).abs() < 0.001 {
+				Ok(None)
+			} else {
+				let problem = format!("{} is not a possible rating", rating.value);
+				rating.value = nearest;
+				Ok(Some(problem))
+			}
+		}
+		(MatchEntryType::MultiSelect(metric), MatchEntryValue::MultiSelect(multi_select)) => {
+			let value_count = multi_select.values.len();
+			let mut seen = Vec::new();
+			multi_select.values.retain(|value| {
+				if metric.options.contains(value) && !seen.contains(value) {
+					seen.push(value.clone());
+					true
+				} else {
+					false
+				}
+			});
+			let removed = value_count - multi_select.values.len();
+			Ok((removed > 0).then(|| format!("Removed {removed} unknown or repeated options")))
+		}
+		(MatchEntryType::Image(metric), MatchEntryValue::Image(image_entry)) => {
+			if metric.allow_video {
+				return Ok(None);
+			}
+			let image_count = image_entry.images.len();
+			image_entry
+				.images
+				.retain(|image| !media::is_video(&image.image_mime));
+			let removed = image_count - image_entry.images.len();
+			Ok((removed > 0).then(|| format!("Removed {removed} videos, only photos are allowed")))
+		}
+		(MatchEntryType::Ability(_), MatchEntryValue::Ability(_))
+		| (MatchEntryType::Bool(_), MatchEntryValue::Bool(_)) => Ok(None),
+		(entry_type, _) => Err(format!("Expected a {} value", type_name(entry_type))),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use serde_json::{json, Value};
+
+	use super::*;
+
+	fn field(entry: Value, required: bool, implies: &[&str]) -> Value {
+		json!({
+			"title": "", "description": "", "page": "", "required": required, "show_if": null,
+			"entry": entry, "implies": implies,
+		})
+	}
+
+	fn fields() -> MatchEntryFields {
+		let ability = json!({ "type": "ability", "implies": [] });
+		serde_json::from_value(json!({
+			"pages": [],
+			"entries": {
+				"cycles": field(json!({ "type": "counter", "limit_range": null }), false, &[]),
+				"auto-cycles": field(
+					json!({ "type": "counter", "limit_range": { "start": 0, "end_inclusive": 5 } }),
+					false,
+					&[],
+				),
+				"notes": field(json!({ "type": "text_entry", "multiline": true }), false, &[]),
+				"high-climb": field(ability.clone(), true, &["low-climb"]),
+				"low-climb": field(ability, false, &[]),
+			},
+			"presence_entry": null,
+		}))
+		.unwrap()
+	}
+
+	fn entry(values: Value) -> FullEntryData {
+		serde_json::from_value(json!({
+			"year": 2026, "event": "2026test", "entries": values, "timestamp_ms": null,
+		}))
+		.unwrap()
+	}
+
+	fn counter(count: i32) -> Value {
+		json!({ "type": "counter", "count": count })
+	}
+
+	fn ability(value: &str) -> Value {
+		json!({ "type": "ability", "value": value })
+	}
+
+	fn actions(issues: &[FieldIssue]) -> Vec<(&str, IssueAction)> {
+		issues
+			.iter()
+			.map(|issue| (issue.field.as_str(), issue.action))
+			.collect()
+	}
+
+	#[test]
+	fn counter_without_range_clamps_negatives() {
+		let (data, issues) = validate_match(entry(json!({ "cycles": counter(-3) })), &fields());
+		assert_eq!(actions(&issues), [("cycles", IssueAction::Clamped)]);
+		assert_eq!(
+			data.entries["cycles"],
+			entry(json!({ "cycles": counter(0) })).entries["cycles"]
+		);
+
+		let (_, issues) = validate_match(entry(json!({ "cycles": counter(1000) })), &fields());
+		assert!(issues.is_empty());
+	}
+
+	#[test]
+	fn counter_clamps_to_range() {
+		let (data, issues) = validate_match(entry(json!({ "auto-cycles": counter(8) })), &fields());
+		assert_eq!(actions(&issues), [("auto-cycles", IssueAction::Clamped)]);
+		assert_eq!(
+			data.entries["auto-cycles"],
+			entry(json!({ "auto-cycles": counter(5) })).entries["auto-cycles"]
+		);
+	}
+
+	#[test]
+	fn long_text_is_truncated() {
+		let text = "é".repeat(MAX_TEXT_LENGTH + 10);
+		let (data, issues) = validate_match(
+			entry(json!({ "notes": { "type": "text_entry", "text": text } })),
+			&fields(),
+		);
+		assert_eq!(actions(&issues), [("notes", IssueAction::Clamped)]);
+		let MatchEntryValue::TextEntry(text_entry) = &data.entries["notes"] else {
+			panic!("Expected a text entry");
+		};
+		assert_eq!(text_entry.text.chars().count(), MAX_TEXT_LENGTH);
+	}
+
+	#[test]
+	fn wrong_and_unknown_values_are_dropped() {
+		let (data, issues) = validate_match(
+			entry(json!({ "cycles": ability("succeeded"), "speed": counter(1) })),
+			&fields(),
+		);
+		assert_eq!(
+			actions(&issues),
+			[
+				("cycles", IssueAction::Dropped),
+				("speed", IssueAction::Dropped)
+			]
+		);
+		assert!(data.entries.is_empty());
+	}
+
+	#[test]
+	fn missing_required_field_rejects_entry() {
+		let data = entry(json!({ "cycles": counter(2) }));
+		let issues = check_required(&data, &fields());
+		assert_eq!(actions(&issues), [("high-climb", IssueAction::Rejected)]);
+		assert!(!ValidationReport::new(Some("qm1"), "1234", issues).saved);
+	}
+
+	#[test]
+	fn contradicted_implication_warns() {
+		let mut data = entry(json!({
+			"high-climb": ability("succeeded"),
+			"low-climb": ability("attempted"),
+		}));
+		let issues = apply_implications(&mut data, &fields());
+		assert_eq!(actions(&issues), [("low-climb", IssueAction::Warning)]);
+		assert!(ValidationReport::new(Some("qm1"), "1234", issues).saved);
+	}
 }
@@ -1,14 +1,26 @@
-use std::io::Cursor;
-use std::{collections::HashMap, path::Path};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use image::imageops::FilterType;
-use image::{DynamicImage, ImageError};
+use image::{DynamicImage, ImageError, ImageFormat};
 use log::{info, warn};
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
-use sled::{Db, Tree};
+use sled::transaction::{ConflictableTransactionError, TransactionError};
+use sled::{Batch, Db, Tree};
 use thiserror::Error;
 
-use crate::api::data::{DriverEntryIdData, FullEntryData, MatchEntryIdData, StoredEntryData};
+use crate::api::data::{
+	DriverEntryIdData, FullEntryData, ImageEdit, MatchEntryIdData, MatchEntryValue, StoredEntryData,
+};
+use crate::config::ImageConfig;
+use crate::data_validation::{FieldIssue, IssueAction};
+use crate::manual_event::ManualEvent;
+use crate::media;
+use crate::pick_list::PickList;
+use crate::statbotics::SavedStatboticsTeam;
+use crate::tba::{EventInfo, TeamAvatar};
 
 #[derive(Debug, Error)]
 pub enum DbError {
@@ -20,6 +32,19 @@ pub enum DbError {
 	Bincode(#[from] bincode::Error),
 	#[error("Database serde error: {0}")]
 	Serde(#[from] serde_json::Error),
+	#[error("The upload continues from byte {received}, not {offset}")]
+	UploadOffset { received: u64, offset: u64 },
+	#[error("Part of the upload is missing, it has to be sent again")]
+	UploadIncomplete,
+}
+
+impl From<TransactionError<DbError>> for DbError {
+	fn from(err: TransactionError<DbError>) -> Self {
+		match err {
+			TransactionError::Abort(err) => err,
+			TransactionError::Storage(err) => DbError::Sled(err),
+		}
+	}
 }
 
 #[derive(Debug, Clone)]
@@ -28,11 +53,40 @@ pub struct Database {
 	driver_entries: Tree,
 	match_entries: Tree,
 	pit_entries: Tree,
+	pick_lists: Tree,
+	upstream_data: Tree,
 }
 
+const IMAGE_PREFIX_ORIGINAL: &str = "image-original:"; // Map image id to the uploaded image and edits to it
 const IMAGE_PREFIX_FULL: &str = "image-full:"; // Map image id to image data
 const IMAGE_PREFIX_SMALL: &str = "image-small:"; // Map image id to image data (small size)
-const IMAGE_SIZE_SMALL: u32 = 300;
+const IMAGE_PREFIX_FULL_WEBP: &str = "image-full-webp:"; // Map image id to image data as WebP, if that's smaller
+const IMAGE_PREFIX_SMALL_WEBP: &str = "image-small-webp:"; // Map image id to image data as WebP (small size)
+const VIDEO_PREFIX_INFO: &str = "video-info:"; // Map video id to the video's type and size
+const VIDEO_PREFIX_CHUNK: &str = "video-chunk:"; // Map video id and chunk index to part of the video data
+const VIDEO_CHUNK_SIZE: usize = 256 * 1024;
+const MEDIA_PREFIX_WRITTEN: &str = "media-written:"; // Map image or video id to when it was last written
+const MEDIA_PREFIX_HASH: &str = "media-hash:"; // Map image or video id to the hash of what was uploaded
+const MEDIA_PREFIX_BY_HASH: &str = "media-by-hash:"; // Map the hash of an upload to the image or video id it was saved as
+const UPLOAD_PREFIX_INFO: &str = "upload-info:"; // Map image id to how much of a chunked upload has been received
+const UPLOAD_PREFIX_CHUNK: &str = "upload-chunk:"; // Map image id and chunk index to part of a chunked upload
+const TBA_EVENT_PREFIX: &str = "tba_event"; // Map year and event to the event from TBA
+const FRC_EVENT_PREFIX: &str = "frc_event"; // Map year and event to the event from the FRC Events API
+const TBA_AVATAR_PREFIX: &str = "tba_avatar"; // Map year and team to the team's avatar from TBA
+const MANUAL_EVENT_PREFIX: &str = "manual_event"; // Map year and event to teams and matches entered by hand
+const STATBOTICS_TEAM_PREFIX: &str = "statbotics_team"; // Map year, event, and team to their data from Statbotics
+
+/// Every prefix that has a single key per image or video, video chunks have their own keys
+const MEDIA_PREFIXES: [&str; 8] = [
+	IMAGE_PREFIX_ORIGINAL,
+	IMAGE_PREFIX_FULL,
+	IMAGE_PREFIX_SMALL,
+	IMAGE_PREFIX_FULL_WEBP,
+	IMAGE_PREFIX_SMALL_WEBP,
+	VIDEO_PREFIX_INFO,
+	MEDIA_PREFIX_WRITTEN,
+	MEDIA_PREFIX_HASH,
+];
 
 #[derive(Debug, Copy, Clone)]
 pub enum ImageSize {
@@ -40,6 +94,17 @@ pub enum ImageSize {
 	Small,
 }
 
+impl ImageSize {
+	fn prefix(self, webp: bool) -> &'static str {
+		match (self, webp) {
+			(ImageSize::Full, false) => IMAGE_PREFIX_FULL,
+			(ImageSize::Small, false) => IMAGE_PREFIX_SMALL,
+			(ImageSize::Full, true) => IMAGE_PREFIX_FULL_WEBP,
+			(ImageSize::Small, true) => IMAGE_PREFIX_SMALL_WEBP,
+		}
+	}
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct ImageData {
 	pub mime_type: String,
@@ -48,6 +113,46 @@ pub struct ImageData {
 	pub image_data: Vec<u8>,
 }
 
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct OriginalImage {
+	/// The image exactly as it was uploaded
+	pub image: ImageData,
+	/// Edits to make to the uploaded image, in order
+	pub edits: Vec<ImageEdit>,
+}
+
+/// An image or video attached to an entry
+#[derive(Debug, Clone)]
+pub struct MediaReference {
+	pub year: u32,
+	pub event: String,
+	pub media_id: String,
+}
+
+/// A chunked upload that hasn't been finished yet
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct UploadInfo {
+	/// The number of bytes received so far
+	pub received: u64,
+	pub chunks: u32,
+	pub updated_ms: u64,
+}
+
+/// Data loaded from TBA or Statbotics, saved so it can still be used when they can't be reached
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CachedData<T> {
+	/// When the data was loaded, in milliseconds since the unix epoch
+	pub fetched_ms: u64,
+	pub data: T,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct VideoInfo {
+	pub mime_type: String,
+	/// Total size of the video in bytes
+	pub size: u64,
+}
+
 impl Database {
 	pub fn get_all_match_entries(&self, year: u32, event: &str) -> Vec<MatchEntryIdData> {
 		self.match_entries
@@ -125,21 +230,21 @@ impl Database {
 		match_id: &str,
 		team: &str,
 		data: FullEntryData,
-	) -> Result<(), DbError> {
-		if let Some(new_data) = Self::get_merged_data(
+		validate: impl FnOnce(&FullEntryData) -> Vec<FieldIssue>,
+	) -> Result<Vec<FieldIssue>, DbError> {
+		let merged = Self::get_merged_data(
 			"MATCH",
 			team,
 			Some(match_id),
-			self.get_match_entry_data(year, event, match_id, team)
-				.unwrap(),
+			self.get_match_entry_data(year, event, match_id, team)?,
 			data,
-		) {
-			let data = serde_json::to_vec(&new_data)?;
-			self.match_entries
-				.insert(Self::match_entry_key(year, event, match_id, team), data)?;
-		}
-
-		Ok(())
+		);
+		Self::save_merged_data(
+			&self.match_entries,
+			Self::match_entry_key(year, event, match_id, team),
+			merged,
+			validate,
+		)
 	}
 	pub fn get_driver_entry_data(
 		&self,
@@ -168,21 +273,21 @@ impl Database {
 		match_id: &str,
 		team: &str,
 		data: FullEntryData,
-	) -> Result<(), DbError> {
-		if let Some(new_data) = Self::get_merged_data(
+		validate: impl FnOnce(&FullEntryData) -> Vec<FieldIssue>,
+	) -> Result<Vec<FieldIssue>, DbError> {
+		let merged = Self::get_merged_data(
 			"DRIVER",
 			team,
 			Some(match_id),
-			self.get_driver_entry_data(year, event, match_id, team)
-				.unwrap(),
+			self.get_driver_entry_data(year, event, match_id, team)?,
 			data,
-		) {
-			let data = serde_json::to_vec(&new_data)?;
-			self.driver_entries
-				.insert(Self::driver_entry_key(year, event, match_id, team), data)?;
-		}
-
-		Ok(())
+		);
+		Self::save_merged_data(
+			&self.driver_entries,
+			Self::driver_entry_key(year, event, match_id, team),
+			merged,
+			validate,
+		)
 	}
 	pub fn get_pit_entry_data(
 		&self,
@@ -209,80 +314,641 @@ impl Database {
 		event: &str,
 		team: &str,
 		data: FullEntryData,
-	) -> Result<(), DbError> {
-		if let Some(new_data) = Self::get_merged_data(
+		validate: impl FnOnce(&FullEntryData) -> Vec<FieldIssue>,
+	) -> Result<Vec<FieldIssue>, DbError> {
+		let merged = Self::get_merged_data(
 			"PIT",
 			team,
 			None,
-			self.get_pit_entry_data(year, event, team).unwrap(),
+			self.get_pit_entry_data(year, event, team)?,
 			data,
-		) {
-			let data = serde_json::to_vec(&new_data)?;
-			self.pit_entries
-				.insert(Self::pit_entry_key(year, event, team), data)?;
-		}
+		);
+		Self::save_merged_data(
+			&self.pit_entries,
+			Self::pit_entry_key(year, event, team),
+			merged,
+			validate,
+		)
+	}
+
+	pub fn get_pick_list(&self, year: u32, event: &str) -> Result<PickList, DbError> {
+		let value = self.pick_lists.get(Self::pick_list_key(year, event))?;
+		Ok(if let Some(val) = value {
+			serde_json::from_slice(&val)?
+		} else {
+			PickList::default()
+		})
+	}
+	pub fn update_pick_list(
+		&self,
+		year: u32,
+		event: &str,
+		changes: PickList,
+	) -> Result<PickList, DbError> {
+		let mut count = 0;
+		let new_value =
+			self.pick_lists
+				.update_and_fetch(Self::pick_list_key(year, event), |old_value| {
+					let mut pick_list = old_value
+						.and_then(|val| serde_json::from_slice::<PickList>(val).ok())
+						.unwrap_or_default();
+					count = pick_list.merge(changes.clone());
+					serde_json::to_vec(&pick_list).ok()
+				})?;
+		info!("Updating pick list for {year}{event} ({count} new items)");
+		Ok(if let Some(val) = new_value {
+			serde_json::from_slice(&val)?
+		} else {
+			PickList::default()
+		})
+	}
 
+	pub fn get_tba_event(
+		&self,
+		year: u32,
+		event: &str,
+	) -> Result<Option<CachedData<EventInfo>>, DbError> {
+		self.get_upstream_data(Self::tba_event_key(year, event))
+	}
+	pub fn set_tba_event(&self, event_info: &CachedData<EventInfo>) -> Result<(), DbError> {
+		self.set_upstream_data(
+			Self::tba_event_key(event_info.data.year, &event_info.data.event),
+			event_info,
+		)
+	}
+	pub fn get_all_tba_events(&self) -> Result<Vec<CachedData<EventInfo>>, DbError> {
+		self.get_all_upstream_data(TBA_EVENT_PREFIX)
+	}
+	pub fn get_frc_event(
+		&self,
+		year: u32,
+		event: &str,
+	) -> Result<Option<CachedData<EventInfo>>, DbError> {
+		self.get_upstream_data(Self::frc_event_key(year, event))
+	}
+	pub fn set_frc_event(&self, event_info: &CachedData<EventInfo>) -> Result<(), DbError> {
+		self.set_upstream_data(
+			Self::frc_event_key(event_info.data.year, &event_info.data.event),
+			event_info,
+		)
+	}
+	pub fn get_all_frc_events(&self) -> Result<Vec<CachedData<EventInfo>>, DbError> {
+		self.get_all_upstream_data(FRC_EVENT_PREFIX)
+	}
+	pub fn get_tba_avatar(
+		&self,
+		year: u32,
+		team: u32,
+	) -> Result<Option<CachedData<TeamAvatar>>, DbError> {
+		self.get_upstream_data(Self::tba_avatar_key(year, team))
+	}
+	pub fn set_tba_avatar(&self, avatar: &CachedData<TeamAvatar>) -> Result<(), DbError> {
+		self.set_upstream_data(
+			Self::tba_avatar_key(avatar.data.year, avatar.data.team),
+			avatar,
+		)
+	}
+	pub fn get_all_tba_avatars(&self) -> Result<Vec<CachedData<TeamAvatar>>, DbError> {
+		self.get_all_upstream_data(TBA_AVATAR_PREFIX)
+	}
+	pub fn get_statbotics_team(
+		&self,
+		year: u32,
+		event: &str,
+		team: u32,
+	) -> Result<Option<CachedData<SavedStatboticsTeam>>, DbError> {
+		self.get_upstream_data(Self::statbotics_team_key(year, event, team))
+	}
+	pub fn set_statbotics_team(
+		&self,
+		team: &CachedData<SavedStatboticsTeam>,
+	) -> Result<(), DbError> {
+		self.set_upstream_data(
+			Self::statbotics_team_key(team.data.year, &team.data.event, team.data.team),
+			team,
+		)
+	}
+	pub fn get_all_statbotics_teams(
+		&self,
+	) -> Result<Vec<CachedData<SavedStatboticsTeam>>, DbError> {
+		self.get_all_upstream_data(STATBOTICS_TEAM_PREFIX)
+	}
+	pub fn get_manual_event(&self, year: u32, event: &str) -> Result<Option<ManualEvent>, DbError> {
+		self.upstream_data
+			.get(Self::manual_event_key(year, event))?
+			.map(|value| Ok(serde_json::from_slice(&value)?))
+			.transpose()
+	}
+	pub fn set_manual_event(
+		&self,
+		year: u32,
+		event: &str,
+		manual_event: &ManualEvent,
+	) -> Result<(), DbError> {
+		self.upstream_data.insert(
+			Self::manual_event_key(year, event),
+			serde_json::to_vec(manual_event)?,
+		)?;
 		Ok(())
 	}
+	pub fn remove_manual_event(&self, year: u32, event: &str) -> Result<(), DbError> {
+		self.upstream_data
+			.remove(Self::manual_event_key(year, event))?;
+		Ok(())
+	}
+	fn get_upstream_data<T: DeserializeOwned>(
+		&self,
+		key: Vec<u8>,
+	) -> Result<Option<CachedData<T>>, DbError> {
+		self.upstream_data
+			.get(key)?
+			.map(|value| Ok(serde_json::from_slice(&value)?))
+			.transpose()
+	}
+	fn set_upstream_data<T: Serialize>(
+		&self,
+		key: Vec<u8>,
+		data: &CachedData<T>,
+	) -> Result<(), DbError> {
+		self.upstream_data.insert(key, serde_json::to_vec(data)?)?;
+		Ok(())
+	}
+	fn get_all_upstream_data<T: DeserializeOwned>(
+		&self,
+		prefix: &str,
+	) -> Result<Vec<CachedData<T>>, DbError> {
+		let mut prefix = prefix.as_bytes().to_vec();
+		prefix.push(255);
+		self.upstream_data
+			.scan_prefix(prefix)
+			.values()
+			.map(|value| Ok(serde_json::from_slice(&value?)?))
+			.collect()
+	}
 
-	pub fn write_image(&self, image: &DynamicImage, image_id: &str) -> Result<(), DbError> {
-		let mut image_data_full: Vec<u8> = Vec::new();
-		image.write_to(
-			&mut Cursor::new(&mut image_data_full),
-			image::ImageFormat::Jpeg,
+	/// Store the full and small versions of an image, in `format`. `unchanged` is the upload if it
+	/// is already in `format` and wasn't edited, it's stored as is when it doesn't need resizing so
+	/// photos aren't compressed twice.
+	pub fn write_image(
+		&self,
+		image: &DynamicImage,
+		format: ImageFormat,
+		unchanged: Option<&[u8]>,
+		image_id: &str,
+		config: &ImageConfig,
+	) -> Result<(), DbError> {
+		let full_image = fit_within(image, config.max_full_size);
+		let unchanged = unchanged.filter(|_| {
+			full_image.width() == image.width() && full_image.height() == image.height()
+		});
+		self.write_image_version(
+			&full_image,
+			format,
+			unchanged,
+			ImageSize::Full,
+			image_id,
+			config,
 		)?;
-		let full_image_data = ImageData {
-			mime_type: "image/jpeg".to_string(),
-			width: image.width(),
-			height: image.height(),
-			image_data: image_data_full,
+		self.write_image_version(
+			&fit_within(&full_image, Some(config.thumbnail_size)),
+			format,
+			None,
+			ImageSize::Small,
+			image_id,
+			config,
+		)?;
+		self.inner
+			.remove(Self::media_key(VIDEO_PREFIX_INFO, image_id))?;
+		self.set_media_written(image_id)
+	}
+	pub fn write_original_image(
+		&self,
+		image_id: &str,
+		image: &OriginalImage,
+	) -> Result<(), DbError> {
+		self.inner.insert(
+			Self::media_key(IMAGE_PREFIX_ORIGINAL, image_id),
+			bincode::serialize(image)?,
+		)?;
+		Ok(())
+	}
+	pub fn get_original_image(&self, image_id: &str) -> Result<Option<OriginalImage>, DbError> {
+		self.inner
+			.get(Self::media_key(IMAGE_PREFIX_ORIGINAL, image_id))?
+			.map(|image_bytes| Ok(Some(bincode::deserialize(&image_bytes)?)))
+			.unwrap_or(Ok(None))
+	}
+	/// Store a video in chunks, with a still image of it as the small image
+	pub fn write_video(
+		&self,
+		video: &[u8],
+		mime_type: &str,
+		thumbnail: &DynamicImage,
+		video_id: &str,
+		config: &ImageConfig,
+	) -> Result<(), DbError> {
+		let mut batch = Batch::default();
+		for key in self
+			.inner
+			.scan_prefix(Self::video_chunk_prefix(video_id))
+			.keys()
+		{
+			batch.remove(key?);
+		}
+		for (index, chunk) in video.chunks(VIDEO_CHUNK_SIZE).enumerate() {
+			batch.insert(Self::video_chunk_key(video_id, index as u32), chunk);
+		}
+		let info = VideoInfo {
+			mime_type: mime_type.to_string(),
+			size: video.len() as u64,
 		};
+		batch.insert(
+			Self::media_key(VIDEO_PREFIX_INFO, video_id),
+			bincode::serialize(&info)?,
+		);
+		batch.remove(Self::media_key(IMAGE_PREFIX_ORIGINAL, video_id));
+		batch.remove(Self::media_key(IMAGE_PREFIX_FULL, video_id));
+		batch.remove(Self::media_key(IMAGE_PREFIX_FULL_WEBP, video_id));
+		self.inner.apply_batch(batch)?;
 
-		let mut image_key_full = Vec::from(IMAGE_PREFIX_FULL);
-		image_key_full.extend(image_id.as_bytes());
-		let image_bytes = bincode::serialize(&full_image_data)?;
-		self.inner.insert(image_key_full, image_bytes)?;
-
-		// insert scaled-down image (or the same image if it is already small)
-		let mut image_key_small = Vec::from(IMAGE_PREFIX_SMALL);
-		image_key_small.extend(image_id.as_bytes());
-		if image.width() > IMAGE_SIZE_SMALL || image.height() > IMAGE_SIZE_SMALL {
-			let mut resized_image_data: Vec<u8> = Vec::new();
-			let resized_image =
-				image.resize(IMAGE_SIZE_SMALL, IMAGE_SIZE_SMALL, FilterType::Lanczos3);
-			resized_image.write_to(
-				&mut Cursor::new(&mut resized_image_data),
-				image::ImageFormat::Jpeg,
-			)?;
-			let image_bytes = bincode::serialize(&ImageData {
-				mime_type: "image/jpeg".to_string(),
-				width: resized_image.width(),
-				height: resized_image.height(),
-				image_data: resized_image_data,
-			})?;
-			self.inner.insert(image_key_small, image_bytes)?;
+		self.write_image_version(
+			&fit_within(thumbnail, Some(config.thumbnail_size)),
+			ImageFormat::Jpeg,
+			None,
+			ImageSize::Small,
+			video_id,
+			config,
+		)?;
+		self.set_media_written(video_id)
+	}
+	pub fn get_video_info(&self, id: &str) -> Result<Option<VideoInfo>, DbError> {
+		self.inner
+			.get(Self::media_key(VIDEO_PREFIX_INFO, id))?
+			.map(|info_bytes| Ok(Some(bincode::deserialize(&info_bytes)?)))
+			.unwrap_or(Ok(None))
+	}
+	/// Read the bytes from `start` up to (not including) `end` of a video, only loading the chunks needed.
+	/// Returns `None` if part of the video is missing.
+	pub fn read_video(&self, id: &str, start: u64, end: u64) -> Result<Option<Vec<u8>>, DbError> {
+		let chunk_size = VIDEO_CHUNK_SIZE as u64;
+		let mut data = Vec::with_capacity(end.saturating_sub(start) as usize);
+		let mut position = start;
+		while position < end {
+			let index = position / chunk_size;
+			let Some(chunk) = self.inner.get(Self::video_chunk_key(id, index as u32))? else {
+				return Ok(None);
+			};
+			let chunk_start = index * chunk_size;
+			let from = (position - chunk_start) as usize;
+			let to = ((end - chunk_start) as usize).min(chunk.len());
+			if from >= to {
+				return Ok(None);
+			}
+			data.extend_from_slice(&chunk[from..to]);
+			position = chunk_start + to as u64;
+		}
+		Ok(Some(data))
+	}
+	/// Get a stored image, preferring the WebP version if `webp` is set and there is one
+	pub fn get_image(
+		&self,
+		id: &str,
+		size: ImageSize,
+		webp: bool,
+	) -> Result<Option<ImageData>, DbError> {
+		let webp_image = if webp {
+			self.inner.get(Self::media_key(size.prefix(true), id))?
 		} else {
-			let image_bytes = bincode::serialize(&full_image_data)?;
-			self.inner.insert(image_key_small, image_bytes)?;
+			None
+		};
+		match webp_image {
+			Some(image_bytes) => Ok(Some(bincode::deserialize(&image_bytes)?)),
+			None => self
+				.inner
+				.get(Self::media_key(size.prefix(false), id))?
+				.map(|image_bytes| Ok(Some(bincode::deserialize(&image_bytes)?)))
+				.unwrap_or(Ok(None)),
 		}
+	}
 
+	/// Every image and video id that has something stored
+	pub fn get_all_media_ids(&self) -> Result<HashSet<String>, DbError> {
+		let mut ids = HashSet::new();
+		for prefix in MEDIA_PREFIXES {
+			for key in self.inner.scan_prefix(prefix).keys() {
+				ids.insert(String::from_utf8_lossy(&key?[prefix.len()..]).to_string());
+			}
+		}
+		Ok(ids)
+	}
+	/// Every image and video attached to a match, driver, or pit entry, for any event
+	pub fn get_all_media_references(&self) -> Vec<MediaReference> {
+		[&self.match_entries, &self.driver_entries, &self.pit_entries]
+			.into_iter()
+			.flat_map(|tree| tree.iter().flatten())
+			.filter_map(|(k, v)| {
+				let (year, event) = Self::entry_key_event(&k)?;
+				let data: StoredEntryData = serde_json::from_slice(&v).ok()?;
+				Some(
+					data.entries
+						.into_values()
+						.filter_map(|value| match value {
+							MatchEntryValue::Image(image_entry) => Some(image_entry.images),
+							_ => None,
+						})
+						.flatten()
+						.map(move |image| MediaReference {
+							year,
+							event: event.clone(),
+							media_id: image.image_id,
+						}),
+				)
+			})
+			.flatten()
+			.collect()
+	}
+	/// The number of bytes stored for an image or video, including all of its versions
+	pub fn get_media_size(&self, id: &str) -> Result<u64, DbError> {
+		let mut size = 0;
+		for prefix in MEDIA_PREFIXES {
+			if let Some(value) = self.inner.get(Self::media_key(prefix, id))? {
+				size += value.len() as u64;
+			}
+		}
+		for value in self
+			.inner
+			.scan_prefix(Self::video_chunk_prefix(id))
+			.values()
+		{
+			size += value?.len() as u64;
+		}
+		Ok(size)
+	}
+	/// When an image or video was last uploaded or edited, `None` if it was before this was recorded
+	pub fn get_media_written_ms(&self, id: &str) -> Result<Option<u64>, DbError> {
+		Ok(self
+			.inner
+			.get(Self::media_key(MEDIA_PREFIX_WRITTEN, id))?
+			.and_then(|value| Some(u64::from_le_bytes(value.as_ref().try_into().ok()?))))
+	}
+	pub fn remove_media(&self, id: &str) -> Result<(), DbError> {
+		let mut batch = Batch::default();
+		if let Some(hash) = self.get_media_hash(id)? {
+			if self.get_media_by_hash(&hash)?.as_deref() == Some(id) {
+				batch.remove(Self::media_key(MEDIA_PREFIX_BY_HASH, &hash));
+			}
+		}
+		for prefix in MEDIA_PREFIXES {
+			batch.remove(Self::media_key(prefix, id));
+		}
+		for key in self.inner.scan_prefix(Self::video_chunk_prefix(id)).keys() {
+			batch.remove(key?);
+		}
+		self.inner.apply_batch(batch)?;
 		Ok(())
 	}
-	pub fn get_image(&self, id: &str, size: ImageSize) -> Result<Option<ImageData>, DbError> {
-		let mut key = Vec::from(match size {
-			ImageSize::Full => IMAGE_PREFIX_FULL,
-			ImageSize::Small => IMAGE_PREFIX_SMALL,
-		});
-		key.extend(id.as_bytes());
+
+	/// Whether an image or video has been saved
+	pub fn has_media(&self, id: &str) -> Result<bool, DbError> {
+		// Images and videos always have a small image
+		Ok(self
+			.inner
+			.contains_key(Self::media_key(IMAGE_PREFIX_SMALL, id))?)
+	}
+	pub fn get_media_hash(&self, id: &str) -> Result<Option<String>, DbError> {
+		Ok(self
+			.inner
+			.get(Self::media_key(MEDIA_PREFIX_HASH, id))?
+			.map(|hash| String::from_utf8_lossy(&hash).to_string()))
+	}
+	pub fn set_media_hash(&self, id: &str, hash: &str) -> Result<(), DbError> {
+		let mut batch = Batch::default();
+		batch.insert(Self::media_key(MEDIA_PREFIX_HASH, id), hash.as_bytes());
+		batch.insert(Self::media_key(MEDIA_PREFIX_BY_HASH, hash), id.as_bytes());
+		self.inner.apply_batch(batch)?;
+		Ok(())
+	}
+	/// Get the id of a saved image or video with the given hash, if there is one
+	pub fn get_media_by_hash(&self, hash: &str) -> Result<Option<String>, DbError> {
+		Ok(self
+			.inner
+			.get(Self::media_key(MEDIA_PREFIX_BY_HASH, hash))?
+			.map(|id| String::from_utf8_lossy(&id).to_string()))
+	}
+	/// Save an image or video under another id by copying what is stored for it, so the same upload
+	/// isn't decoded and encoded again. Edits belong to the entry they were made for, so nothing is
+	/// copied from an image that has been edited and this gives false.
+	pub fn copy_media(&self, from: &str, to: &str) -> Result<bool, DbError> {
+		if self
+			.get_original_image(from)?
+			.is_some_and(|original| !original.edits.is_empty())
+		{
+			return Ok(false);
+		}
+		let mut batch = Batch::default();
+		for prefix in MEDIA_PREFIXES {
+			match self.inner.get(Self::media_key(prefix, from))? {
+				Some(value) => batch.insert(Self::media_key(prefix, to), value),
+				None => batch.remove(Self::media_key(prefix, to)),
+			}
+		}
+		for key in self.inner.scan_prefix(Self::video_chunk_prefix(to)).keys() {
+			batch.remove(key?);
+		}
+		let from_prefix = Self::video_chunk_prefix(from);
+		for item in self.inner.scan_prefix(&from_prefix) {
+			let (key, value) = item?;
+			let mut to_key = Self::video_chunk_prefix(to);
+			to_key.extend_from_slice(&key[from_prefix.len()..]);
+			batch.insert(to_key, value);
+		}
+		batch.insert(
+			Self::media_key(MEDIA_PREFIX_WRITTEN, to),
+			&now_ms().to_le_bytes(),
+		);
+		self.inner.apply_batch(batch)?;
+		Ok(true)
+	}
+
+	pub fn get_upload_info(&self, id: &str) -> Result<Option<UploadInfo>, DbError> {
 		self.inner
-			.get(key)?
-			.map(|image_bytes| {
-				let image: ImageData = bincode::deserialize(&image_bytes)?;
-				Ok(Some(image))
-			})
+			.get(Self::media_key(UPLOAD_PREFIX_INFO, id))?
+			.map(|info_bytes| Ok(Some(bincode::deserialize(&info_bytes)?)))
 			.unwrap_or(Ok(None))
 	}
+	/// Add the next part of a chunked upload, starting it if this is the first part. `offset` has
+	/// to be the number of bytes received so far, so a chunk that is sent twice isn't added twice.
+	pub fn append_upload(&self, id: &str, offset: u64, data: &[u8]) -> Result<UploadInfo, DbError> {
+		let info_key = Self::media_key(UPLOAD_PREFIX_INFO, id);
+		let info = self.inner.transaction(|tx| {
+			let mut info = match tx.get(&info_key)? {
+				Some(info_bytes) => bincode::deserialize(&info_bytes)
+					.map_err(|err| ConflictableTransactionError::Abort(DbError::Bincode(err)))?,
+				None => UploadInfo {
+					received: 0,
+					chunks: 0,
+					updated_ms: 0,
+				},
+			};
+			if info.received != offset {
+				return Err(ConflictableTransactionError::Abort(DbError::UploadOffset {
+					received: info.received,
+					offset,
+				}));
+			}
+			tx.insert(Self::upload_chunk_key(id, info.chunks), data)?;
+			info.received += data.len() as u64;
+			info.chunks += 1;
+			info.updated_ms = now_ms();
+			let info_bytes = bincode::serialize(&info)
+				.map_err(|err| ConflictableTransactionError::Abort(DbError::Bincode(err)))?;
+			tx.insert(info_key.as_slice(), info_bytes)?;
+			Ok(info)
+		})?;
+		Ok(info)
+	}
+	/// Get all of the data for a chunked upload, `None` if it was never started. It's kept until
+	/// it is removed once saved, but if any of it is missing it is removed and has to be started
+	/// again.
+	pub fn get_upload(&self, id: &str) -> Result<Option<Vec<u8>>, DbError> {
+		let info_key = Self::media_key(UPLOAD_PREFIX_INFO, id);
+		let data = self.inner.transaction(|tx| {
+			let Some(info_bytes) = tx.get(&info_key)? else {
+				return Ok(None);
+			};
+			let info: UploadInfo = bincode::deserialize(&info_bytes)
+				.map_err(|err| ConflictableTransactionError::Abort(DbError::Bincode(err)))?;
+			let mut data = Vec::with_capacity(info.received as usize);
+			for index in 0..info.chunks {
+				match tx.get(Self::upload_chunk_key(id, index))? {
+					Some(chunk) => data.extend_from_slice(&chunk),
+					None => return Ok(Some(Err(DbError::UploadIncomplete))),
+				}
+			}
+			if data.len() as u64 != info.received {
+				return Ok(Some(Err(DbError::UploadIncomplete)));
+			}
+			Ok(Some(Ok(data)))
+		})?;
+		match data {
+			Some(Err(err)) => {
+				self.remove_upload(id)?;
+				Err(err)
+			}
+			Some(Ok(data)) => Ok(Some(data)),
+			None => Ok(None),
+		}
+	}
+	pub fn remove_upload(&self, id: &str) -> Result<(), DbError> {
+		let mut batch = Batch::default();
+		batch.remove(Self::media_key(UPLOAD_PREFIX_INFO, id));
+		for key in self.inner.scan_prefix(Self::upload_chunk_prefix(id)).keys() {
+			batch.remove(key?);
+		}
+		self.inner.apply_batch(batch)?;
+		Ok(())
+	}
+	/// Every chunked upload that hasn't been finished
+	pub fn get_all_uploads(&self) -> Result<Vec<(String, UploadInfo)>, DbError> {
+		self.inner
+			.scan_prefix(UPLOAD_PREFIX_INFO)
+			.map(|item| {
+				let (key, value) = item?;
+				Ok((
+					String::from_utf8_lossy(&key[UPLOAD_PREFIX_INFO.len()..]).to_string(),
+					bincode::deserialize(&value)?,
+				))
+			})
+			.collect()
+	}
 
+	fn set_media_written(&self, id: &str) -> Result<(), DbError> {
+		self.inner.insert(
+			Self::media_key(MEDIA_PREFIX_WRITTEN, id),
+			&now_ms().to_le_bytes(),
+		)?;
+		Ok(())
+	}
+	/// Store one size of an image, encoding it in `format` unless it's already `encoded`
+	fn write_image_version(
+		&self,
+		image: &DynamicImage,
+		format: ImageFormat,
+		encoded: Option<&[u8]>,
+		size: ImageSize,
+		image_id: &str,
+		config: &ImageConfig,
+	) -> Result<(), DbError> {
+		let image_data = ImageData {
+			mime_type: format.to_mime_type().to_string(),
+			width: image.width(),
+			height: image.height(),
+			image_data: match encoded {
+				Some(encoded) => encoded.to_vec(),
+				None => media::encode_image(image, format)?,
+			},
+		};
+		let webp_key = Self::media_key(size.prefix(true), image_id);
+		// Only lossless WebP can be encoded, which is usually smaller than a PNG but never than a
+		// JPEG of a photo, so it isn't worth encoding for anything else
+		let webp_data = if config.webp && format == ImageFormat::Png {
+			Some(media::encode_image(image, ImageFormat::WebP)?)
+		} else {
+			None
+		};
+		match webp_data {
+			Some(webp_data) if webp_data.len() < image_data.image_data.len() => {
+				let webp_image = ImageData {
+					mime_type: ImageFormat::WebP.to_mime_type().to_string(),
+					image_data: webp_data,
+					..image_data.clone()
+				};
+				self.inner
+					.insert(webp_key, bincode::serialize(&webp_image)?)?;
+			}
+			_ => {
+				self.inner.remove(webp_key)?;
+			}
+		}
+		self.inner.insert(
+			Self::media_key(size.prefix(false), image_id),
+			bincode::serialize(&image_data)?,
+		)?;
+		Ok(())
+	}
+
+	fn media_key(prefix: &str, id: &str) -> Vec<u8> {
+		let mut bytes = Vec::from(prefix);
+		bytes.extend(id.as_bytes());
+		bytes
+	}
+	fn video_chunk_prefix(id: &str) -> Vec<u8> {
+		let mut bytes = Self::media_key(VIDEO_PREFIX_CHUNK, id);
+		bytes.push(255);
+		bytes
+	}
+	fn upload_chunk_prefix(id: &str) -> Vec<u8> {
+		let mut bytes = Self::media_key(UPLOAD_PREFIX_CHUNK, id);
+		bytes.push(255);
+		bytes
+	}
+	fn upload_chunk_key(id: &str, index: u32) -> Vec<u8> {
+		let mut bytes = Self::upload_chunk_prefix(id);
+		bytes.extend_from_slice(&index.to_be_bytes());
+		bytes
+	}
+	fn video_chunk_key(id: &str, index: u32) -> Vec<u8> {
+		let mut bytes = Self::video_chunk_prefix(id);
+		bytes.extend_from_slice(&index.to_be_bytes());
+		bytes
+	}
+	/// Get the year and event from the key of a match, driver, or pit entry
+	fn entry_key_event(key: &[u8]) -> Option<(u32, String)> {
+		let start = key.iter().position(|n| *n == 255)? + 1;
+		let year = u32::from_le_bytes(key.get(start..start + 4)?.try_into().ok()?);
+		let event = key.get(start + 5..)?.split(|n| *n == 255).next()?;
+		Some((year, String::from_utf8_lossy(event).to_string()))
+	}
 	fn driver_entry_prefix(year: u32, event: &str) -> Vec<u8> {
 		let mut bytes = "driver_entry".as_bytes().to_vec();
 		bytes.push(255);
@@ -330,6 +996,58 @@ impl Database {
 		bytes
 	}
 
+	fn pick_list_key(year: u32, event: &str) -> Vec<u8> {
+		let mut bytes = "pick_list".as_bytes().to_vec();
+		bytes.push(255);
+		bytes.extend_from_slice(&year.to_le_bytes());
+		bytes.push(255);
+		bytes.extend_from_slice(event.as_bytes());
+		bytes
+	}
+
+	fn tba_event_key(year: u32, event: &str) -> Vec<u8> {
+		let mut bytes = TBA_EVENT_PREFIX.as_bytes().to_vec();
+		bytes.push(255);
+		bytes.extend_from_slice(&year.to_le_bytes());
+		bytes.push(255);
+		bytes.extend_from_slice(event.as_bytes());
+		bytes
+	}
+	fn frc_event_key(year: u32, event: &str) -> Vec<u8> {
+		let mut bytes = FRC_EVENT_PREFIX.as_bytes().to_vec();
+		bytes.push(255);
+		bytes.extend_from_slice(&year.to_le_bytes());
+		bytes.push(255);
+		bytes.extend_from_slice(event.as_bytes());
+		bytes
+	}
+	fn manual_event_key(year: u32, event: &str) -> Vec<u8> {
+		let mut bytes = MANUAL_EVENT_PREFIX.as_bytes().to_vec();
+		bytes.push(255);
+		bytes.extend_from_slice(&year.to_le_bytes());
+		bytes.push(255);
+		bytes.extend_from_slice(event.as_bytes());
+		bytes
+	}
+	fn tba_avatar_key(year: u32, team: u32) -> Vec<u8> {
+		let mut bytes = TBA_AVATAR_PREFIX.as_bytes().to_vec();
+		bytes.push(255);
+		bytes.extend_from_slice(&year.to_le_bytes());
+		bytes.push(255);
+		bytes.extend_from_slice(&team.to_le_bytes());
+		bytes
+	}
+	fn statbotics_team_key(year: u32, event: &str, team: u32) -> Vec<u8> {
+		let mut bytes = STATBOTICS_TEAM_PREFIX.as_bytes().to_vec();
+		bytes.push(255);
+		bytes.extend_from_slice(&year.to_le_bytes());
+		bytes.push(255);
+		bytes.extend_from_slice(event.as_bytes());
+		bytes.push(255);
+		bytes.extend_from_slice(&team.to_le_bytes());
+		bytes
+	}
+
 	fn get_merged_data(
 		data_type: &str,
 		team: &str,
@@ -388,6 +1106,27 @@ impl Database {
 		}
 	}
 
+	/// Checks the merged entry as a whole and replaces the stored one with it, so values the checks
+	/// remove are removed from the database too. Nothing is written if the entry is rejected.
+	fn save_merged_data(
+		tree: &Tree,
+		key: Vec<u8>,
+		merged: Option<FullEntryData>,
+		validate: impl FnOnce(&FullEntryData) -> Vec<FieldIssue>,
+	) -> Result<Vec<FieldIssue>, DbError> {
+		let Some(merged) = merged else {
+			return Ok(Vec::new());
+		};
+		let issues = validate(&merged);
+		if issues
+			.iter()
+			.all(|issue| issue.action != IssueAction::Rejected)
+		{
+			tree.insert(key, serde_json::to_vec(&merged)?)?;
+		}
+		Ok(issues)
+	}
+
 	fn fill_entry_data(year: u32, event: &str, data: StoredEntryData) -> FullEntryData {
 		FullEntryData {
 			year,
@@ -404,11 +1143,149 @@ impl Database {
 		let driver_entries = db.open_tree("driver_entires".as_bytes())?;
 		let match_entries = db.open_tree("match_entires".as_bytes())?;
 		let pit_entries = db.open_tree("pit_entires".as_bytes())?;
+		let pick_lists = db.open_tree("pick_lists".as_bytes())?;
+		let upstream_data = db.open_tree("upstream_data".as_bytes())?;
 		Ok(Database {
 			inner: db,
 			driver_entries,
 			match_entries,
 			pit_entries,
+			pick_lists,
+			upstream_data,
 		})
 	}
 }
+
+/// The current time in milliseconds since the unix epoch
+pub fn now_ms() -> u64 {
+	SystemTime::now()
+		.duration_since(UNIX_EPOCH)
+		.map(|duration| duration.as_millis() as u64)
+		.unwrap_or_default()
+}
+
+/// Scale an image down so that neither side is longer than `max_size`, keeping its aspect ratio
+fn fit_within(image: &DynamicImage, max_size: Option<u32>) -> DynamicImage {
+	match max_size {
+		Some(max_size) if image.width() > max_size || image.height() > max_size => {
+			image.resize(max_size, max_size, FilterType::Lanczos3)
+		}
+		_ => image.clone(),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use serde_json::json;
+
+	use super::*;
+	use crate::api::data::{MatchAbilityEntry, MatchAbilityValue};
+	use crate::config::match_entry::MatchEntryFields;
+	use crate::data_validation::{apply_implications, check_required};
+
+	fn fields() -> MatchEntryFields {
+		let ability = |implies: &[&str]| {
+			json!({
+				"title": "", "description": "", "page": "", "required": false, "show_if": null,
+				"entry": { "type": "ability", "implies": [] }, "implies": implies,
+			})
+		};
+		serde_json::from_value(json!({
+			"pages": [],
+			"entries": { "high-climb": ability(&["low-climb"]), "low-climb": ability(&[]) },
+			"presence_entry": null,
+		}))
+		.unwrap()
+	}
+
+	fn entry(values: &[(&str, MatchAbilityValue, u64)]) -> FullEntryData {
+		FullEntryData {
+			year: 2026,
+			event: "2026test".to_string(),
+			entries: values
+				.iter()
+				.map(|(field, value, timestamp_ms)| {
+					(
+						field.to_string(),
+						MatchEntryValue::Ability(MatchAbilityEntry {
+							value: value.clone(),
+							scout: "scout".to_string(),
+							timestamp_ms: *timestamp_ms,
+						}),
+					)
+				})
+				.collect(),
+			timestamp_ms: None,
+		}
+	}
+
+	fn merge(old: FullEntryData, new: FullEntryData) -> FullEntryData {
+		merge_old(Some(old), new)
+	}
+
+	fn merge_old(old: Option<FullEntryData>, new: FullEntryData) -> FullEntryData {
+		Database::get_merged_data("MATCH", "1", Some("qm1"), old, new).unwrap()
+	}
+
+	#[test]
+	fn direct_values_replace_implied_ones() {
+		let mut saved = entry(&[("high-climb", MatchAbilityValue::Succeeded, 100)]);
+		apply_implications(&mut saved, &fields());
+		assert_eq!(saved.entries["low-climb"].get_timestamp(), 0);
+
+		// Recorded before the high climb, but still what the scout said about the low climb
+		let merged = merge(
+			saved,
+			entry(&[("low-climb", MatchAbilityValue::Attempted, 50)]),
+		);
+		assert_eq!(
+			merged.entries["low-climb"],
+			entry(&[("low-climb", MatchAbilityValue::Attempted, 50)]).entries["low-climb"]
+		);
+	}
+
+	#[test]
+	fn implied_values_never_replace_direct_ones() {
+		let saved = entry(&[("low-climb", MatchAbilityValue::Attempted, 50)]);
+		let mut incoming = entry(&[("high-climb", MatchAbilityValue::Succeeded, 100)]);
+		apply_implications(&mut incoming, &fields());
+
+		let merged = merge(saved.clone(), incoming);
+		assert_eq!(merged.entries["low-climb"], saved.entries["low-climb"]);
+	}
+
+	#[test]
+	fn implied_values_follow_corrections() {
+		let tree = sled::Config::new()
+			.temporary(true)
+			.open()
+			.unwrap()
+			.open_tree("MATCH")
+			.unwrap();
+		let save = |new: FullEntryData| {
+			let old = tree
+				.get("qm1")
+				.unwrap()
+				.map(|saved| serde_json::from_slice(&saved).unwrap());
+			let issues = Database::save_merged_data(
+				&tree,
+				b"qm1".to_vec(),
+				Some(merge_old(old, new)),
+				|merged| check_required(merged, &fields()),
+			)
+			.unwrap();
+			assert!(issues.is_empty());
+			let mut saved: FullEntryData =
+				serde_json::from_slice(&tree.get("qm1").unwrap().unwrap()).unwrap();
+			assert!(!saved.entries.contains_key("low-climb"));
+			apply_implications(&mut saved, &fields());
+			saved
+		};
+
+		let saved = save(entry(&[("high-climb", MatchAbilityValue::Succeeded, 100)]));
+		assert!(saved.entries.contains_key("low-climb"));
+		// The scout got the climb wrong, so the low climb it implied goes away too
+		let saved = save(entry(&[("high-climb", MatchAbilityValue::Nothing, 200)]));
+		assert!(!saved.entries.contains_key("low-climb"));
+	}
+}
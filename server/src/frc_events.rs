@@ -0,0 +1,691 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use std::time::Duration;
+
+use color_eyre::Result;
+use log::{error, info};
+use reqwest::Client;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+
+use crate::config::{FrcEventsConfig, FrcEventsRole, GameConfig};
+use crate::tba::{
+	custom_entries_for, CustomEntries, EventInfo, MatchId, MatchInfo, MatchResult,
+	RawTbaScoreBreakdownValue, RawTbaTeamInfo, SetMatch,
+};
+use crate::upstream::{self, UpstreamHealth, UpstreamStatus};
+
+const FRC_EVENTS_URL: &str = "https://frc-api.firstinspires.org/v3.0";
+
+/// How long to wait for the FRC Events API, the same as for TBA
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// A client for the FRC Events API, which loads events into the same form as TBA
+#[derive(Debug)]
+pub struct FrcEvents {
+	client: Client,
+	username: String,
+	auth_key: String,
+	role: FrcEventsRole,
+	status: Arc<UpstreamStatus>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FrcTeamList {
+	pub teams: Vec<FrcTeam>,
+	#[serde(default)]
+	pub page_total: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FrcTeam {
+	pub team_number: u32,
+	pub name_short: Option<String>,
+	pub name_full: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct FrcSchedule {
+	#[serde(rename = "Schedule")]
+	pub schedule: Vec<FrcScheduleMatch>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FrcScheduleMatch {
+	pub match_number: u32,
+	pub start_time: Option<String>,
+	pub tournament_level: String,
+	pub teams: Vec<FrcScheduleTeam>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FrcScheduleTeam {
+	pub team_number: Option<u32>,
+	pub station: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+struct FrcMatches {
+	#[serde(rename = "Matches")]
+	matches: Vec<FrcMatch>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FrcMatch {
+	match_number: u32,
+	actual_start_time: Option<String>,
+	score_red_final: Option<i32>,
+	score_blue_final: Option<i32>,
+	teams: Vec<FrcScheduleTeam>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+struct FrcMatchScores {
+	#[serde(rename = "MatchScores")]
+	match_scores: Vec<FrcMatchScore>,
+}
+
+/// The score breakdown for a match, which uses the same names as TBA's for most games
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FrcMatchScore {
+	match_number: u32,
+	alliances: Vec<HashMap<String, RawTbaScoreBreakdownValue>>,
+}
+
+impl FrcScheduleMatch {
+	pub fn match_id(&self) -> Option<MatchId> {
+		match_id(&self.tournament_level, self.match_number)
+	}
+}
+
+/// The teams in an alliance, where `colour` is `Red` or `Blue`
+pub fn alliance_teams(teams: &[FrcScheduleTeam], colour: &str) -> Vec<u32> {
+	teams
+		.iter()
+		.filter(|team| team.station.starts_with(colour))
+		.filter_map(|team| team.team_number)
+		.collect()
+}
+
+/// The match id TBA would use. Playoffs are numbered through the whole double elimination bracket,
+/// which is 13 matches then the finals.
+fn match_id(tournament_level: &str, match_number: u32) -> Option<MatchId> {
+	match tournament_level {
+		"Practice" => Some(MatchId::Practice(SetMatch {
+			set: 1,
+			num: match_number,
+		})),
+		"Qualification" => Some(MatchId::Qualification(SetMatch {
+			set: 1,
+			num: match_number,
+		})),
+		"Playoff" if match_number <= 13 => Some(MatchId::Semifinal(SetMatch {
+			set: match_number,
+			num: 1,
+		})),
+		"Playoff" => Some(MatchId::Final(SetMatch {
+			set: 1,
+			num: match_number - 13,
+		})),
+		_ => None,
+	}
+}
+
+impl FrcEvents {
+	/// Returns `None` if the FRC Events API isn't used
+	pub fn new(config: &FrcEventsConfig) -> Result<Option<FrcEvents>> {
+		if config.role == FrcEventsRole::Disabled {
+			return Ok(None);
+		}
+		Ok(Some(FrcEvents {
+			client: Client::builder()
+				.user_agent(env!("CARGO_PKG_NAME"))
+				.timeout(REQUEST_TIMEOUT)
+				.build()?,
+			username: config.username.clone(),
+			auth_key: config.auth_key.clone(),
+			role: config.role,
+			status: Arc::new(UpstreamStatus::default()),
+		}))
+	}
+
+	pub fn role(&self) -> FrcEventsRole {
+		self.role
+	}
+
+	pub fn health(&self) -> UpstreamHealth {
+		self.status.health("FRC Events")
+	}
+
+	/// Load the teams, schedule, and results for an event. TBA event keys are used, which are the
+	/// year followed by the FRC event code.
+	pub async fn load_event(
+		&self,
+		game_config: &GameConfig,
+		year: u32,
+		event: &str,
+	) -> Result<EventInfo> {
+		info!("FRC Events ({event}): Loading data");
+		let code = event
+			.strip_prefix(&year.to_string())
+			.unwrap_or(event)
+			.to_uppercase();
+		let loaded = tokio::try_join!(
+			self.load_teams(year, &code),
+			self.load_matches(game_config, year, &code, "Qualification"),
+			self.load_matches(game_config, year, &code, "Playoff"),
+		);
+		match loaded {
+			Ok((teams, mut match_infos, playoff_matches)) => {
+				info!("FRC Events ({event}): load complete");
+				self.status.record_success(event);
+				match_infos.extend(playoff_matches);
+				Ok(EventInfo::from_matches(match_infos, teams, year, event))
+			}
+			Err(err) => {
+				error!("FRC Events ({event}): load error: {err}");
+				if upstream::is_unreachable(&err) {
+					self.status.record_failure(&err);
+				}
+				Err(err)
+			}
+		}
+	}
+
+	async fn get<T: DeserializeOwned>(&self, path: String) -> Result<T> {
+		Ok(self
+			.client
+			.get(format!("{FRC_EVENTS_URL}/{path}"))
+			.basic_auth(&self.username, Some(&self.auth_key))
+			.send()
+			.await?
+			.error_for_status()?
+			.json()
+			.await?)
+	}
+
+	async fn load_teams(&self, year: u32, code: &str) -> Result<Vec<RawTbaTeamInfo>> {
+		let mut teams = Vec::new();
+		let mut page = 1;
+		loop {
+			let team_list = self
+				.get::<FrcTeamList>(format!("{year}/teams?eventCode={code}&page={page}"))
+				.await?;
+			teams.extend(team_list.teams.into_iter().map(|team| {
+				RawTbaTeamInfo {
+					num: team.team_number,
+					name: team
+						.name_short
+						.or(team.name_full)
+						.unwrap_or_else(|| "unknown".to_string()),
+					// Avatars only come from TBA
+					has_avatar: false,
+				}
+			}));
+			if page >= team_list.page_total {
+				return Ok(teams);
+			}
+			page += 1;
+		}
+	}
+
+	/// Load the schedule for a tournament level, with the results of the matches that have been played
+	async fn load_matches(
+		&self,
+		game_config: &GameConfig,
+		year: u32,
+		code: &str,
+		level: &str,
+	) -> Result<Vec<MatchInfo>> {
+		let (schedule, results, scores) = tokio::try_join!(
+			self.get::<FrcSchedule>(format!("{year}/schedule/{code}?tournamentLevel={level}")),
+			self.get::<FrcMatches>(format!("{year}/matches/{code}?tournamentLevel={level}")),
+			self.get::<FrcMatchScores>(format!("{year}/scores/{code}/{level}")),
+		)?;
+		let mut results = results
+			.matches
+			.into_iter()
+			.map(|result| (result.match_number, result))
+			.collect::<BTreeMap<_, _>>();
+		let mut scores = scores
+			.match_scores
+			.into_iter()
+			.map(|score| (score.match_number, score))
+			.collect::<HashMap<_, _>>();
+
+		let mut match_infos = Vec::new();
+		for scheduled in schedule.schedule {
+			let result = results.remove(&scheduled.match_number);
+			let score = scores.remove(&scheduled.match_number);
+			let Some(id) = scheduled.match_id() else {
+				continue;
+			};
+			let start_time = result
+				.as_ref()
+				.and_then(|result| result.actual_start_time.as_deref())
+				.or(scheduled.start_time.as_deref())
+				.and_then(parse_time)
+				.unwrap_or_default();
+			match_infos.push(into_match(
+				game_config,
+				id,
+				start_time,
+				&scheduled.teams,
+				result.as_ref(),
+				score,
+			));
+		}
+		// Replayed matches can have results without being in the schedule
+		for (match_number, result) in results {
+			let Some(id) = match_id(level, match_number) else {
+				continue;
+			};
+			let start_time = result
+				.actual_start_time
+				.as_deref()
+				.and_then(parse_time)
+				.unwrap_or_default();
+			let score = scores.remove(&match_number);
+			match_infos.push(into_match(
+				game_config,
+				id,
+				start_time,
+				&result.teams,
+				Some(&result),
+				score,
+			));
+		}
+		Ok(match_infos)
+	}
+}
+
+fn into_match(
+	game_config: &GameConfig,
+	id: MatchId,
+	start_time: u64,
+	scheduled_teams: &[FrcScheduleTeam],
+	result: Option<&FrcMatch>,
+	score: Option<FrcMatchScore>,
+) -> MatchInfo {
+	// The teams that actually played, if they've been posted
+	let teams = result.map_or(scheduled_teams, |result| &result.teams);
+	let score_red = result
+		.and_then(|result| result.score_red_final)
+		.and_then(|score| u16::try_from(score).ok());
+	let score_blue = result
+		.and_then(|result| result.score_blue_final)
+		.and_then(|score| u16::try_from(score).ok());
+	let breakdown = |colour: &str| {
+		score.as_ref().and_then(|score| {
+			score
+				.alliances
+				.iter()
+				.find(|alliance| {
+					matches!(alliance.get("alliance"), Some(RawTbaScoreBreakdownValue::String(name)) if name == colour)
+				})
+				.cloned()
+		})
+	};
+	let (breakdown_red, breakdown_blue) = (breakdown("Red"), breakdown("Blue"));
+	let rp = |breakdown: &Option<HashMap<String, RawTbaScoreBreakdownValue>>| match breakdown
+		.as_ref()
+		.and_then(|breakdown| breakdown.get("rp"))
+	{
+		Some(RawTbaScoreBreakdownValue::Number(rp)) => u16::try_from(*rp).ok(),
+		_ => None,
+	};
+	MatchInfo {
+		id,
+		start_time,
+		teams_blue: alliance_teams(teams, "Blue"),
+		teams_red: alliance_teams(teams, "Red"),
+		result: match (score_red, score_blue) {
+			(Some(red), Some(blue)) if red > blue => MatchResult::Red,
+			(Some(red), Some(blue)) if red < blue => MatchResult::Blue,
+			(Some(_), Some(_)) => MatchResult::Tie,
+			_ => MatchResult::Tbd,
+		},
+		score_blue,
+		score_red,
+		rp_blue: rp(&breakdown_blue),
+		rp_red: rp(&breakdown_red),
+		custom_entries: CustomEntries {
+			blue: custom_entries_for(game_config, breakdown_blue),
+			red: custom_entries_for(game_config, breakdown_red),
+		},
+	}
+}
+
+/// Combine an event loaded from TBA and the FRC Events API. Whichever source has posted a match's
+/// results first is used for them, and `role` decides which is used when both have.
+pub fn merge_events(tba_event: EventInfo, frc_event: EventInfo, role: FrcEventsRole) -> EventInfo {
+	let offset = time_offset(&tba_event, &frc_event);
+	let mut frc_matches = frc_event
+		.match_infos
+		.into_iter()
+		.map(|mut match_info| {
+			if match_info.start_time != 0 {
+				match_info.start_time = match_info.start_time.saturating_add_signed(offset);
+			}
+			(match_info.id, match_info)
+		})
+		.collect::<BTreeMap<_, _>>();
+
+	let mut match_infos = Vec::new();
+	for tba_match in tba_event.match_infos {
+		let tba_start_time = tba_match.start_time;
+		let mut match_info = match frc_matches.remove(&tba_match.id) {
+			Some(frc_match) if role == FrcEventsRole::Primary => merge_match(frc_match, tba_match),
+			Some(frc_match) => merge_match(tba_match, frc_match),
+			None => tba_match,
+		};
+		// TBA's times are in the right time zone, the FRC Events API's are only estimated
+		if tba_start_time != 0 {
+			match_info.start_time = tba_start_time;
+		}
+		match_infos.push(match_info);
+	}
+	match_infos.extend(frc_matches.into_values());
+
+	// TBA has avatars and the names teams go by
+	let mut team_infos = frc_event.team_infos;
+	team_infos.extend(tba_event.team_infos.clone());
+	let mut event_info = EventInfo::from_matches(
+		match_infos,
+		team_infos
+			.into_values()
+			.map(|team| RawTbaTeamInfo {
+				num: team.num,
+				name: team.name,
+				has_avatar: team.has_avatar,
+			})
+			.collect(),
+		tba_event.year,
+		&tba_event.event,
+	);
+
+	// The official rankings only come from TBA, its record is used unless the FRC Events API has
+	// results for qualification matches it doesn't have yet
+	for (num, tba_team) in tba_event.team_infos {
+		if tba_team.rank.is_none() {
+			continue;
+		}
+		let quals_played = event_info
+			.match_infos
+			.iter()
+			.filter(|match_info| {
+				matches!(match_info.id, MatchId::Qualification(_))
+					&& match_info.result != MatchResult::Tbd
+					&& (match_info.teams_red.contains(&num) || match_info.teams_blue.contains(&num))
+			})
+			.count() as u32;
+		let Some(team_info) = event_info.team_infos.get_mut(&num) else {
+			continue;
+		};
+		if quals_played <= tba_team.wins + tba_team.losses + tba_team.ties {
+			team_info.wins = tba_team.wins;
+			team_info.losses = tba_team.losses;
+			team_info.ties = tba_team.ties;
+			team_info.ranking_points = tba_team.ranking_points;
+		}
+		team_info.rank = tba_team.rank;
+		team_info.rp_average = tba_team.rp_average;
+		team_info.sort_orders = tba_team.sort_orders;
+		team_info.dqs = tba_team.dqs;
+	}
+	event_info
+}
+
+/// Fill in what `preferred` doesn't have yet from `other`
+pub fn merge_match(mut preferred: MatchInfo, other: MatchInfo) -> MatchInfo {
+	let has_result =
+		|match_info: &MatchInfo| match_info.score_red.is_some() || match_info.score_blue.is_some();
+	if !has_result(&preferred) && has_result(&other) {
+		preferred.result = other.result;
+		preferred.score_red = other.score_red;
+		preferred.score_blue = other.score_blue;
+	}
+	preferred.rp_red = preferred.rp_red.or(other.rp_red);
+	preferred.rp_blue = preferred.rp_blue.or(other.rp_blue);
+	let has_entries = |custom_entries: &CustomEntries| {
+		custom_entries
+			.red
+			.iter()
+			.chain(&custom_entries.blue)
+			.any(|entries| !entries.is_empty())
+	};
+	if !has_entries(&preferred.custom_entries) {
+		preferred.custom_entries = other.custom_entries;
+	}
+	if preferred.teams_red.is_empty() && preferred.teams_blue.is_empty() {
+		preferred.teams_red = other.teams_red;
+		preferred.teams_blue = other.teams_blue;
+	}
+	if preferred.start_time == 0 {
+		preferred.start_time = other.start_time;
+	}
+	preferred
+}
+
+/// The FRC Events API's times are local to the event, so estimate the time zone from a match both
+/// sources have a time for, rounded to the half hour
+fn time_offset(tba_event: &EventInfo, frc_event: &EventInfo) -> i64 {
+	tba_event
+		.match_infos
+		.iter()
+		.filter(|tba_match| tba_match.start_time != 0)
+		.find_map(|tba_match| {
+			let frc_match = frc_event
+				.match_infos
+				.iter()
+				.find(|frc_match| frc_match.id == tba_match.id && frc_match.start_time != 0)?;
+			let difference = tba_match.start_time as i64 - frc_match.start_time as i64;
+			Some((difference as f64 / 1800.0).round() as i64 * 1800)
+		})
+		.unwrap_or_default()
+}
+
+/// Parse unix time in seconds, or a date and time like `2024-03-01T09:30:00` treated as UTC
+pub fn parse_time(time: &str) -> Option<u64> {
+	if let Ok(seconds) = time.parse() {
+		return Some(seconds);
+	}
+	let (date, time) = time.split_once(['T', ' '])?;
+	let mut date = date.split('-').map(|part| part.parse::<i64>());
+	let (year, month, day) = (date.next()?.ok()?, date.next()?.ok()?, date.next()?.ok()?);
+	// Ignore fractions of seconds and time zones
+	let time = time.split(['.', 'Z', '+']).next()?;
+	let mut time = time.split(':').map(|part| part.parse::<i64>());
+	let hour = time.next()?.ok()?;
+	let minute = time.next()?.ok()?;
+	let second = time.next().unwrap_or(Ok(0)).ok()?;
+	if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+		return None;
+	}
+
+	// Days since the unix epoch, from http://howardhinnant.github.io/date_algorithms.html
+	let year = if month <= 2 { year - 1 } else { year };
+	let era = year.div_euclid(400);
+	let year_of_era = year - era * 400;
+	let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+	let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+	let days = era * 146097 + day_of_era - 719468;
+	u64::try_from(days * 86400 + hour * 3600 + minute * 60 + second).ok()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const TEAMS: [u32; 6] = [1, 2, 3, 4, 5, 6];
+
+	fn qual(num: u32, start_time: u64, scores: Option<(u16, u16)>) -> MatchInfo {
+		MatchInfo {
+			id: MatchId::Qualification(SetMatch { set: 1, num }),
+			start_time,
+			teams_blue: vec![1, 2, 3],
+			teams_red: vec![4, 5, 6],
+			result: match scores {
+				Some((red, blue)) if red > blue => MatchResult::Red,
+				Some((red, blue)) if red < blue => MatchResult::Blue,
+				Some(_) => MatchResult::Tie,
+				None => MatchResult::Tbd,
+			},
+			score_blue: scores.map(|(_, blue)| blue),
+			score_red: scores.map(|(red, _)| red),
+			rp_blue: None,
+			rp_red: None,
+			custom_entries: CustomEntries {
+				blue: Default::default(),
+				red: Default::default(),
+			},
+		}
+	}
+
+	fn event(match_infos: Vec<MatchInfo>) -> EventInfo {
+		EventInfo::from_matches(
+			match_infos,
+			TEAMS
+				.iter()
+				.map(|num| RawTbaTeamInfo {
+					num: *num,
+					name: format!("Team {num}"),
+					has_avatar: false,
+				})
+				.collect(),
+			2026,
+			"2026test",
+		)
+	}
+
+	fn find(event_info: &EventInfo, num: u32) -> &MatchInfo {
+		event_info
+			.match_infos
+			.iter()
+			.find(|match_info| match_info.id == MatchId::Qualification(SetMatch { set: 1, num }))
+			.unwrap()
+	}
+
+	#[test]
+	fn results_by_role() {
+		let tba_event = || event(vec![qual(1, 0, Some((50, 40))), qual(2, 0, None)]);
+		let frc_event = || event(vec![qual(1, 0, Some((50, 60))), qual(2, 0, Some((30, 20)))]);
+
+		let primary = merge_events(tba_event(), frc_event(), FrcEventsRole::Primary);
+		assert_eq!(find(&primary, 1).score_blue, Some(60));
+		assert_eq!(find(&primary, 1).result, MatchResult::Blue);
+		let fallback = merge_events(tba_event(), frc_event(), FrcEventsRole::Fallback);
+		assert_eq!(find(&fallback, 1).score_blue, Some(40));
+		assert_eq!(find(&fallback, 1).result, MatchResult::Red);
+
+		// Results only one source has posted are used either way
+		for merged in [primary, fallback] {
+			assert_eq!(find(&merged, 2).score_red, Some(30));
+			assert_eq!(find(&merged, 2).result, MatchResult::Red);
+		}
+	}
+
+	#[test]
+	fn merge_match_fills_in_missing_values() {
+		let mut other = qual(1, 1000, Some((10, 20)));
+		other.rp_red = Some(1);
+		other.rp_blue = Some(3);
+		let mut preferred = qual(1, 0, None);
+		preferred.rp_blue = Some(2);
+		preferred.teams_blue.clear();
+		preferred.teams_red.clear();
+
+		let merged = merge_match(preferred, other);
+		assert_eq!(merged.result, MatchResult::Blue);
+		assert_eq!((merged.score_red, merged.score_blue), (Some(10), Some(20)));
+		assert_eq!((merged.rp_red, merged.rp_blue), (Some(1), Some(2)));
+		assert_eq!(merged.teams_blue, [1, 2, 3]);
+		assert_eq!(merged.start_time, 1000);
+
+		let merged = merge_match(qual(1, 500, Some((30, 30))), qual(1, 1000, Some((10, 20))));
+		assert_eq!(merged.result, MatchResult::Tie);
+		assert_eq!(merged.start_time, 500);
+	}
+
+	#[test]
+	fn time_offsets() {
+		let tba_event = event(vec![qual(1, 0, None), qual(2, 1_000_000, None)]);
+		// Five hours behind, with a few minutes of difference in the schedules
+		let frc_event = event(vec![qual(2, 1_000_000 - 18_000 - 420, None)]);
+		assert_eq!(time_offset(&tba_event, &frc_event), 18_000);
+		// Half hour time zones, ahead of UTC
+		let frc_event = event(vec![qual(2, 1_000_000 + 19_800 + 100, None)]);
+		assert_eq!(time_offset(&tba_event, &frc_event), -19_800);
+		// Matches without times can't be compared
+		let frc_event = event(vec![qual(1, 5000, None), qual(2, 0, None)]);
+		assert_eq!(time_offset(&tba_event, &frc_event), 0);
+	}
+
+	#[test]
+	fn keeps_tba_start_times() {
+		let tba_event = event(vec![qual(1, 1_000_000, None), qual(2, 0, None)]);
+		let frc_event = event(vec![
+			qual(1, 1_000_000 - 18_000 - 60, None),
+			qual(2, 1_000_600 - 18_000, None),
+			qual(3, 1_001_200 - 18_000, None),
+			qual(4, 0, None),
+		]);
+		let merged = merge_events(tba_event, frc_event, FrcEventsRole::Primary);
+		assert_eq!(find(&merged, 1).start_time, 1_000_000);
+		// Times only the FRC Events API has are moved by the estimated time zone
+		assert_eq!(find(&merged, 2).start_time, 1_000_600);
+		assert_eq!(find(&merged, 3).start_time, 1_001_200);
+		assert_eq!(find(&merged, 4).start_time, 0);
+	}
+
+	#[test]
+	fn ranked_record_unless_more_quals_played() {
+		let mut tba_event = event(vec![qual(1, 0, Some((50, 40))), qual(2, 0, None)]);
+		for team_info in tba_event.team_infos.values_mut() {
+			team_info.rank = Some(team_info.num);
+			team_info.ranking_points = 7;
+		}
+		let frc_event = || event(vec![qual(1, 0, Some((50, 40))), qual(2, 0, Some((10, 20)))]);
+
+		// The FRC Events API has a result TBA's rankings don't include yet
+		let merged = merge_events(tba_event.clone(), frc_event(), FrcEventsRole::Fallback);
+		let team = &merged.team_infos[&1];
+		assert_eq!((team.wins, team.losses, team.ties), (1, 1, 0));
+		assert_ne!(team.ranking_points, 7);
+		assert_eq!(team.rank, Some(1));
+
+		// Once TBA has posted it, its record is used
+		tba_event.match_infos[1] = qual(2, 0, Some((10, 20)));
+		let team_info = tba_event.team_infos.get_mut(&1).unwrap();
+		team_info.wins = 1;
+		team_info.losses = 1;
+		let merged = merge_events(tba_event, frc_event(), FrcEventsRole::Fallback);
+		let team = &merged.team_infos[&1];
+		assert_eq!((team.wins, team.losses, team.ties), (1, 1, 0));
+		assert_eq!(team.ranking_points, 7);
+	}
+
+	#[test]
+	fn parse_times() {
+		assert_eq!(parse_time("1709285400"), Some(1709285400));
+		assert_eq!(parse_time("1970-01-01T00:00:00"), Some(0));
+		assert_eq!(parse_time("2024-03-01T09:30:00"), Some(1709285400));
+		assert_eq!(parse_time("2024-03-01 09:30"), Some(1709285400));
+		// Fractions of seconds and time zones are ignored
+		assert_eq!(parse_time("2024-03-01T09:30:15.123"), Some(1709285415));
+		assert_eq!(parse_time("2024-03-01T09:30:00Z"), Some(1709285400));
+		// Leap day, and the end of a leap year
+		assert_eq!(parse_time("2024-02-29T00:00:00"), Some(1709164800));
+		assert_eq!(parse_time("2024-12-31T23:59:59"), Some(1735689599));
+		assert_eq!(parse_time("2024-13-01T00:00:00"), None);
+		assert_eq!(parse_time("2024-03-01"), None);
+		assert_eq!(parse_time("1969-12-31T23:59:59"), None);
+		assert_eq!(parse_time("soon"), None);
+	}
+}
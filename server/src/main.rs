@@ -3,10 +3,20 @@ mod api;
 mod config;
 mod data_validation;
 mod database;
+mod frc_events;
 mod leaderboard;
+mod manual_event;
+mod media;
+mod pdf;
+mod pick_list;
+mod robot_card;
 mod server;
+mod snapshot;
 mod statbotics;
+mod storage;
 mod tba;
+mod tba_webhook;
+mod upstream;
 
 use std::time::Instant;
 
@@ -0,0 +1,308 @@
+use std::collections::HashMap;
+
+use poem_openapi::{Enum, Object};
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::frc_events::{self, parse_time, FrcSchedule, FrcTeamList};
+use crate::tba::{CustomEntries, EventInfo, MatchId, MatchInfo, MatchResult, SetMatch, TeamInfo};
+
+/// A team list and schedule entered by hand, for events that aren't on TBA yet
+#[derive(Debug, Clone, PartialEq, Default, Deserialize, Serialize, Object, TS)]
+#[ts(export, export_to = "../../client/src/generated/")]
+pub struct ManualEvent {
+	pub mode: ManualEventMode,
+	pub teams: Vec<ManualTeam>,
+	pub matches: Vec<ManualMatch>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize, Serialize, Enum, TS)]
+#[ts(export, export_to = "../../client/src/generated/")]
+#[serde(rename_all = "snake_case")]
+#[oai(rename_all = "snake_case")]
+pub enum ManualEventMode {
+	/// Add the teams and matches TBA doesn't have, TBA's data is used when both have something
+	#[default]
+	Merge,
+	/// Only use the teams and matches entered here, ignoring TBA
+	Replace,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, Object, TS)]
+#[ts(export, export_to = "../../client/src/generated/")]
+pub struct ManualTeam {
+	pub number: u32,
+	pub name: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, Object, TS)]
+#[ts(export, export_to = "../../client/src/generated/")]
+pub struct ManualMatch {
+	pub id: MatchId,
+	/// Unix time in seconds, like TBA's match times
+	#[ts(type = "number | null")]
+	pub start_time: Option<u64>,
+	pub teams_red: Vec<u32>,
+	pub teams_blue: Vec<u32>,
+}
+
+impl ManualEvent {
+	/// Combine the manual data with the event from TBA, if there is one
+	pub fn apply(&self, tba_event: Option<EventInfo>, year: u32, event: &str) -> EventInfo {
+		let mut event_info = match (self.mode, tba_event) {
+			(ManualEventMode::Merge, Some(tba_event)) => tba_event,
+			_ => EventInfo::without_tba(year, event, []),
+		};
+		for manual_match in &self.matches {
+			if event_info
+				.match_infos
+				.iter()
+				.any(|match_info| match_info.id == manual_match.id)
+			{
+				continue;
+			}
+			event_info.match_infos.push(MatchInfo {
+				id: manual_match.id,
+				start_time: manual_match.start_time.unwrap_or_default(),
+				teams_blue: manual_match.teams_blue.clone(),
+				teams_red: manual_match.teams_red.clone(),
+				result: MatchResult::Tbd,
+				score_blue: None,
+				score_red: None,
+				rp_blue: None,
+				rp_red: None,
+				custom_entries: CustomEntries {
+					blue: Default::default(),
+					red: Default::default(),
+				},
+			});
+		}
+		event_info
+			.match_infos
+			.sort_by_key(|match_info| match_info.id);
+		event_info
+			.match_infos
+			.sort_by_key(|match_info| match_info.start_time);
+
+		let names = self
+			.teams
+			.iter()
+			.map(|team| (team.number, team.name.as_str()))
+			.collect::<HashMap<_, _>>();
+		let scheduled = event_info
+			.match_infos
+			.iter()
+			.flat_map(|match_info| match_info.teams_red.iter().chain(&match_info.teams_blue))
+			.copied()
+			.collect::<Vec<_>>();
+		for team in self.teams.iter().map(|team| team.number).chain(scheduled) {
+			let team_info = event_info
+				.team_infos
+				.entry(team)
+				.or_insert_with(|| TeamInfo::new(team, String::new()));
+			if team_info.name.is_empty() {
+				if let Some(name) = names.get(&team) {
+					team_info.name = name.to_string();
+				}
+			}
+		}
+		event_info
+	}
+}
+
+/// Read a team list from CSV, with the team number then optionally the name on each line
+pub fn parse_team_list_csv(csv: &str) -> Result<Vec<ManualTeam>, String> {
+	let mut teams = Vec::new();
+	for (index, fields) in csv_rows(csv).enumerate() {
+		let number = match fields[0].parse() {
+			Ok(number) => number,
+			// Allow a header
+			Err(_) if index == 0 => continue,
+			Err(_) => {
+				return Err(format!(
+					"Line {}: '{}' isn't a team number",
+					index + 1,
+					fields[0]
+				))
+			}
+		};
+		teams.push(ManualTeam {
+			number,
+			name: fields.get(1).cloned().unwrap_or_default(),
+		});
+	}
+	Ok(teams)
+}
+
+/// Read a team list from the JSON of the FRC Events API's teams endpoint
+pub fn parse_team_list_json(json: serde_json::Value) -> Result<Vec<ManualTeam>, String> {
+	let team_list = serde_json::from_value::<FrcTeamList>(json)
+		.map_err(|err| format!("Not a team list from the FRC Events API: {err}"))?;
+	Ok(team_list
+		.teams
+		.into_iter()
+		.map(|team| ManualTeam {
+			number: team.team_number,
+			name: team.name_short.or(team.name_full).unwrap_or_default(),
+		})
+		.collect())
+}
+
+/// Read a qualification schedule from CSV, with the match number, three red teams, three blue
+/// teams, then optionally the start time on each line. Start times can be unix times in seconds,
+/// or local to the event like `2024-03-01 09:30`, which needs the event's offset from UTC.
+pub fn parse_schedule_csv(
+	csv: &str,
+	utc_offset_minutes: Option<i32>,
+) -> Result<Vec<ManualMatch>, String> {
+	let mut matches = Vec::new();
+	for (index, fields) in csv_rows(csv).enumerate() {
+		let line = index + 1;
+		let num = match fields[0].parse() {
+			Ok(num) => num,
+			Err(_) if index == 0 => continue,
+			Err(_) => return Err(format!("Line {line}: '{}' isn't a match number", fields[0])),
+		};
+		if fields.len() < 7 {
+			return Err(format!(
+				"Line {line}: expected a match number and six teams, but there are only {} columns",
+				fields.len()
+			));
+		}
+		let teams = fields[1..7]
+			.iter()
+			.map(|team| {
+				team.parse::<u32>()
+					.map_err(|_| format!("Line {line}: '{team}' isn't a team number"))
+			})
+			.collect::<Result<Vec<_>, _>>()?;
+		let start_time = match fields.get(7).filter(|time| !time.is_empty()) {
+			Some(time) => Some(
+				parse_local_time(time, utc_offset_minutes)
+					.map_err(|err| format!("Line {line}: {err}"))?,
+			),
+			None => None,
+		};
+		matches.push(ManualMatch {
+			id: MatchId::Qualification(SetMatch { set: 1, num }),
+			start_time,
+			teams_red: teams[..3].to_vec(),
+			teams_blue: teams[3..].to_vec(),
+		});
+	}
+	Ok(matches)
+}
+
+/// Read a schedule from the JSON of the FRC Events API's schedule endpoint. Its times are local
+/// to the event, so they need the event's offset from UTC.
+pub fn parse_schedule_json(
+	json: serde_json::Value,
+	utc_offset_minutes: Option<i32>,
+) -> Result<Vec<ManualMatch>, String> {
+	let schedule = serde_json::from_value::<FrcSchedule>(json)
+		.map_err(|err| format!("Not a schedule from the FRC Events API: {err}"))?;
+	let mut matches = Vec::new();
+	for frc_match in schedule.schedule {
+		let Some(id) = frc_match.match_id() else {
+			continue;
+		};
+		let start_time = match &frc_match.start_time {
+			Some(time) => Some(parse_local_time(time, utc_offset_minutes)?),
+			None => None,
+		};
+		matches.push(ManualMatch {
+			id,
+			start_time,
+			teams_red: frc_events::alliance_teams(&frc_match.teams, "Red"),
+			teams_blue: frc_events::alliance_teams(&frc_match.teams, "Blue"),
+		});
+	}
+	Ok(matches)
+}
+
+/// Parse a start time as unix time in seconds, or as a date and time local to the event. Local
+/// times have to be converted with the event's offset from UTC so they can be ordered with TBA's.
+fn parse_local_time(time: &str, utc_offset_minutes: Option<i32>) -> Result<u64, String> {
+	if let Ok(seconds) = time.parse() {
+		return Ok(seconds);
+	}
+	let local = parse_time(time).ok_or_else(|| format!("'{time}' isn't a date and time"))?;
+	let utc_offset_minutes = utc_offset_minutes.ok_or_else(|| {
+		format!("'{time}' is local to the event, so the event's UTC offset is needed")
+	})?;
+	Ok(local.saturating_add_signed(-(utc_offset_minutes as i64) * 60))
+}
+
+/// The non-empty rows of a CSV file, with the fields trimmed. Fields can be quoted to include commas.
+fn csv_rows(csv: &str) -> impl Iterator<Item = Vec<String>> + '_ {
+	csv.lines()
+		.filter(|line| !line.trim().is_empty())
+		.map(|line| {
+			let mut fields = Vec::new();
+			let mut field = String::new();
+			let mut quoted = false;
+			let mut chars = line.chars().peekable();
+			while let Some(c) = chars.next() {
+				match c {
+					'"' if quoted && chars.peek() == Some(&'"') => {
+						field.push('"');
+						chars.next();
+					}
+					'"' => quoted = !quoted,
+					',' if !quoted => fields.push(std::mem::take(&mut field).trim().to_string()),
+					_ => field.push(c),
+				}
+			}
+			fields.push(field.trim().to_string());
+			fields
+		})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn csv_rows_split_and_trim() {
+		let rows =
+			csv_rows("number, name\n\n  254 ,\"Cheesy Poofs, The\"\n1678,\"Say \"\"Hi\"\"\"\n")
+				.collect::<Vec<_>>();
+		assert_eq!(
+			rows,
+			vec![
+				vec!["number", "name"],
+				vec!["254", "Cheesy Poofs, The"],
+				vec!["1678", "Say \"Hi\""],
+			]
+		);
+		assert_eq!(csv_rows("1,,3").next().unwrap(), vec!["1", "", "3"]);
+		assert_eq!(csv_rows(" \n\t\n").count(), 0);
+	}
+
+	#[test]
+	fn schedule_times() {
+		let csv = "match,r1,r2,r3,b1,b2,b3,time\n\
+			1,1,2,3,4,5,6,2024-03-01 09:30\n\
+			2,1,2,3,4,5,6,1709285400\n\
+			3,1,2,3,4,5,6,\n";
+		let matches = parse_schedule_csv(csv, Some(-8 * 60)).unwrap();
+		let start_times = matches
+			.iter()
+			.map(|manual_match| manual_match.start_time)
+			.collect::<Vec<_>>();
+		// 09:30 in UTC-8 is 17:30 UTC
+		assert_eq!(start_times, vec![Some(1709314200), Some(1709285400), None]);
+		assert_eq!(matches[0].teams_red, vec![1, 2, 3]);
+		assert_eq!(matches[0].teams_blue, vec![4, 5, 6]);
+
+		assert_eq!(
+			parse_schedule_csv(csv, None).unwrap_err(),
+			"Line 2: '2024-03-01 09:30' is local to the event, so the event's UTC offset is needed"
+		);
+		assert!(parse_schedule_csv("1,1,2,3,4,5,6,1709285400", None).is_ok());
+		assert_eq!(
+			parse_schedule_csv("1,1,2,3,4,5,6,soon", Some(0)).unwrap_err(),
+			"Line 1: 'soon' isn't a date and time"
+		);
+	}
+}
@@ -0,0 +1,157 @@
+use std::io::Cursor;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use image::codecs::webp::WebPEncoder;
+use image::{DynamicImage, ImageDecoder, ImageFormat, ImageReader, ImageResult, Rgb, RgbImage};
+use log::warn;
+use sha2::{Digest, Sha256};
+use tokio::process::Command;
+
+use crate::api::data::{ImageEdit, ImageRotation};
+
+const PLACEHOLDER_WIDTH: u32 = 320;
+const PLACEHOLDER_HEIGHT: u32 = 180;
+
+static NEXT_TEMP_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Whether a mime type is for a video rather than a still image
+pub fn is_video(mime_type: &str) -> bool {
+	mime_type.starts_with("video/")
+}
+
+/// The SHA-256 hash of an upload as lowercase hex, used to skip uploads that are already saved
+pub fn content_hash(data: &[u8]) -> String {
+	Sha256::digest(data)
+		.iter()
+		.map(|byte| format!("{byte:02x}"))
+		.collect()
+}
+
+/// Decode an uploaded image, turning it the right way up if the camera recorded its orientation.
+/// Uses the format of the mime type if it is known, otherwise guesses from the data.
+pub fn decode_image(data: &[u8], mime_type: &str) -> ImageResult<(DynamicImage, ImageFormat)> {
+	let mut image_reader = ImageReader::new(Cursor::new(data));
+	match ImageFormat::from_mime_type(mime_type) {
+		Some(image_format) => image_reader.set_format(image_format),
+		None => image_reader = image_reader.with_guessed_format()?,
+	}
+	// The reader always has a format here, decoding fails below if it couldn't be guessed
+	let format = image_reader.format().unwrap_or(ImageFormat::Png);
+	let mut decoder = image_reader.into_decoder()?;
+	let orientation = decoder.orientation()?;
+	let mut image = DynamicImage::from_decoder(decoder)?;
+	image.apply_orientation(orientation);
+	Ok((image, format))
+}
+
+/// Rotate and crop an image
+pub fn apply_edit(image: DynamicImage, edit: &ImageEdit) -> DynamicImage {
+	let image = match edit.rotate {
+		ImageRotation::None => image,
+		ImageRotation::Clockwise => image.rotate90(),
+		ImageRotation::HalfTurn => image.rotate180(),
+		ImageRotation::CounterClockwise => image.rotate270(),
+	};
+	match &edit.crop {
+		Some(crop) => {
+			let (width, height) = (image.width() as f32, image.height() as f32);
+			let x = (crop.x * width).round() as u32;
+			let y = (crop.y * height).round() as u32;
+			image.crop_imm(
+				x,
+				y,
+				((crop.width * width).round() as u32).max(1),
+				((crop.height * height).round() as u32).max(1),
+			)
+		}
+		None => image,
+	}
+}
+
+/// The format to store an image in, keeping the uploaded format when it's one browsers can show.
+/// Other formats are stored as PNG if they have transparency, or JPEG otherwise.
+pub fn stored_format(uploaded_format: ImageFormat, image: &DynamicImage) -> ImageFormat {
+	match uploaded_format {
+		ImageFormat::Jpeg | ImageFormat::Png => uploaded_format,
+		_ if image.color().has_alpha() => ImageFormat::Png,
+		_ => ImageFormat::Jpeg,
+	}
+}
+
+/// Encode an image, converting the colours to ones the format supports first
+pub fn encode_image(image: &DynamicImage, format: ImageFormat) -> ImageResult<Vec<u8>> {
+	let mut data = Vec::new();
+	match format {
+		ImageFormat::Jpeg => DynamicImage::ImageRgb8(image.to_rgb8())
+			.write_to(&mut Cursor::new(&mut data), format)?,
+		// Only lossless WebP can be encoded, which is still usually smaller than PNG
+		ImageFormat::WebP if image.color().has_alpha() => {
+			image
+				.to_rgba8()
+				.write_with_encoder(WebPEncoder::new_lossless(&mut data))?;
+		}
+		ImageFormat::WebP => image
+			.to_rgb8()
+			.write_with_encoder(WebPEncoder::new_lossless(&mut data))?,
+		_ => image.write_to(&mut Cursor::new(&mut data), format)?,
+	}
+	Ok(data)
+}
+
+/// Get a still image to use as the thumbnail of a video.
+/// This uses the first frame if ffmpeg is installed, otherwise a generic play button image.
+pub async fn video_thumbnail(video: &[u8]) -> DynamicImage {
+	match first_frame(video).await {
+		Ok(frame) => frame,
+		Err(err) => {
+			warn!("Couldn't get the first frame of a video, using a placeholder: {err}");
+			placeholder_thumbnail()
+		}
+	}
+}
+
+async fn first_frame(video: &[u8]) -> Result<DynamicImage, String> {
+	// ffmpeg can't seek in piped input, which most containers need, so go through a file
+	let path = std::env::temp_dir().join(format!(
+		"infiniscouter-video-{}-{}",
+		std::process::id(),
+		NEXT_TEMP_ID.fetch_add(1, Ordering::Relaxed)
+	));
+	tokio::fs::write(&path, video)
+		.await
+		.map_err(|err| err.to_string())?;
+	let output = Command::new("ffmpeg")
+		.args(["-v", "error", "-i"])
+		.arg(&path)
+		.args(["-frames:v", "1", "-f", "image2pipe", "-c:v", "png", "-"])
+		.output()
+		.await;
+	let _ = tokio::fs::remove_file(&path).await;
+	let output = output.map_err(|err| format!("Failed to run ffmpeg: {err}"))?;
+	if !output.status.success() {
+		return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+	}
+	let mut reader = ImageReader::new(Cursor::new(output.stdout));
+	reader.set_format(ImageFormat::Png);
+	reader.decode().map_err(|err| err.to_string())
+}
+
+/// A dark frame with a play button in the middle
+fn placeholder_thumbnail() -> DynamicImage {
+	let (width, height) = (PLACEHOLDER_WIDTH as f32, PLACEHOLDER_HEIGHT as f32);
+	let size = height / 3.0;
+	DynamicImage::ImageRgb8(RgbImage::from_fn(
+		PLACEHOLDER_WIDTH,
+		PLACEHOLDER_HEIGHT,
+		|x, y| {
+			// Triangle pointing right, centred in the frame
+			let dx = x as f32 - (width - size) / 2.0;
+			let dy = (y as f32 - height / 2.0).abs();
+			if dx >= 0.0 && dx <= size && dy <= (size - dx) / 2.0 {
+				Rgb([230, 230, 230])
+			} else {
+				Rgb([40, 40, 40])
+			}
+		},
+	))
+}
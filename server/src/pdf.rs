@@ -0,0 +1,232 @@
+use std::fmt::Write;
+
+use image::{DynamicImage, ImageFormat, ImageResult};
+
+use crate::media;
+
+/// US letter, in points
+pub const PAGE_WIDTH: f32 = 612.0;
+pub const PAGE_HEIGHT: f32 = 792.0;
+
+/// Widths of the printable ASCII characters in Helvetica, in thousandths of the font size
+const HELVETICA_WIDTHS: [u16; 95] = [
+	278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278, 278, 556, 556, 556,
+	556, 556, 556, 556, 556, 556, 556, 278, 278, 584, 584, 584, 556, 1015, 667, 667, 722, 722, 667,
+	611, 778, 722, 278, 500, 667, 556, 833, 722, 778, 667, 778, 722, 667, 611, 722, 667, 944, 667,
+	667, 611, 278, 278, 278, 469, 556, 333, 556, 556, 500, 556, 556, 278, 556, 556, 222, 222, 500,
+	222, 833, 556, 556, 556, 556, 333, 500, 278, 556, 500, 722, 500, 500, 500, 334, 260, 334, 584,
+];
+
+/// A PDF made of text, filled rectangles, and photos, using the standard Helvetica fonts so
+/// nothing needs to be embedded
+#[derive(Debug, Default)]
+pub struct PdfDocument {
+	pages: Vec<String>,
+	images: Vec<PdfImage>,
+}
+
+#[derive(Debug)]
+struct PdfImage {
+	width: u32,
+	height: u32,
+	jpeg: Vec<u8>,
+}
+
+/// A photo added to a document, which can be drawn on any of its pages
+#[derive(Debug, Clone, Copy)]
+pub struct ImageRef(usize);
+
+impl PdfDocument {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Start a new page, later drawing goes on this page
+	pub fn add_page(&mut self) {
+		self.pages.push(String::new());
+	}
+
+	pub fn add_image(&mut self, image: &DynamicImage) -> ImageResult<ImageRef> {
+		self.images.push(PdfImage {
+			width: image.width(),
+			height: image.height(),
+			jpeg: media::encode_image(image, ImageFormat::Jpeg)?,
+		});
+		Ok(ImageRef(self.images.len() - 1))
+	}
+
+	/// Draw text with its top left corner at `x`, `y`, measured from the top left of the page
+	pub fn text(&mut self, x: f32, y: f32, size: f32, bold: bool, colour: [u8; 3], text: &str) {
+		let font = if bold { "F2" } else { "F1" };
+		let page = self.current_page();
+		let _ = writeln!(
+			page,
+			"BT {} rg /{font} {size:.1} Tf {x:.2} {:.2} Td ({}) Tj ET",
+			rgb(colour),
+			PAGE_HEIGHT - y - size * 0.8,
+			escape_text(text)
+		);
+	}
+
+	/// Fill a rectangle with its top left corner at `x`, `y`, measured from the top left of the page
+	pub fn rect(&mut self, x: f32, y: f32, width: f32, height: f32, colour: [u8; 3]) {
+		let page = self.current_page();
+		let _ = writeln!(
+			page,
+			"{} rg {x:.2} {:.2} {width:.2} {height:.2} re f",
+			rgb(colour),
+			PAGE_HEIGHT - y - height
+		);
+	}
+
+	/// Draw a photo stretched to fill a rectangle, with its top left corner at `x`, `y`
+	pub fn image(&mut self, image: ImageRef, x: f32, y: f32, width: f32, height: f32) {
+		let page = self.current_page();
+		let _ = writeln!(
+			page,
+			"q {width:.2} 0 0 {height:.2} {x:.2} {:.2} cm /Im{} Do Q",
+			PAGE_HEIGHT - y - height,
+			image.0
+		);
+	}
+
+	/// The size of a photo added to the document, in pixels
+	pub fn image_size(&self, image: ImageRef) -> (u32, u32) {
+		let image = &self.images[image.0];
+		(image.width, image.height)
+	}
+
+	pub fn to_bytes(&self) -> Vec<u8> {
+		let mut objects: Vec<Vec<u8>> = Vec::new();
+		// 1: catalog, 2: page list, 3 and 4: fonts, then the images, then each page and its content
+		let first_image = 5;
+		let first_page = first_image + self.images.len();
+		let page_ids = (0..self.pages.len().max(1))
+			.map(|index| first_page + index * 2)
+			.collect::<Vec<_>>();
+
+		objects.push(b"<< /Type /Catalog /Pages 2 0 R >>".to_vec());
+		objects.push(
+			format!(
+				"<< /Type /Pages /Kids [{}] /Count {} >>",
+				page_ids
+					.iter()
+					.map(|id| format!("{id} 0 R"))
+					.collect::<Vec<_>>()
+					.join(" "),
+				page_ids.len()
+			)
+			.into_bytes(),
+		);
+		for font in ["Helvetica", "Helvetica-Bold"] {
+			objects.push(
+				format!(
+					"<< /Type /Font /Subtype /Type1 /BaseFont /{font} /Encoding /WinAnsiEncoding >>"
+				)
+				.into_bytes(),
+			);
+		}
+		for image in &self.images {
+			let mut object = format!(
+				"<< /Type /XObject /Subtype /Image /Width {} /Height {} /ColorSpace /DeviceRGB \
+				 /BitsPerComponent 8 /Filter /DCTDecode /Length {} >>\nstream\n",
+				image.width,
+				image.height,
+				image.jpeg.len()
+			)
+			.into_bytes();
+			object.extend_from_slice(&image.jpeg);
+			object.extend_from_slice(b"\nendstream");
+			objects.push(object);
+		}
+		let image_resources = (0..self.images.len())
+			.map(|index| format!("/Im{index} {} 0 R", first_image + index))
+			.collect::<Vec<_>>()
+			.join(" ");
+		let empty_page = String::new();
+		for (index, page_id) in page_ids.iter().enumerate() {
+			let content = self.pages.get(index).unwrap_or(&empty_page);
+			objects.push(
+				format!(
+					"<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {PAGE_WIDTH} {PAGE_HEIGHT}] \
+					 /Resources << /Font << /F1 3 0 R /F2 4 0 R >> /XObject << {image_resources} >> >> \
+					 /Contents {} 0 R >>",
+					page_id + 1
+				)
+				.into_bytes(),
+			);
+			objects.push(
+				format!(
+					"<< /Length {} >>\nstream\n{content}endstream",
+					content.len()
+				)
+				.into_bytes(),
+			);
+		}
+
+		let mut pdf = b"%PDF-1.4\n".to_vec();
+		let mut offsets = Vec::new();
+		for (index, object) in objects.iter().enumerate() {
+			offsets.push(pdf.len());
+			pdf.extend_from_slice(format!("{} 0 obj\n", index + 1).as_bytes());
+			pdf.extend_from_slice(object);
+			pdf.extend_from_slice(b"\nendobj\n");
+		}
+		let xref_offset = pdf.len();
+		let mut xref = format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1);
+		for offset in offsets {
+			let _ = writeln!(xref, "{offset:010} 00000 n ");
+		}
+		let _ = write!(
+			xref,
+			"trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{xref_offset}\n%%EOF\n",
+			objects.len() + 1
+		);
+		pdf.extend_from_slice(xref.as_bytes());
+		pdf
+	}
+
+	fn current_page(&mut self) -> &mut String {
+		if self.pages.is_empty() {
+			self.add_page();
+		}
+		self.pages.last_mut().unwrap()
+	}
+}
+
+/// How wide some text is when drawn, in points. Bold text is a bit wider than this.
+pub fn text_width(text: &str, size: f32) -> f32 {
+	text.chars()
+		.map(|c| match c as u32 {
+			code @ 32..=126 => HELVETICA_WIDTHS[code as usize - 32] as f32,
+			_ => 556.0,
+		})
+		.sum::<f32>()
+		* size / 1000.0
+}
+
+fn rgb(colour: [u8; 3]) -> String {
+	colour
+		.map(|channel| format!("{:.3}", channel as f32 / 255.0))
+		.join(" ")
+}
+
+/// Escape text for a PDF string, characters the standard fonts don't have are replaced with `?`
+fn escape_text(text: &str) -> String {
+	let mut escaped = String::new();
+	for c in text.chars() {
+		match c {
+			'(' | ')' | '\\' => {
+				escaped.push('\\');
+				escaped.push(c);
+			}
+			' '..='~' => escaped.push(c),
+			// WinAnsi matches Latin-1 for these
+			'\u{a0}'..='\u{ff}' => {
+				let _ = write!(escaped, "\\{:03o}", c as u32);
+			}
+			_ => escaped.push('?'),
+		}
+	}
+	escaped
+}
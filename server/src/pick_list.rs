@@ -0,0 +1,101 @@
+use std::collections::HashMap;
+
+use poem_openapi::{Enum, Object};
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+/// The alliance selection pick list for an event, shared by everyone editing it
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize, Object, TS)]
+#[ts(export, export_to = "../../client/src/generated/")]
+pub struct PickList {
+	/// Which list each team has been sorted into
+	#[serde(default)]
+	#[oai(default)]
+	pub teams: HashMap<u32, PickListEntry>,
+	/// Teams that have (or haven't) been picked so far during alliance selection
+	#[serde(default)]
+	#[oai(default)]
+	pub picked: HashMap<u32, PickedEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, Object, TS)]
+#[ts(export, export_to = "../../client/src/generated/")]
+pub struct PickListEntry {
+	/// The list this team is in, or none if it has been removed from the lists
+	pub category: Option<PickListCategory>,
+	/// The relative position of this team in the list; lower comes before higher
+	pub position: f32,
+	/// The scout that made this change
+	#[serde(default)]
+	#[oai(default)]
+	pub scout: String,
+	/// The timestamp for this change (to ensure old changes don't overwrite new changes)
+	#[serde(default)]
+	#[oai(default)]
+	#[ts(type = "number")]
+	pub timestamp_ms: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, Object, TS)]
+#[ts(export, export_to = "../../client/src/generated/")]
+pub struct PickedEntry {
+	/// Whether the team has been picked by an alliance
+	pub picked: bool,
+	/// The scout that made this change
+	#[serde(default)]
+	#[oai(default)]
+	pub scout: String,
+	/// The timestamp for this change (to ensure old changes don't overwrite new changes)
+	#[serde(default)]
+	#[oai(default)]
+	#[ts(type = "number")]
+	pub timestamp_ms: u64,
+}
+
+/// The lists a team can be sorted into
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, Enum, TS)]
+#[ts(export, export_to = "../../client/src/generated/")]
+#[serde(rename_all = "snake_case")]
+#[oai(rename_all = "snake_case")]
+pub enum PickListCategory {
+	/// Teams we want as our first pick
+	First,
+	/// Teams we want as our second pick
+	Second,
+	/// Teams we don't want on our alliance at all
+	DoNotPick,
+}
+
+impl PickList {
+	/// Applies every change that is newer than what we already have, returning how many were applied
+	pub fn merge(&mut self, changes: PickList) -> usize {
+		let mut count = 0;
+		for (team, entry) in changes.teams {
+			match self.teams.get(&team) {
+				Some(old_entry) if old_entry.timestamp_ms >= entry.timestamp_ms => {}
+				_ => {
+					self.teams.insert(team, entry);
+					count += 1;
+				}
+			}
+		}
+		for (team, entry) in changes.picked {
+			match self.picked.get(&team) {
+				Some(old_entry) if old_entry.timestamp_ms >= entry.timestamp_ms => {}
+				_ => {
+					self.picked.insert(team, entry);
+					count += 1;
+				}
+			}
+		}
+		count
+	}
+
+	pub fn is_picked(&self, team: u32) -> bool {
+		self.picked.get(&team).is_some_and(|entry| entry.picked)
+	}
+
+	pub fn get_category(&self, team: u32) -> Option<PickListCategory> {
+		self.teams.get(&team).and_then(|entry| entry.category)
+	}
+}
@@ -0,0 +1,313 @@
+use std::cmp::Reverse;
+
+use poem_openapi::{Enum, Object};
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::analysis::{
+	get_driver_entries, get_match_entries, get_metric_name, get_pit_entry, get_single_metric,
+};
+use crate::api::data::{FullEntryData, ImageEntry, MatchEntryValue};
+use crate::config::match_entry::{MatchEntryFields, MatchEntryType};
+use crate::config::{GameConfigs, TeamConfig};
+use crate::database::Database;
+use crate::statbotics::StatboticsTeam;
+use crate::tba::EventInfo;
+
+/// Every photo and video of a team
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, Object, TS)]
+#[ts(export, export_to = "../../client/src/generated/")]
+pub struct TeamGallery {
+	pub team_number: u32,
+	/// The URL of the team's avatar from TBA, if they have one
+	pub avatar_url: Option<String>,
+	/// Newest first
+	pub images: Vec<TeamImage>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, Object, TS)]
+#[ts(export, export_to = "../../client/src/generated/")]
+pub struct TeamImage {
+	pub image_id: String,
+	/// The format mime type of the image, e.g. `image/png` or `video/mp4`
+	pub image_mime: String,
+	pub full_url: String,
+	pub small_url: String,
+	/// The kind of scouting the image was taken for
+	pub source: ImageSource,
+	/// The match the image was taken in, if it isn't from pit scouting
+	pub match_id: Option<String>,
+	/// The name of the entry the image was added to
+	pub entry_name: String,
+	pub scout: String,
+	#[ts(type = "number")]
+	pub timestamp_ms: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize, Enum, TS)]
+#[ts(export, export_to = "../../client/src/generated/")]
+#[serde(rename_all = "snake_case")]
+#[oai(rename_all = "snake_case")]
+pub enum ImageSource {
+	Pit,
+	Match,
+	DriveTeam,
+}
+
+/// A printable summary of a robot for the drive team
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, Object, TS)]
+#[ts(export, export_to = "../../client/src/generated/")]
+pub struct RobotCard {
+	pub team_number: u32,
+	pub team_name: String,
+	/// The newest photo from pit scouting, or from a match if there are none
+	pub photo: Option<TeamImage>,
+	/// The answers from pit scouting
+	pub specs: Vec<CardValue>,
+	/// The expected score and the metrics from the pre-match display
+	pub metrics: Vec<CardValue>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, Object, TS)]
+#[ts(export, export_to = "../../client/src/generated/")]
+pub struct CardValue {
+	pub name: String,
+	pub value: String,
+}
+
+pub fn get_team_gallery(
+	database: &Database,
+	team_config: &TeamConfig,
+	config: &GameConfigs,
+	tba_data: Option<&EventInfo>,
+	team: u32,
+) -> TeamGallery {
+	let (year, event) = (team_config.current_year, team_config.current_event.as_str());
+	let team_id = team.to_string();
+	let mut images = Vec::new();
+	if let Some(pit_entry) = database.get_pit_entry_data(year, event, &team_id).unwrap() {
+		add_images(
+			&mut images,
+			&pit_entry,
+			&config.pit_entry_fields,
+			ImageSource::Pit,
+			None,
+		);
+	}
+	for match_entry in database
+		.get_all_match_entries(year, event)
+		.into_iter()
+		.filter(|match_entry| match_entry.team_id == team_id)
+	{
+		add_images(
+			&mut images,
+			&match_entry.data,
+			&config.match_entry_fields,
+			ImageSource::Match,
+			Some(&match_entry.match_id),
+		);
+	}
+	for driver_entry in database
+		.get_all_driver_entries(year, event)
+		.into_iter()
+		.filter(|driver_entry| driver_entry.team_id == team_id)
+	{
+		add_images(
+			&mut images,
+			&driver_entry.data,
+			&config.driver_entry_fields,
+			ImageSource::DriveTeam,
+			Some(&driver_entry.match_id),
+		);
+	}
+	images.sort_by_key(|image| Reverse(image.timestamp_ms));
+
+	TeamGallery {
+		team_number: team,
+		avatar_url: tba_data.and_then(|tba_data| tba_data.get_team_info(team).icon_uri),
+		images,
+	}
+}
+
+fn add_images(
+	images: &mut Vec<TeamImage>,
+	data: &FullEntryData,
+	fields: &MatchEntryFields,
+	source: ImageSource,
+	match_id: Option<&str>,
+) {
+	for (entry_id, value) in &data.entries {
+		let MatchEntryValue::Image(ImageEntry {
+			images: entry_images,
+			scout,
+			timestamp_ms,
+		}) = value
+		else {
+			continue;
+		};
+		let entry_name = fields
+			.entries
+			.get(entry_id)
+			.map(|entry| entry.title.clone())
+			.unwrap_or_else(|| entry_id.clone());
+		images.extend(entry_images.iter().map(|image| TeamImage {
+			image_id: image.image_id.clone(),
+			image_mime: image.image_mime.clone(),
+			full_url: format!("/image/full/{}", image.image_id),
+			small_url: format!("/image/small/{}", image.image_id),
+			source,
+			match_id: match_id.map(str::to_string),
+			entry_name: entry_name.clone(),
+			scout: scout.clone(),
+			timestamp_ms: *timestamp_ms,
+		}));
+	}
+}
+
+/// The photo that shows the robot best, the newest one from pit scouting if there is one
+pub fn robot_photo(gallery: &TeamGallery) -> Option<&TeamImage> {
+	gallery
+		.images
+		.iter()
+		.filter(|image| image.image_mime.starts_with("image/"))
+		.min_by_key(|image| image.source != ImageSource::Pit)
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn get_robot_card(
+	database: &Database,
+	team_config: &TeamConfig,
+	config: &GameConfigs,
+	tba_data: &EventInfo,
+	statbotics: Option<&StatboticsTeam>,
+	team: u32,
+) -> RobotCard {
+	let (year, event) = (team_config.current_year, team_config.current_event.as_str());
+	let gallery = get_team_gallery(database, team_config, config, Some(tba_data), team);
+	let photo = robot_photo(&gallery).cloned();
+
+	let match_entries = get_match_entries(database, config, year, event, tba_data);
+	let driver_entries = get_driver_entries(database, config, year, event);
+	let pit_entry = get_pit_entry(database, config, year, event, team);
+	let metric_value = |metric: &str| {
+		let entry = get_single_metric(
+			config,
+			&match_entries,
+			&driver_entries,
+			pit_entry.as_ref(),
+			statbotics,
+			team,
+			tba_data.team_infos.get(&team),
+			metric,
+			None,
+		);
+		CardValue {
+			name: get_metric_name(config, metric).name,
+			value: entry.pit_value.unwrap_or(entry.text),
+		}
+	};
+
+	// Pit questions in the order they're asked, without photos or long notes
+	let specs = config
+		.pit_entry_fields
+		.pages
+		.iter()
+		.flat_map(|page| page.layout.iter())
+		.filter(|entry_id| {
+			config
+				.pit_entry_fields
+				.entries
+				.get(*entry_id)
+				.is_some_and(|entry| match &entry.entry {
+					MatchEntryType::Image(_) => false,
+					MatchEntryType::TextEntry(text) => !text.multiline,
+					_ => true,
+				})
+		})
+		.filter(|entry_id| {
+			pit_entry
+				.as_ref()
+				.is_some_and(|pit_entry| pit_entry.entries.contains_key(*entry_id))
+		})
+		.map(|entry_id| metric_value(entry_id))
+		.collect();
+	let pre_match = &config.game_config.display.pre_match;
+	let metrics = std::iter::once(&pre_match.score)
+		.chain(pre_match.metrics.iter())
+		.map(|metric| metric_value(metric))
+		.collect();
+
+	RobotCard {
+		team_number: team,
+		team_name: tba_data.get_team_info(team).name,
+		photo,
+		specs,
+		metrics,
+	}
+}
+
+/// A page with the robot card that fits on a sheet of paper when printed
+pub fn robot_card_html(card: &RobotCard) -> String {
+	let rows = |values: &[CardValue]| {
+		values
+			.iter()
+			.map(|value| {
+				format!(
+					"<tr><th>{}</th><td>{}</td></tr>",
+					escape_html(&value.name),
+					escape_html(&value.value)
+				)
+			})
+			.collect::<String>()
+	};
+	let photo = card
+		.photo
+		.as_ref()
+		.map(|photo| {
+			format!(
+				r#"<img src="{}" alt="Team {} robot">"#,
+				escape_html(&photo.full_url),
+				card.team_number
+			)
+		})
+		.unwrap_or_else(|| "<p>No photo</p>".to_string());
+	format!(
+		r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>{number} {name}</title>
+<style>
+@page {{ size: letter; margin: 0.5in; }}
+body {{ font-family: sans-serif; margin: 0; }}
+h1 {{ margin: 0 0 0.2in; }}
+img {{ max-width: 100%; max-height: 4in; display: block; margin-bottom: 0.2in; }}
+.columns {{ display: flex; gap: 0.3in; }}
+table {{ border-collapse: collapse; flex: 1; align-self: flex-start; }}
+th, td {{ border: 1px solid #888; padding: 2px 6px; text-align: left; }}
+th {{ width: 50%; }}
+</style>
+</head>
+<body>
+<h1>{number} {name}</h1>
+{photo}
+<div class="columns">
+<table><caption>Robot</caption>{specs}</table>
+<table><caption>Performance</caption>{metrics}</table>
+</div>
+</body>
+</html>
+"#,
+		number = card.team_number,
+		name = escape_html(&card.team_name),
+		specs = rows(&card.specs),
+		metrics = rows(&card.metrics),
+	)
+}
+
+fn escape_html(text: &str) -> String {
+	text.replace('&', "&amp;")
+		.replace('<', "&lt;")
+		.replace('>', "&gt;")
+		.replace('"', "&quot;")
+}
@@ -1,22 +1,35 @@
+use std::ops::Bound;
 use std::sync::Arc;
 
 use color_eyre::Result;
+use log::warn;
 use poem::endpoint::StaticFilesEndpoint;
-use poem::http::StatusCode;
-use poem::middleware::{AddData, Compression};
+use poem::http::{header, StatusCode};
+use poem::middleware::{AddData, CatchPanic, Compression};
+use poem::web::headers::{ContentRange, HeaderMapExt, Range};
 use poem::web::{Data, Path};
-use poem::{get, handler, listener::TcpListener, EndpointExt, Response, Route, Server};
+use poem::{
+	get, handler, listener::TcpListener, post, EndpointExt, Request, Response, Route, Server,
+};
 use poem_openapi::OpenApiService;
 
-use crate::api::Api;
+use crate::analysis;
+use crate::api::{self, Api, ApiError};
 use crate::config::ConfigManager;
-use crate::database::{Database, ImageSize};
+use crate::database::{Database, ImageSize, VideoInfo};
+use crate::frc_events::FrcEvents;
+use crate::robot_card;
 use crate::statbotics::StatboticsCache;
-use crate::tba::Tba;
+use crate::tba::{MatchId, Tba};
+use crate::tba_webhook::{self, WebhookMessage};
+
+/// The most of a video sent at once when the rest of it is asked for
+const VIDEO_RANGE_LIMIT: u64 = 2 * 1024 * 1024;
 
 pub struct ScoutingServer {
 	api: Api,
 	tba: Arc<Tba>,
+	statbotics: StatboticsCache,
 	config: ConfigManager,
 	database: Arc<Database>,
 }
@@ -37,25 +50,224 @@ async fn get_avatar(
 }
 
 #[handler]
-async fn get_image_full(image_id: Path<String>, database: Data<&Arc<Database>>) -> Response {
-	match database.get_image(&image_id, ImageSize::Full).unwrap() {
-		Some(image) => Response::builder()
-			.content_type(image.mime_type)
-			.body(image.image_data),
-		None => poem::Response::from(StatusCode::NOT_FOUND),
+async fn get_image_full(
+	req: &Request,
+	image_id: Path<String>,
+	database: Data<&Arc<Database>>,
+) -> Response {
+	if let Some(video) = database.get_video_info(&image_id).unwrap() {
+		return get_video(req, &image_id, video, &database);
+	}
+	get_image(req, &image_id, ImageSize::Full, &database)
+}
+
+/// Serve a video, or the part of it asked for with a `Range` header so that it can be streamed and seeked
+fn get_video(req: &Request, video_id: &str, video: VideoInfo, database: &Database) -> Response {
+	let (start, end) = match req.headers().typed_get::<Range>() {
+		Some(range) => {
+			let bounds = range
+				.satisfiable_ranges(video.size)
+				.next()
+				.map(|(start, end)| {
+					let start = match start {
+						Bound::Included(n) => n,
+						Bound::Excluded(n) => n + 1,
+						Bound::Unbounded => 0,
+					};
+					let end = match end {
+						Bound::Included(n) => n + 1,
+						Bound::Excluded(n) => n,
+						// Players ask for the rest of the video, send it a piece at a time
+						Bound::Unbounded => start.saturating_add(VIDEO_RANGE_LIMIT),
+					};
+					(start, end.min(video.size))
+				});
+			match bounds {
+				Some((start, end)) if start < end => (start, end),
+				_ => {
+					return Response::builder()
+						.status(StatusCode::RANGE_NOT_SATISFIABLE)
+						.typed_header(ContentRange::unsatisfied_bytes(video.size))
+						.finish()
+				}
+			}
+		}
+		None => (0, video.size),
+	};
+	let Some(data) = database.read_video(video_id, start, end).unwrap() else {
+		return poem::Response::from(StatusCode::NOT_FOUND);
+	};
+
+	let builder = Response::builder()
+		.content_type(video.mime_type)
+		.header(header::ACCEPT_RANGES, "bytes");
+	if start == 0 && end == video.size {
+		builder.body(data)
+	} else {
+		builder
+			.status(StatusCode::PARTIAL_CONTENT)
+			.typed_header(ContentRange::bytes(start..end, video.size).unwrap())
+			.body(data)
 	}
 }
 
 #[handler]
-async fn get_image_small(image_id: Path<String>, database: Data<&Arc<Database>>) -> Response {
-	match database.get_image(&image_id, ImageSize::Small).unwrap() {
+async fn get_image_small(
+	req: &Request,
+	image_id: Path<String>,
+	database: Data<&Arc<Database>>,
+) -> Response {
+	get_image(req, &image_id, ImageSize::Small, &database)
+}
+
+/// Serve an image, as WebP if the browser supports it and there's a smaller WebP version
+fn get_image(req: &Request, image_id: &str, size: ImageSize, database: &Database) -> Response {
+	let accepts_webp = req
+		.headers()
+		.get(header::ACCEPT)
+		.and_then(|accept| accept.to_str().ok())
+		.is_some_and(|accept| accept.contains("image/webp"));
+	match database.get_image(image_id, size, accepts_webp).unwrap() {
 		Some(image) => Response::builder()
 			.content_type(image.mime_type)
+			.header(header::VARY, "Accept")
 			.body(image.image_data),
 		None => poem::Response::from(StatusCode::NOT_FOUND),
 	}
 }
 
+/// A printable report on a team, e.g. `/report/team/254.pdf`
+#[handler]
+async fn get_team_report(
+	file: Path<String>,
+	tba: Data<&Arc<Tba>>,
+	statbotics: Data<&StatboticsCache>,
+	config: Data<&ConfigManager>,
+	database: Data<&Arc<Database>>,
+) -> poem::Result<Response> {
+	let team = pdf_file_stem(&file)?
+		.parse::<u32>()
+		.map_err(|e| poem::Error::new(e, StatusCode::BAD_REQUEST))?;
+	let info = analysis::get_single_team_analysis(
+		&tba,
+		&statbotics,
+		&database,
+		config.get_server_config(),
+		config.get_current_game_config(),
+		team,
+	)
+	.await;
+	let gallery = robot_card::get_team_gallery(
+		&database,
+		config.get_server_config(),
+		config.get_current_game_config(),
+		None,
+		team,
+	);
+	Ok(pdf_response(
+		analysis::report::team_report(&database, &info, robot_card::robot_photo(&gallery)),
+		&format!("team-{team}.pdf"),
+	))
+}
+
+/// A printable brief for a match, e.g. `/report/match/qualification/12/1.pdf`
+#[handler]
+async fn get_match_report(
+	Path((match_type, num, file)): Path<(String, u32, String)>,
+	tba: Data<&Arc<Tba>>,
+	statbotics: Data<&StatboticsCache>,
+	config: Data<&ConfigManager>,
+	database: Data<&Arc<Database>>,
+) -> poem::Result<Response> {
+	let set = pdf_file_stem(&file)?
+		.parse::<u32>()
+		.map_err(|e| poem::Error::new(e, StatusCode::BAD_REQUEST))?;
+	let match_id = api::parse_match_id(&match_type, num, set)?;
+	let info = analysis::get_match_analysis(
+		&tba,
+		&statbotics,
+		&database,
+		config.get_server_config(),
+		config.get_current_game_config(),
+		match_id,
+	)
+	.await;
+	let title = match match_id {
+		MatchId::Qualification(_) => format!("Qualification {num}"),
+		_ => {
+			let mut match_type = match_type.clone();
+			match_type[..1].make_ascii_uppercase();
+			format!("{match_type} {set}-{num}")
+		}
+	};
+	Ok(pdf_response(
+		analysis::report::match_report(&info, &title),
+		&format!("{match_type}-{num}-{set}.pdf"),
+	))
+}
+
+/// The name of a file requested as `<name>.pdf`, without the extension
+fn pdf_file_stem(file: &str) -> poem::Result<&str> {
+	file.strip_suffix(".pdf").ok_or_else(|| {
+		poem::Error::from_string(
+			"Reports can only be downloaded as PDFs",
+			StatusCode::NOT_FOUND,
+		)
+	})
+}
+
+fn pdf_response(pdf: Vec<u8>, file_name: &str) -> Response {
+	Response::builder()
+		.content_type("application/pdf")
+		.header(
+			header::CONTENT_DISPOSITION,
+			format!("inline; filename=\"{file_name}\""),
+		)
+		.body(pdf)
+}
+
+/// Receive messages from the TBA webhook, checking they're signed with the webhook secret
+#[handler]
+async fn receive_tba_webhook(
+	req: &Request,
+	body: Vec<u8>,
+	tba: Data<&Arc<Tba>>,
+	config: Data<&ConfigManager>,
+) -> Response {
+	let secret = config.get_tba_webhook_secret();
+	if secret.is_empty() {
+		return poem::Response::from(StatusCode::NOT_FOUND);
+	}
+	let signature = req
+		.headers()
+		.get(tba_webhook::SIGNATURE_HEADER)
+		.and_then(|signature| signature.to_str().ok())
+		.unwrap_or_default();
+	if !tba_webhook::verify_signature(secret, &body, signature) {
+		warn!("TBA webhook: ignoring a message with the wrong signature");
+		return poem::Response::from(StatusCode::UNAUTHORIZED);
+	}
+	match serde_json::from_slice::<WebhookMessage>(&body) {
+		Ok(message) => {
+			tba.apply_webhook(message).await;
+			poem::Response::from(StatusCode::OK)
+		}
+		Err(err) => {
+			warn!("TBA webhook: couldn't read a message: {err}");
+			poem::Response::from(StatusCode::BAD_REQUEST)
+		}
+	}
+}
+
+/// Send errors from the API as JSON, so the client can show what went wrong
+async fn api_error(err: poem::Error) -> Response {
+	ApiError {
+		status: err.status().as_u16(),
+		message: err.to_string(),
+	}
+	.into_response()
+}
+
 impl ScoutingServer {
 	pub fn new(config: ConfigManager, database: Database) -> Result<Self> {
 		let tba_auth_key = config.get_tba_auth_key().to_string();
@@ -63,24 +275,33 @@ impl ScoutingServer {
 			.get_game_config(config.get_server_config().current_year)
 			.unwrap()
 			.clone();
-		let tba = Arc::new(Tba::new(game_config, tba_auth_key)?);
 		let database = Arc::new(database);
+		let frc_events = FrcEvents::new(&config.get_server_config().frc_events)?;
+		let tba = Arc::new(Tba::new(
+			game_config,
+			tba_auth_key,
+			frc_events,
+			database.clone(),
+		)?);
+		let statbotics = StatboticsCache::new(
+			&config.get_server_config().current_event,
+			config.get_server_config().current_year,
+			config
+				.get_current_game_config()
+				.game_config
+				.statbotics
+				.clone(),
+			database.clone(),
+		);
 		Ok(Self {
 			api: Api::new(
 				tba.clone(),
-				StatboticsCache::new(
-					&config.get_server_config().current_event,
-					config.get_server_config().current_year,
-					config
-						.get_current_game_config()
-						.game_config
-						.statbotics
-						.clone(),
-				),
+				statbotics.clone(),
 				config.clone(),
 				database.clone(),
 			),
 			tba,
+			statbotics,
 			config,
 			database,
 		})
@@ -92,7 +313,16 @@ impl ScoutingServer {
 			.url_prefix("/api")
 			.server("http://localhost:4421/");
 		let swagger_ui = api_service.swagger_ui();
-		let app = Route::new()
+		let api_service = api_service
+			.with(CatchPanic::new().with_handler(|_| {
+				ApiError {
+					status: StatusCode::INTERNAL_SERVER_ERROR.as_u16(),
+					message: "The server crashed while handling this request".to_string(),
+				}
+				.into_response()
+			}))
+			.catch_all_error(api_error);
+		let compressed = Route::new()
 			// TODO: Use EmbeddedFilesEndpoint to embed these into the release binary.
 			.nest(
 				"/",
@@ -104,10 +334,20 @@ impl ScoutingServer {
 			.nest("/api", api_service)
 			.nest("/api/docs", swagger_ui)
 			.at("/avatar/:team", get(get_avatar))
+			.at("/report/team/:file", get(get_team_report))
+			.at(
+				"/report/match/:match_type/:num/:file",
+				get(get_match_report),
+			)
+			.at("/webhooks/tba", post(receive_tba_webhook))
+			.with(Compression::new());
+		// Images are already compressed, and compressing would break range requests for videos
+		let app = Route::new()
+			.nest("/", compressed)
 			.at("/image/full/:image_id", get(get_image_full))
 			.at("/image/small/:image_id", get(get_image_small))
-			.with(Compression::new())
 			.with(AddData::new(self.tba.clone()))
+			.with(AddData::new(self.statbotics.clone()))
 			.with(AddData::new(self.config.clone()))
 			.with(AddData::new(self.database.clone()));
 		Server::new(TcpListener::bind(addr)).run(app).await?;
@@ -0,0 +1,99 @@
+use log::info;
+use poem_openapi::Object;
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::database::{CachedData, Database, DbError};
+use crate::statbotics::SavedStatboticsTeam;
+use crate::tba::{EventInfo, TeamAvatar};
+
+/// Everything saved from TBA, the FRC Events API, and Statbotics, to copy to a server that will be used without internet
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct DataSnapshot {
+	pub tba_events: Vec<CachedData<EventInfo>>,
+	#[serde(default)]
+	pub frc_events: Vec<CachedData<EventInfo>>,
+	pub tba_avatars: Vec<CachedData<TeamAvatar>>,
+	pub statbotics_teams: Vec<CachedData<SavedStatboticsTeam>>,
+}
+
+/// What was imported from a snapshot
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, Object, TS)]
+#[ts(export, export_to = "../../client/src/generated/")]
+pub struct SnapshotImport {
+	pub tba_events: usize,
+	pub frc_events: usize,
+	pub tba_avatars: usize,
+	pub statbotics_teams: usize,
+	/// The number of items not imported because the server already had newer data for them
+	pub skipped: usize,
+}
+
+pub fn export_snapshot(db: &Database) -> Result<DataSnapshot, DbError> {
+	Ok(DataSnapshot {
+		tba_events: db.get_all_tba_events()?,
+		frc_events: db.get_all_frc_events()?,
+		tba_avatars: db.get_all_tba_avatars()?,
+		statbotics_teams: db.get_all_statbotics_teams()?,
+	})
+}
+
+/// Save the data in a snapshot, except where the server has loaded something newer itself
+pub fn import_snapshot(db: &Database, snapshot: DataSnapshot) -> Result<SnapshotImport, DbError> {
+	let mut import = SnapshotImport {
+		tba_events: 0,
+		frc_events: 0,
+		tba_avatars: 0,
+		statbotics_teams: 0,
+		skipped: 0,
+	};
+	for event in snapshot.tba_events {
+		let saved = db.get_tba_event(event.data.year, &event.data.event)?;
+		if is_newer(&event, saved.as_ref()) {
+			db.set_tba_event(&event)?;
+			import.tba_events += 1;
+		} else {
+			import.skipped += 1;
+		}
+	}
+	for event in snapshot.frc_events {
+		let saved = db.get_frc_event(event.data.year, &event.data.event)?;
+		if is_newer(&event, saved.as_ref()) {
+			db.set_frc_event(&event)?;
+			import.frc_events += 1;
+		} else {
+			import.skipped += 1;
+		}
+	}
+	for avatar in snapshot.tba_avatars {
+		let saved = db.get_tba_avatar(avatar.data.year, avatar.data.team)?;
+		if is_newer(&avatar, saved.as_ref()) {
+			db.set_tba_avatar(&avatar)?;
+			import.tba_avatars += 1;
+		} else {
+			import.skipped += 1;
+		}
+	}
+	for team in snapshot.statbotics_teams {
+		let saved = db.get_statbotics_team(team.data.year, &team.data.event, team.data.team)?;
+		if is_newer(&team, saved.as_ref()) {
+			db.set_statbotics_team(&team)?;
+			import.statbotics_teams += 1;
+		} else {
+			import.skipped += 1;
+		}
+	}
+	info!(
+		"Imported a snapshot with {} TBA events, {} FRC Events API events, {} avatars, and {} Statbotics teams ({} skipped)",
+		import.tba_events,
+		import.frc_events,
+		import.tba_avatars,
+		import.statbotics_teams,
+		import.skipped
+	);
+	Ok(import)
+}
+
+fn is_newer<T>(data: &CachedData<T>, saved: Option<&CachedData<T>>) -> bool {
+	saved.is_none_or(|saved| data.fetched_ms > saved.fetched_ms)
+}
@@ -4,24 +4,39 @@ use std::time::Instant;
 use std::{collections::HashMap, time::Duration};
 
 use color_eyre::Result;
-use log::{error, info};
+use log::{error, info, warn};
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use tokio::spawn;
 use tokio::sync::{Mutex, RwLock};
 
 use crate::config::{MatchStatisticsPropType, StatboticsConfig};
+use crate::database::{self, CachedData, Database};
+use crate::upstream::{self, LoadRetry, UpstreamHealth, UpstreamStatus};
 
-#[derive(Debug, Clone, PartialEq)]
+/// How long to wait for Statbotics, so saved data is used quickly when the internet is unreliable
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(15);
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct StatboticsTeam {
 	pub epa: StatboticsEpa,
 	pub record: StatboticsRecordQuals,
 	pub other_details: HashMap<String, f32>,
+	#[serde(skip, default = "Instant::now")]
 	pub last_update: Instant,
 	pub query_type: StatboticsQueryType,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+/// A team's data from Statbotics for an event, saved so it can be used without internet
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct SavedStatboticsTeam {
+	pub team: u32,
+	pub year: u32,
+	pub event: String,
+	pub stats: StatboticsTeam,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub enum StatboticsQueryType {
 	EventSpecific,
 	YearData,
@@ -88,53 +103,111 @@ pub struct StatboticsRecordSimple {
 	pub count: u32,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct StatboticsCache {
 	client: Client,
 	teams: Arc<RwLock<HashMap<u32, Arc<StatboticsTeam>>>>,
 	teams_loading: Arc<Mutex<HashSet<u32>>>,
+	/// Teams that couldn't be loaded with no saved data to use instead, so requests don't each
+	/// wait for Statbotics to fail again
+	load_retries: Arc<Mutex<HashMap<String, LoadRetry>>>,
 	event: String,
 	year: u32,
 	config: StatboticsConfig,
+	database: Arc<Database>,
+	status: Arc<UpstreamStatus>,
 }
 
 impl StatboticsCache {
-	pub fn new(event: &str, year: u32, config: StatboticsConfig) -> Self {
+	pub fn new(event: &str, year: u32, config: StatboticsConfig, database: Arc<Database>) -> Self {
 		Self {
 			teams: Arc::new(RwLock::new(HashMap::new())),
 			teams_loading: Arc::new(Mutex::new(HashSet::new())),
+			load_retries: Arc::new(Mutex::new(HashMap::new())),
 			client: Client::builder()
 				.user_agent(env!("CARGO_PKG_NAME"))
+				.timeout(REQUEST_TIMEOUT)
 				.build()
 				.unwrap(),
 			event: event.to_string(),
 			year,
 			config,
+			database,
+			status: Arc::new(UpstreamStatus::default()),
 		}
 	}
 	pub async fn get(&self, team: u32) -> Option<Arc<StatboticsTeam>> {
 		let team_stats = self.teams.read().await.get(&team).cloned();
 
 		match team_stats {
-			None => match Self::load_team(&self.client, team, &self.event, self.year, &self.config)
+			None => {
+				// It failed recently with nothing saved, so only try again in the background
+				let retry_at = self
+					.load_retries
+					.lock()
+					.await
+					.get(&team.to_string())
+					.map(|retry| retry.at);
+				if let Some(retry_at) = retry_at {
+					if retry_at <= Instant::now() {
+						self.trigger_load_team(team).await;
+					}
+					return None;
+				}
+				let team_stats = match Self::load_team(
+					&self.client,
+					&self.database,
+					team,
+					&self.event,
+					self.year,
+					&self.config,
+				)
 				.await
-			{
-				Ok(team_stats) => {
-					info!(
-						"Statbotics ({team}): load complete using {}",
-						match team_stats.query_type {
-							StatboticsQueryType::EventSpecific => "event",
-							StatboticsQueryType::YearData => "YTD",
+				{
+					Ok(team_stats) => {
+						info!(
+							"Statbotics ({team}): load complete using {}",
+							match team_stats.query_type {
+								StatboticsQueryType::EventSpecific => "event",
+								StatboticsQueryType::YearData => "YTD",
+							}
+						);
+						self.status.record_success(&team.to_string());
+						team_stats
+					}
+					Err(err) => {
+						error!("Statbotics ({team}): load error: {err}");
+						// Teams without data fail too, which doesn't mean Statbotics is down
+						if upstream::is_unreachable(&err) {
+							self.status.record_failure(&err);
 						}
-					);
-					self.teams.write().await.insert(team, team_stats.clone());
-					Some(team_stats)
-				}
-				Err(err) => {
-					error!("Statbotics ({team}): load error: {err}");
-					None
-				}
-			},
+						// Use the last data that was loaded until Statbotics can be reached again
+						let saved = self
+							.database
+							.get_statbotics_team(self.year, &self.event, team)
+							.unwrap_or_else(|err| {
+								error!("Statbotics ({team}): failed to read saved data: {err}");
+								None
+							});
+						let Some(saved) = saved else {
+							LoadRetry::schedule(
+								&mut *self.load_retries.lock().await,
+								&team.to_string(),
+							);
+							return None;
+						};
+						warn!(
+							"Statbotics ({team}): using saved data from {} minutes ago",
+							database::now_ms().saturating_sub(saved.fetched_ms) / 60_000
+						);
+						self.status
+							.record_saved_data(&team.to_string(), saved.fetched_ms);
+						Arc::new(saved.data.stats)
+					}
+				};
+				self.teams.write().await.insert(team, team_stats.clone());
+				Some(team_stats)
+			}
 			Some(team_stats) => {
 				if team_stats.last_update.elapsed() > Duration::from_secs(5 * 60) {
 					self.trigger_load_team(team).await;
@@ -144,6 +217,15 @@ impl StatboticsCache {
 			}
 		}
 	}
+	/// Forget everything loaded so far, so the next requests use the saved data or load it again
+	pub async fn clear_cache(&self) {
+		self.teams.write().await.clear();
+		self.load_retries.lock().await.clear();
+		self.status.clear_saved_data();
+	}
+	pub fn health(&self) -> UpstreamHealth {
+		self.status.health("Statbotics")
+	}
 	async fn trigger_load_team(&self, team: u32) {
 		let mut lock = self.teams_loading.lock().await;
 
@@ -152,19 +234,33 @@ impl StatboticsCache {
 			drop(lock);
 
 			let client_clone = self.client.clone();
+			let database_clone = self.database.clone();
+			let status_clone = self.status.clone();
 			let teams_clone = self.teams.clone();
 			let teams_loading_clone = self.teams_loading.clone();
+			let load_retries_clone = self.load_retries.clone();
 			let event = self.event.clone();
 			let year = self.year;
 			let config = self.config.clone();
 			spawn(async move {
-				match Self::load_team(&client_clone, team, &event, year, &config).await {
+				match Self::load_team(&client_clone, &database_clone, team, &event, year, &config)
+					.await
+				{
 					Ok(data) => {
 						info!("Statbotics ({team}): background load complete");
+						status_clone.record_success(&team.to_string());
 						teams_clone.write().await.insert(team, data);
+						load_retries_clone.lock().await.remove(&team.to_string());
 					}
 					Err(err) => {
 						error!("Statbotics ({team}): background load error: {err}");
+						if upstream::is_unreachable(&err) {
+							status_clone.record_failure(&err);
+						}
+						LoadRetry::schedule(
+							&mut *load_retries_clone.lock().await,
+							&team.to_string(),
+						);
 					}
 				}
 
@@ -174,6 +270,29 @@ impl StatboticsCache {
 	}
 
 	async fn load_team(
+		client: &Client,
+		database: &Database,
+		team: u32,
+		event: &str,
+		year: u32,
+		config: &StatboticsConfig,
+	) -> Result<Arc<StatboticsTeam>> {
+		let team_stats = Self::fetch_team(client, team, event, year, config).await?;
+		if let Err(err) = database.set_statbotics_team(&CachedData {
+			fetched_ms: database::now_ms(),
+			data: SavedStatboticsTeam {
+				team,
+				year,
+				event: event.to_string(),
+				stats: team_stats.as_ref().clone(),
+			},
+		}) {
+			error!("Statbotics ({team}): failed to save data: {err}");
+		}
+		Ok(team_stats)
+	}
+
+	async fn fetch_team(
 		client: &Client,
 		team: u32,
 		event: &str,
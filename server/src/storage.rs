@@ -0,0 +1,143 @@
+use std::collections::{BTreeMap, HashSet};
+
+use log::info;
+use poem_openapi::Object;
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::database::{self, Database, DbError};
+
+/// How long an image or video is kept without being attached to an entry. Media is uploaded
+/// separately from the entries that use it, which can be saved much later if a scout is offline.
+const UNUSED_MEDIA_GRACE_MS: u64 = 2 * 24 * 60 * 60 * 1000;
+
+/// How much space images and videos are using
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, Object, TS)]
+#[ts(export, export_to = "../../client/src/generated/")]
+pub struct StorageReport {
+	/// The media attached to entries for each event
+	pub events: Vec<EventStorage>,
+	/// The number of images and videos that aren't attached to any entries
+	pub unused_count: usize,
+	#[ts(type = "number")]
+	pub unused_bytes: u64,
+	/// The space used by all images and videos, media used at several events is only counted once
+	#[ts(type = "number")]
+	pub total_bytes: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, Object, TS)]
+#[ts(export, export_to = "../../client/src/generated/")]
+pub struct EventStorage {
+	pub year: u32,
+	pub event: String,
+	/// The number of images and videos attached to entries for this event
+	pub media_count: usize,
+	#[ts(type = "number")]
+	pub bytes: u64,
+}
+
+/// The result of removing images and videos that aren't used
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, Object, TS)]
+#[ts(export, export_to = "../../client/src/generated/")]
+pub struct StorageCleanup {
+	/// The number of images and videos removed
+	pub removed: usize,
+	#[ts(type = "number")]
+	pub freed_bytes: u64,
+	/// The number of unused images and videos kept because they were uploaded recently, the
+	/// entries using them may not have been saved yet
+	pub kept_recent: usize,
+	/// The number of chunked uploads removed because they weren't finished
+	pub abandoned_uploads: usize,
+}
+
+pub fn get_storage_report(db: &Database) -> Result<StorageReport, DbError> {
+	let mut events = BTreeMap::<(u32, String), HashSet<String>>::new();
+	let mut used = HashSet::new();
+	for reference in db.get_all_media_references() {
+		used.insert(reference.media_id.clone());
+		events
+			.entry((reference.year, reference.event))
+			.or_default()
+			.insert(reference.media_id);
+	}
+
+	let mut report = StorageReport {
+		events: Vec::new(),
+		unused_count: 0,
+		unused_bytes: 0,
+		total_bytes: 0,
+	};
+	let stored = db.get_all_media_ids()?;
+	for id in &stored {
+		let size = db.get_media_size(id)?;
+		report.total_bytes += size;
+		if !used.contains(id) {
+			report.unused_count += 1;
+			report.unused_bytes += size;
+		}
+	}
+	for ((year, event), media_ids) in events {
+		let mut bytes = 0;
+		// Entries can refer to media that was never uploaded
+		let media_ids = media_ids
+			.into_iter()
+			.filter(|id| stored.contains(id))
+			.collect::<Vec<_>>();
+		for id in &media_ids {
+			bytes += db.get_media_size(id)?;
+		}
+		report.events.push(EventStorage {
+			year,
+			event,
+			media_count: media_ids.len(),
+			bytes,
+		});
+	}
+	Ok(report)
+}
+
+/// Remove images and videos that aren't attached to any entries, for any event, e.g. ones from
+/// entries that were replaced. Also removes chunked uploads that were given up on.
+pub fn remove_unused_media(db: &Database) -> Result<StorageCleanup, DbError> {
+	let now_ms = database::now_ms();
+	let used = db
+		.get_all_media_references()
+		.into_iter()
+		.map(|reference| reference.media_id)
+		.collect::<HashSet<_>>();
+
+	let mut cleanup = StorageCleanup {
+		removed: 0,
+		freed_bytes: 0,
+		kept_recent: 0,
+		abandoned_uploads: 0,
+	};
+	for id in db.get_all_media_ids()? {
+		if used.contains(&id) {
+			continue;
+		}
+		// Media from before upload times were recorded is old enough
+		let written_ms = db.get_media_written_ms(&id)?.unwrap_or_default();
+		if now_ms.saturating_sub(written_ms) < UNUSED_MEDIA_GRACE_MS {
+			cleanup.kept_recent += 1;
+			continue;
+		}
+		cleanup.freed_bytes += db.get_media_size(&id)?;
+		db.remove_media(&id)?;
+		cleanup.removed += 1;
+	}
+	for (id, upload) in db.get_all_uploads()? {
+		if now_ms.saturating_sub(upload.updated_ms) >= UNUSED_MEDIA_GRACE_MS {
+			cleanup.freed_bytes += upload.received;
+			db.remove_upload(&id)?;
+			cleanup.abandoned_uploads += 1;
+		}
+	}
+	info!(
+		"Removed {} unused images and videos and {} abandoned uploads ({} bytes), kept {} recent ones",
+		cleanup.removed, cleanup.abandoned_uploads, cleanup.freed_bytes, cleanup.kept_recent
+	);
+	Ok(cleanup)
+}
@@ -10,7 +10,7 @@ use base64::Engine;
 use color_eyre::{eyre::bail, Result};
 use futures_util::future;
 use itertools::Itertools;
-use log::{error, info};
+use log::{error, info, warn};
 use poem::http::{HeaderMap, HeaderValue};
 use poem_openapi::{Enum, Object, Union};
 use reqwest::Client;
@@ -21,9 +21,16 @@ use ts_rs::TS;
 
 use crate::analysis::{TeamNameEntry, TBA_PREFIX};
 use crate::api::data::{CounterEntry, MatchBoolEntry, MatchEntryValue, MatchEnumEntry};
-use crate::config::{GameConfig, GameConfigs};
+use crate::config::{FrcEventsRole, GameConfig, GameConfigs};
+use crate::database::{self, CachedData, Database, DbError};
+use crate::frc_events::{self, FrcEvents};
+use crate::manual_event::{ManualEvent, ManualEventMode};
+use crate::tba_webhook::{self, WebhookMessage};
+use crate::upstream::{self, LoadRetry, UpstreamHealth, UpstreamStatus};
 use crate::DefaultInstant;
 
+/// How long to wait for TBA, so saved data is used quickly when the internet is unreliable
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(15);
 type AvatarCache = RwLock<HashMap<(u32, u32), Option<Vec<u8>>>>;
 
 /// A struct to manage and cache information from the blue alliance
@@ -31,9 +38,24 @@ type AvatarCache = RwLock<HashMap<(u32, u32), Option<Vec<u8>>>>;
 pub struct Tba {
 	event_cache: Arc<RwLock<BTreeMap<String, EventInfo>>>,
 	events_loading: Arc<Mutex<HashSet<String>>>,
+	/// Events that couldn't be loaded with no saved data to use instead, so requests don't each
+	/// wait for TBA to fail again
+	load_retries: Arc<Mutex<HashMap<String, LoadRetry>>>,
 	avatar_cache: Arc<AvatarCache>,
 	client: Client,
 	game_configs: Arc<GameConfigs>,
+	database: Arc<Database>,
+	status: Arc<UpstreamStatus>,
+	frc_events: Option<Arc<FrcEvents>>,
+}
+
+/// A team's avatar for a year, saved so it can be shown without internet
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct TeamAvatar {
+	pub team: u32,
+	pub year: u32,
+	/// The PNG image, if the team has an avatar
+	pub image: Option<Vec<u8>>,
 }
 
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize, Object, TS)]
@@ -57,11 +79,25 @@ impl EventInfo {
 		event: &str,
 		game_config: &GameConfig,
 	) -> EventInfo {
-		let mut match_infos: Vec<_> = match_infos
-			.into_iter()
-			.filter_map(|m| m.into_match(game_config).ok())
-			.sorted_by_key(|mi| mi.id)
-			.collect();
+		EventInfo::from_matches(
+			match_infos
+				.into_iter()
+				.filter_map(|m| m.into_match(game_config).ok())
+				.collect(),
+			team_infos,
+			year,
+			event,
+		)
+	}
+
+	/// An event with each team's record counted from its matches
+	pub fn from_matches(
+		match_infos: Vec<MatchInfo>,
+		team_infos: Vec<RawTbaTeamInfo>,
+		year: u32,
+		event: &str,
+	) -> EventInfo {
+		let mut match_infos: Vec<_> = match_infos.into_iter().sorted_by_key(|mi| mi.id).collect();
 		match_infos.sort_by_key(|m| m.start_time);
 		EventInfo {
 			team_infos: team_infos
@@ -111,6 +147,10 @@ impl EventInfo {
 									}
 								})
 								.sum::<u16>() as u32,
+							rank: None,
+							rp_average: None,
+							sort_orders: Vec::new(),
+							dqs: 0,
 						},
 					)
 				})
@@ -122,6 +162,68 @@ impl EventInfo {
 		}
 	}
 
+	/// An event without a schedule or results, for when TBA doesn't have it or can't be reached
+	pub fn without_tba(year: u32, event: &str, teams: impl IntoIterator<Item = u32>) -> EventInfo {
+		EventInfo {
+			match_infos: Vec::new(),
+			team_infos: teams
+				.into_iter()
+				.map(|num| (num, TeamInfo::new(num, String::new())))
+				.collect(),
+			event: event.to_string(),
+			year,
+			last_update: DefaultInstant(Instant::now()),
+		}
+	}
+
+	/// Use TBA's official rankings for each team's record, since they include tiebreakers and
+	/// don't rely on score breakdowns
+	fn apply_rankings(&mut self, rankings: RawTbaRankings) {
+		let total_rps = rankings
+			.extra_stats_info
+			.iter()
+			.position(|info| info.name == "Total Ranking Points");
+		for ranking in rankings.rankings {
+			let Some(team_info) = ranking
+				.team_key
+				.trim_start_matches("frc")
+				.parse()
+				.ok()
+				.and_then(|num| self.team_infos.get_mut(&num))
+			else {
+				continue;
+			};
+			team_info.rank = Some(ranking.rank);
+			// The first sort order is the ranking score
+			team_info.rp_average = ranking.sort_orders.first().copied().flatten();
+			team_info.sort_orders = rankings
+				.sort_order_info
+				.iter()
+				.zip(&ranking.sort_orders)
+				.filter_map(|(info, value)| {
+					Some(SortOrder {
+						name: info.name.clone(),
+						value: (*value)?,
+					})
+				})
+				.collect();
+			team_info.dqs = ranking.dq;
+			if let Some(record) = ranking.record {
+				team_info.wins = record.wins;
+				team_info.losses = record.losses;
+				team_info.ties = record.ties;
+			}
+			if let Some(rps) =
+				total_rps.and_then(|index| ranking.extra_stats.get(index).copied().flatten())
+			{
+				team_info.ranking_points = rps as u32;
+			} else if let Some(rp_average) = team_info.rp_average {
+				team_info.ranking_points =
+					(rp_average * ranking.matches_played as f32).round() as u32;
+			}
+		}
+	}
+
 	pub fn get_team_info(&self, team_id: u32) -> TeamNameEntry {
 		if let Some(team_info) = self.team_infos.get(&team_id) {
 			TeamNameEntry {
@@ -140,51 +242,161 @@ impl EventInfo {
 }
 
 impl Tba {
-	pub fn new(game_configs: Arc<GameConfigs>, key: String) -> Result<Tba> {
+	pub fn new(
+		game_configs: Arc<GameConfigs>,
+		key: String,
+		frc_events: Option<FrcEvents>,
+		database: Arc<Database>,
+	) -> Result<Tba> {
 		let mut headers = HeaderMap::new();
 		headers.insert("X-TBA-Auth-Key", HeaderValue::from_str(&key)?);
 		Ok(Tba {
 			event_cache: Arc::new(RwLock::new(BTreeMap::new())),
 			events_loading: Arc::new(Mutex::new(HashSet::new())),
+			load_retries: Arc::new(Mutex::new(HashMap::new())),
 			avatar_cache: Arc::new(RwLock::new(HashMap::new())),
 			client: Client::builder()
 				.user_agent(env!("CARGO_PKG_NAME"))
 				.default_headers(headers)
+				.timeout(REQUEST_TIMEOUT)
 				.build()?,
 			game_configs,
+			database,
+			status: Arc::new(UpstreamStatus::default()),
+			frc_events: frc_events.map(Arc::new),
 		})
 	}
 
 	pub async fn get_avatar(&self, team: u32, year: u32) -> Option<Vec<u8>> {
-		Self::get_avatar_impl(&self.avatar_cache, &self.client, team, year).await
+		Self::get_avatar_impl(
+			&self.avatar_cache,
+			&self.client,
+			&self.database,
+			&self.status,
+			team,
+			year,
+		)
+		.await
+	}
+
+	/// Forget everything loaded so far, so the next requests use the saved data or load it again
+	pub async fn clear_cache(&self) {
+		self.event_cache.write().await.clear();
+		self.load_retries.lock().await.clear();
+		self.avatar_cache.write().await.clear();
+		self.status.clear_saved_data();
+	}
+
+	pub fn health(&self) -> UpstreamHealth {
+		self.status.health("TBA")
+	}
+
+	pub fn frc_events_health(&self) -> Option<UpstreamHealth> {
+		self.frc_events
+			.as_ref()
+			.map(|frc_events| frc_events.health())
 	}
 
+	/// Get an event from TBA, combined with any teams and matches entered by hand
 	pub async fn get_event(&self, year: u32, event: &str) -> Option<EventInfo> {
+		match self.get_manual_event(year, event) {
+			Some(manual_event) if manual_event.mode == ManualEventMode::Replace => {
+				Some(manual_event.apply(None, year, event))
+			}
+			Some(manual_event) => {
+				let tba_event = self.get_tba_event(year, event).await;
+				Some(manual_event.apply(tba_event, year, event))
+			}
+			None => self.get_tba_event(year, event).await,
+		}
+	}
+
+	/// Get an event like [`Tba::get_event`], but only if it has already been loaded, so this never
+	/// waits for TBA
+	pub async fn get_loaded_event(&self, year: u32, event: &str) -> Option<EventInfo> {
+		let tba_event = self.event_cache.read().await.get(event).cloned();
+		match self.get_manual_event(year, event) {
+			Some(manual_event) if manual_event.mode == ManualEventMode::Replace => {
+				Some(manual_event.apply(None, year, event))
+			}
+			Some(manual_event) => Some(manual_event.apply(tba_event, year, event)),
+			None => tba_event,
+		}
+	}
+
+	fn get_manual_event(&self, year: u32, event: &str) -> Option<ManualEvent> {
+		self.database
+			.get_manual_event(year, event)
+			.unwrap_or_else(|err| {
+				error!("Failed to read the manual data for {event}: {err}");
+				None
+			})
+	}
+
+	async fn get_tba_event(&self, year: u32, event: &str) -> Option<EventInfo> {
 		let event_info = self.event_cache.read().await.get(event).cloned();
 
 		match event_info {
-			None => match Self::load_event(
-				&self.client,
-				&self.avatar_cache,
-				&self.game_configs.game_config,
-				year,
-				event,
-			)
-			.await
-			{
-				Ok(event_info) => {
-					info!("TBA ({event}): load complete");
-					self.event_cache
-						.write()
-						.await
-						.insert(event.to_string(), event_info.clone());
-					Some(event_info)
-				}
-				Err(err) => {
-					error!("TBA ({event}): load error: {err}");
-					None
+			None => {
+				// It failed recently with nothing saved, so only try again in the background
+				let retry_at = self
+					.load_retries
+					.lock()
+					.await
+					.get(event)
+					.map(|retry| retry.at);
+				if let Some(retry_at) = retry_at {
+					if retry_at <= Instant::now() {
+						self.trigger_load(year, event).await;
+					}
+					return None;
 				}
-			},
+				let event_info = match Self::load_event(
+					&self.client,
+					&self.avatar_cache,
+					&self.database,
+					&self.status,
+					self.frc_events.as_deref(),
+					&self.game_configs.game_config,
+					year,
+					event,
+				)
+				.await
+				{
+					Ok(event_info) => {
+						info!("TBA ({event}): load complete");
+						event_info
+					}
+					Err(err) => {
+						error!("TBA ({event}): load error: {err}");
+						if upstream::is_unreachable(&err) {
+							self.status.record_failure(&err);
+						}
+						// Use the last data that was loaded until TBA can be reached again
+						let saved = Self::saved_event(
+							&self.database,
+							self.frc_events.as_deref(),
+							year,
+							event,
+						);
+						let Some(saved) = saved else {
+							LoadRetry::schedule(&mut *self.load_retries.lock().await, event);
+							return None;
+						};
+						warn!(
+							"TBA ({event}): using saved data from {} minutes ago",
+							database::now_ms().saturating_sub(saved.fetched_ms) / 60_000
+						);
+						self.status.record_saved_data(event, saved.fetched_ms);
+						saved.data
+					}
+				};
+				self.event_cache
+					.write()
+					.await
+					.insert(event.to_string(), event_info.clone());
+				Some(event_info)
+			}
 			Some(event_info) => {
 				if event_info.last_update.0.elapsed() > Duration::from_secs(5 * 60) {
 					self.trigger_load(year, event).await;
@@ -195,6 +407,97 @@ impl Tba {
 		}
 	}
 
+	/// Apply a message from the TBA webhook to the event if it's loaded, so results show up
+	/// without waiting for the next reload
+	pub async fn apply_webhook(&self, message: WebhookMessage) {
+		match message {
+			WebhookMessage::Verification { verification_key } => {
+				warn!("TBA webhook: enter the verification key {verification_key} on TBA to start getting messages");
+			}
+			WebhookMessage::MatchScore {
+				event_key,
+				tba_match,
+			} => {
+				let match_info = match tba_match.into_match(&self.game_configs.game_config) {
+					Ok(match_info) => match_info,
+					Err(err) => {
+						error!("TBA webhook ({event_key}): bad match score: {err}");
+						return;
+					}
+				};
+				let mut event_cache = self.event_cache.write().await;
+				let Some(event_info) = event_cache.get_mut(&event_key) else {
+					return;
+				};
+				info!("TBA webhook ({event_key}): score for {:?}", match_info.id);
+				match event_info
+					.match_infos
+					.iter_mut()
+					.find(|existing| existing.id == match_info.id)
+				{
+					// Keep anything only the FRC Events API has for the match
+					Some(existing) => {
+						*existing = frc_events::merge_match(match_info, existing.clone());
+					}
+					None => event_info.match_infos.push(match_info),
+				}
+				event_info.match_infos.sort_by_key(|mi| mi.id);
+				event_info.match_infos.sort_by_key(|mi| mi.start_time);
+				let year = event_info.year;
+				drop(event_cache);
+				// The records and rankings change too
+				self.trigger_load(year, &event_key).await;
+			}
+			WebhookMessage::UpcomingMatch {
+				event_key,
+				match_key,
+				scheduled_time,
+				predicted_time,
+			} => {
+				let Some(id) = tba_webhook::parse_match_key(&match_key) else {
+					warn!("TBA webhook ({event_key}): unknown match key {match_key}");
+					return;
+				};
+				let mut event_cache = self.event_cache.write().await;
+				let Some(event_info) = event_cache.get_mut(&event_key) else {
+					return;
+				};
+				match event_info
+					.match_infos
+					.iter_mut()
+					.find(|match_info| match_info.id == id)
+				{
+					Some(match_info) => {
+						if let Some(start_time) = predicted_time.or(scheduled_time) {
+							match_info.start_time = start_time;
+							event_info.match_infos.sort_by_key(|mi| mi.id);
+							event_info.match_infos.sort_by_key(|mi| mi.start_time);
+						}
+					}
+					None => {
+						// The schedule must have changed
+						let year = event_info.year;
+						drop(event_cache);
+						self.trigger_load(year, &event_key).await;
+					}
+				}
+			}
+			WebhookMessage::ScheduleUpdated { event_key } => {
+				let year = self
+					.event_cache
+					.read()
+					.await
+					.get(&event_key)
+					.map(|event_info| event_info.year);
+				if let Some(year) = year {
+					info!("TBA webhook ({event_key}): schedule updated");
+					self.trigger_load(year, &event_key).await;
+				}
+			}
+			WebhookMessage::Other(_) => {}
+		}
+	}
+
 	async fn trigger_load(&self, year: u32, event: &str) {
 		let mut lock = self.events_loading.lock().await;
 
@@ -205,13 +508,20 @@ impl Tba {
 			let event = event.to_string();
 			let client_clone = self.client.clone();
 			let avatar_cache_clone = self.avatar_cache.clone();
+			let database_clone = self.database.clone();
+			let status_clone = self.status.clone();
+			let frc_events_clone = self.frc_events.clone();
 			let event_cache_clone = self.event_cache.clone();
 			let events_loading_clone = self.events_loading.clone();
+			let load_retries_clone = self.load_retries.clone();
 			let game_configs = self.game_configs.clone();
 			spawn(async move {
 				match Self::load_event(
 					&client_clone,
 					&avatar_cache_clone,
+					&database_clone,
+					&status_clone,
+					frc_events_clone.as_deref(),
 					&game_configs.game_config,
 					year,
 					&event,
@@ -221,9 +531,14 @@ impl Tba {
 					Ok(data) => {
 						info!("TBA ({event}): background load complete");
 						event_cache_clone.write().await.insert(event.clone(), data);
+						load_retries_clone.lock().await.remove(&event);
 					}
 					Err(err) => {
 						error!("TBA ({event}): background load error: {err}");
+						if upstream::is_unreachable(&err) {
+							status_clone.record_failure(&err);
+						}
+						LoadRetry::schedule(&mut *load_retries_clone.lock().await, &event);
 					}
 				}
 
@@ -235,6 +550,8 @@ impl Tba {
 	async fn get_avatar_impl(
 		avatar_cache: &AvatarCache,
 		client: &Client,
+		database: &Database,
+		status: &UpstreamStatus,
 		team: u32,
 		year: u32,
 	) -> Option<Vec<u8>> {
@@ -242,35 +559,171 @@ impl Tba {
 			return data.clone();
 		}
 
-		let image = client
-			.get(format!(
-				"https://www.thebluealliance.com/api/v3/team/frc{team}/media/{year}",
-			))
-			.send()
-			.await
-			.unwrap()
-			.json::<Vec<RawTbaImage>>()
-			.await
-			.ok()
-			.and_then(|media| {
-				media
-					.into_iter()
-					.filter(|i| i.image_type == "avatar")
-					.find_map(|i| i.details.and_then(|d| d.base64_image))
-			})
-			.and_then(|image_base64| STANDARD.decode(image_base64).ok());
+		let image = match Self::load_avatar(client, team, year).await {
+			Ok(image) => image,
+			Err(err) => {
+				// Not cached in memory, so it's tried again once TBA can be reached
+				error!("TBA ({team}): avatar load error: {err}");
+				if upstream::is_unreachable(&err) {
+					status.record_failure(&err);
+				}
+				return database
+					.get_tba_avatar(year, team)
+					.ok()
+					.flatten()
+					.and_then(|saved| saved.data.image);
+			}
+		};
 
 		avatar_cache
 			.write()
 			.await
 			.insert((team, year), image.clone());
+		if let Err(err) = database.set_tba_avatar(&CachedData {
+			fetched_ms: database::now_ms(),
+			data: TeamAvatar {
+				team,
+				year,
+				image: image.clone(),
+			},
+		}) {
+			error!("TBA ({team}): failed to save avatar: {err}");
+		}
 
 		image
 	}
 
+	/// Load a team's avatar, `None` if TBA doesn't have one for them
+	async fn load_avatar(client: &Client, team: u32, year: u32) -> Result<Option<Vec<u8>>> {
+		let media = client
+			.get(format!(
+				"https://www.thebluealliance.com/api/v3/team/frc{team}/media/{year}",
+			))
+			.send()
+			.await?
+			.error_for_status()?
+			.json::<Vec<RawTbaImage>>()
+			.await?;
+		Ok(media
+			.into_iter()
+			.filter(|i| i.image_type == "avatar")
+			.find_map(|i| i.details.and_then(|d| d.base64_image))
+			.and_then(|image_base64| STANDARD.decode(image_base64).ok()))
+	}
+
+	/// Load an event from TBA, and from the FRC Events API if it's set up
+	#[allow(clippy::too_many_arguments)]
 	async fn load_event(
 		client: &Client,
 		avatar_cache: &AvatarCache,
+		database: &Database,
+		status: &UpstreamStatus,
+		frc_events: Option<&FrcEvents>,
+		game_config: &GameConfig,
+		year: u32,
+		event: &str,
+	) -> Result<EventInfo> {
+		let tba_event = Self::load_tba_event(
+			client,
+			avatar_cache,
+			database,
+			status,
+			game_config,
+			year,
+			event,
+		)
+		.await;
+		if let Ok(tba_event) = &tba_event {
+			if let Err(err) = database.set_tba_event(&CachedData {
+				fetched_ms: database::now_ms(),
+				data: tba_event.clone(),
+			}) {
+				error!("TBA ({event}): failed to save data: {err}");
+			}
+		}
+		let frc_events = match frc_events {
+			Some(frc_events)
+				if frc_events.role() == FrcEventsRole::Primary || tba_event.is_err() =>
+			{
+				frc_events
+			}
+			_ => return tba_event,
+		};
+		let frc_event = frc_events.load_event(game_config, year, event).await;
+		if let Ok(frc_event) = &frc_event {
+			if let Err(err) = database.set_frc_event(&CachedData {
+				fetched_ms: database::now_ms(),
+				data: frc_event.clone(),
+			}) {
+				error!("FRC Events ({event}): failed to save data: {err}");
+			}
+		}
+		// Whichever source couldn't be loaded is filled in with what was last saved from it, so
+		// results that only it has posted aren't lost
+		let role = frc_events.role();
+		match (tba_event, frc_event) {
+			(Ok(tba_event), Ok(frc_event)) => {
+				Ok(frc_events::merge_events(tba_event, frc_event, role))
+			}
+			(Ok(tba_event), Err(_)) => Ok(
+				match Self::read_saved(database.get_frc_event(year, event), event) {
+					Some(saved) => frc_events::merge_events(tba_event, saved.data, role),
+					None => tba_event,
+				},
+			),
+			(Err(err), Ok(frc_event)) => {
+				error!("TBA ({event}): load error, using the FRC Events API: {err}");
+				if upstream::is_unreachable(&err) {
+					status.record_failure(&err);
+				}
+				Ok(
+					match Self::read_saved(database.get_tba_event(year, event), event) {
+						Some(saved) => frc_events::merge_events(saved.data, frc_event, role),
+						None => frc_event,
+					},
+				)
+			}
+			(Err(err), Err(_)) => Err(err),
+		}
+	}
+
+	/// The event as it was last saved from TBA, combined with what was last saved from the FRC
+	/// Events API if it's used
+	fn saved_event(
+		database: &Database,
+		frc_events: Option<&FrcEvents>,
+		year: u32,
+		event: &str,
+	) -> Option<CachedData<EventInfo>> {
+		let tba_event = Self::read_saved(database.get_tba_event(year, event), event);
+		let Some(frc_events) = frc_events else {
+			return tba_event;
+		};
+		let frc_event = Self::read_saved(database.get_frc_event(year, event), event);
+		match (tba_event, frc_event) {
+			(Some(tba_event), Some(frc_event)) => Some(CachedData {
+				fetched_ms: tba_event.fetched_ms.max(frc_event.fetched_ms),
+				data: frc_events::merge_events(tba_event.data, frc_event.data, frc_events.role()),
+			}),
+			(tba_event, frc_event) => tba_event.or(frc_event),
+		}
+	}
+
+	fn read_saved(
+		saved: Result<Option<CachedData<EventInfo>>, DbError>,
+		event: &str,
+	) -> Option<CachedData<EventInfo>> {
+		saved.unwrap_or_else(|err| {
+			error!("TBA ({event}): failed to read saved data: {err}");
+			None
+		})
+	}
+
+	async fn load_tba_event(
+		client: &Client,
+		avatar_cache: &AvatarCache,
+		database: &Database,
+		status: &UpstreamStatus,
 		game_config: &GameConfig,
 		year: u32,
 		event: &str,
@@ -293,15 +746,22 @@ impl Tba {
 					.nickname
 					.or(raw_team.name)
 					.unwrap_or_else(|| "unknown".to_string()),
-				has_avatar: Self::get_avatar_impl(avatar_cache, client, raw_team.team_number, year)
-					.await
-					.is_some(),
+				has_avatar: Self::get_avatar_impl(
+					avatar_cache,
+					client,
+					database,
+					status,
+					raw_team.team_number,
+					year,
+				)
+				.await
+				.is_some(),
 			}
 		}))
 		.await
 		.into_iter()
 		.collect();
-		Ok(EventInfo::new(
+		let mut event_info = EventInfo::new(
 			client
 				.get(format!(
 					"https://www.thebluealliance.com/api/v3/event/{event}/matches"
@@ -315,12 +775,33 @@ impl Tba {
 			event,
 			game_config,
 		)
-		.await)
+		.await;
+		// Rankings are null until the first qualification match is played
+		let rankings = async {
+			client
+				.get(format!(
+					"https://www.thebluealliance.com/api/v3/event/{event}/rankings"
+				))
+				.send()
+				.await?
+				.error_for_status()?
+				.json::<Option<RawTbaRankings>>()
+				.await
+		};
+		match rankings.await {
+			Ok(Some(rankings)) => event_info.apply_rankings(rankings),
+			Ok(None) => {}
+			Err(err) => {
+				error!("TBA ({event}): failed to load rankings, continuing without them: {err}")
+			}
+		}
+		status.record_success(event);
+		Ok(event_info)
 	}
 }
 
 #[derive(Debug, Clone, PartialEq, Deserialize)]
-struct RawTbaMatch {
+pub struct RawTbaMatch {
 	alliances: RawTbaAlliances,
 	actual_time: Option<u64>,
 	predicted_time: Option<u64>,
@@ -383,14 +864,14 @@ impl RawTbaMatch {
 				.blue
 				.team_keys
 				.into_iter()
-				.map(|t| t.trim_start_matches("frc").parse().unwrap())
+				.filter_map(|t| t.trim_start_matches("frc").parse().ok())
 				.collect(),
 			teams_red: self
 				.alliances
 				.red
 				.team_keys
 				.into_iter()
-				.map(|t| t.trim_start_matches("frc").parse().unwrap())
+				.filter_map(|t| t.trim_start_matches("frc").parse().ok())
 				.collect(),
 			score_blue,
 			score_red,
@@ -435,104 +916,96 @@ impl RawTbaMatch {
 	}
 }
 
-fn custom_entries_for(
+/// The values of the TBA props in a score breakdown, for each robot on an alliance. Values that
+/// don't match what the game config expects are left out, in case TBA changes the breakdown.
+pub fn custom_entries_for(
 	game_config: &GameConfig,
 	values: Option<HashMap<String, RawTbaScoreBreakdownValue>>,
 ) -> [HashMap<String, MatchEntryValue>; 3] {
 	[1, 2, 3].map(|n| {
+		let Some(values) = &values else {
+			return HashMap::new();
+		};
 		game_config
 			.tba
 			.props
 			.iter()
 			.filter_map(|(prop_name, prop)| {
 				let name = prop.property.replace("{N}", &n.to_string());
-				if let Some(values) = &values {
-					values.get(&name).map(|data| {
-						(
-							format!("{TBA_PREFIX}{prop_name}"),
-							match prop.ty {
-								crate::config::MatchStatisticsPropType::Bool => {
-									if let RawTbaScoreBreakdownValue::Boolean(value) = *data {
-										MatchEntryValue::Bool(MatchBoolEntry {
-											value,
-											scout: "TBA".to_string(),
-											timestamp_ms: 0,
-										})
-									} else if let RawTbaScoreBreakdownValue::String(value) = data {
-										MatchEntryValue::Bool(MatchBoolEntry {
-											value: if let Some(true_value) = &prop.true_value {
-                                                value == true_value
-                                            } else {
-                                                value.starts_with('Y') || value.starts_with('y')
-                                            },
-											scout: "TBA".to_string(),
-											timestamp_ms: 0,
-										})
-									} else {
-										panic!("Expected TBA data of type bool for prop {name}, but found {data:?}");
-									}
-								}
-								crate::config::MatchStatisticsPropType::Sum => {
-									// This is hacky but like I'm really lazy rn
-									MatchEntryValue::Counter(CounterEntry {
-										count: prop
-											.options
-											.iter()
-											.flatten()
-											.filter(|v| match values.get(&v.id) {
-												Some(RawTbaScoreBreakdownValue::Boolean(true)) => {
-													true
-												}
-												Some(RawTbaScoreBreakdownValue::String(s))
-													if s.starts_with('Y') || s.starts_with('y') =>
-												{
-													true
-												}
-												_ => false,
-											})
-											.count() as i32,
-										scout: "TBA".to_string(),
-										timestamp_ms: 0,
-									})
-								}
-								crate::config::MatchStatisticsPropType::Enum => {
-									if let RawTbaScoreBreakdownValue::String(string) = data {
-										if let Some(entry) =
-											prop.options.iter().flatten().find(|v| &v.id == string)
-										{
-											MatchEntryValue::Enum(MatchEnumEntry {
-												value: entry
-													.name
-													.as_ref()
-													.unwrap_or(&entry.id)
-													.clone(),
-												scout: "TBA".to_string(),
-												timestamp_ms: 0,
-											})
-										} else {
-											panic!("Invalid enum string {string:?} for TBA prop {name}, expected one of: {:?}", prop.options);
-										}
-									} else {
-										panic!("Expected TBA data of type string (enum) for prop {name}, but found {data:?}");
-									}
-								}
-								crate::config::MatchStatisticsPropType::Number => {
-									if let RawTbaScoreBreakdownValue::Number(count) = *data {
-										MatchEntryValue::Counter(CounterEntry {
-											count,
-											scout: "TBA".to_string(),
-											timestamp_ms: 0,
-										})
-									} else {
-										panic!("Expected TBA data of type number for prop {name}, but found {data:?}");
+				let data = values.get(&name)?;
+				let value = match prop.ty {
+					crate::config::MatchStatisticsPropType::Bool => match data {
+						RawTbaScoreBreakdownValue::Boolean(value) => Some(*value),
+						RawTbaScoreBreakdownValue::String(value) => {
+							Some(if let Some(true_value) = &prop.true_value {
+								value == true_value
+							} else {
+								value.starts_with('Y') || value.starts_with('y')
+							})
+						}
+						_ => None,
+					}
+					.map(|value| {
+						MatchEntryValue::Bool(MatchBoolEntry {
+							value,
+							scout: "TBA".to_string(),
+							timestamp_ms: 0,
+						})
+					}),
+					crate::config::MatchStatisticsPropType::Sum => {
+						// This is hacky but like I'm really lazy rn
+						Some(MatchEntryValue::Counter(CounterEntry {
+							count: prop
+								.options
+								.iter()
+								.flatten()
+								.filter(|v| match values.get(&v.id) {
+									Some(RawTbaScoreBreakdownValue::Boolean(true)) => true,
+									Some(RawTbaScoreBreakdownValue::String(s))
+										if s.starts_with('Y') || s.starts_with('y') =>
+									{
+										true
 									}
-								}
-							},
-						)
-					})
-				} else {
-					None
+									_ => false,
+								})
+								.count() as i32,
+							scout: "TBA".to_string(),
+							timestamp_ms: 0,
+						}))
+					}
+					crate::config::MatchStatisticsPropType::Enum => match data {
+						RawTbaScoreBreakdownValue::String(string) => prop
+							.options
+							.iter()
+							.flatten()
+							.find(|v| &v.id == string)
+							.map(|entry| {
+								MatchEntryValue::Enum(MatchEnumEntry {
+									value: entry.name.as_ref().unwrap_or(&entry.id).clone(),
+									scout: "TBA".to_string(),
+									timestamp_ms: 0,
+								})
+							}),
+						_ => None,
+					},
+					crate::config::MatchStatisticsPropType::Number => match data {
+						RawTbaScoreBreakdownValue::Number(count) => {
+							Some(MatchEntryValue::Counter(CounterEntry {
+								count: *count,
+								scout: "TBA".to_string(),
+								timestamp_ms: 0,
+							}))
+						}
+						_ => None,
+					},
+				};
+				if value.is_none() {
+					warn!(
+						"Unexpected TBA data {data:?} for {:?} prop {name}, expected one of: {:?}",
+						prop.ty, prop.options
+					);
 				}
+				Some((format!("{TBA_PREFIX}{prop_name}"), value?))
 			})
 			.collect()
 	})
@@ -552,7 +1025,7 @@ struct RawTbaAlliance {
 
 #[derive(Debug, Clone, PartialEq, Deserialize)]
 #[serde(untagged)]
-enum RawTbaScoreBreakdownValue {
+pub enum RawTbaScoreBreakdownValue {
 	Number(i32),
 	String(String),
 	Boolean(bool),
@@ -572,6 +1045,42 @@ struct RawTbaTeam {
 	nickname: Option<String>,
 }
 
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+struct RawTbaRankings {
+	rankings: Vec<RawTbaRanking>,
+	#[serde(default)]
+	sort_order_info: Vec<RawTbaStatInfo>,
+	#[serde(default)]
+	extra_stats_info: Vec<RawTbaStatInfo>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+struct RawTbaRanking {
+	rank: u32,
+	team_key: String,
+	#[serde(default)]
+	matches_played: u32,
+	#[serde(default)]
+	dq: u32,
+	record: Option<RawTbaRecord>,
+	#[serde(default)]
+	sort_orders: Vec<Option<f32>>,
+	#[serde(default)]
+	extra_stats: Vec<Option<f32>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+struct RawTbaRecord {
+	wins: u32,
+	losses: u32,
+	ties: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+struct RawTbaStatInfo {
+	name: String,
+}
+
 #[derive(Debug, Clone, PartialEq, Deserialize)]
 struct RawTbaImage {
 	#[serde(rename = "type")]
@@ -602,9 +1111,46 @@ pub struct TeamInfo {
 	pub losses: u32,
 	pub ties: u32,
 	pub ranking_points: u32,
+	/// The official rank, once TBA has posted rankings
+	#[serde(default)]
+	pub rank: Option<u32>,
+	/// The official ranking score, which is the average RPs
+	#[serde(default)]
+	pub rp_average: Option<f32>,
+	/// The ranking score then the tiebreakers, in the order they're used
+	#[serde(default)]
+	pub sort_orders: Vec<SortOrder>,
+	/// The number of matches the team was disqualified from
+	#[serde(default)]
+	pub dqs: u32,
+}
+
+/// One of the values teams are ranked by
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, Object, TS)]
+#[ts(export, export_to = "../../client/src/generated/")]
+pub struct SortOrder {
+	pub name: String,
+	pub value: f32,
 }
 
 impl TeamInfo {
+	/// A team that hasn't played any matches yet
+	pub fn new(num: u32, name: String) -> TeamInfo {
+		TeamInfo {
+			num,
+			name,
+			has_avatar: false,
+			wins: 0,
+			losses: 0,
+			ties: 0,
+			ranking_points: 0,
+			rank: None,
+			rp_average: None,
+			sort_orders: Vec::new(),
+			dqs: 0,
+		}
+	}
+
 	pub fn get_icon_url(&self) -> Option<String> {
 		self.has_avatar.then(|| format!("/avatar/{}", self.num))
 	}
@@ -664,3 +1210,150 @@ pub struct MatchInfo {
 	pub rp_red: Option<u16>,
 	pub custom_entries: CustomEntries,
 }
+
+#[cfg(test)]
+mod tests {
+	use serde_json::json;
+
+	use super::*;
+
+	fn event_info() -> EventInfo {
+		let mut event_info = EventInfo::without_tba(2024, "2024casj", [254, 1678, 971]);
+		let team_info = event_info.team_infos.get_mut(&971).unwrap();
+		team_info.wins = 3;
+		team_info.losses = 2;
+		event_info
+	}
+
+	fn rankings(value: serde_json::Value) -> RawTbaRankings {
+		serde_json::from_value(value).unwrap()
+	}
+
+	/// Trimmed from a real `/event/{event_key}/rankings` response
+	fn casj_rankings() -> RawTbaRankings {
+		rankings(json!({
+			"extra_stats_info": [{ "name": "Total Ranking Points", "precision": 0 }],
+			"rankings": [
+				{
+					"dq": 0,
+					"extra_stats": [45],
+					"matches_played": 12,
+					"qual_average": null,
+					"rank": 1,
+					"record": { "losses": 1, "ties": 0, "wins": 11 },
+					"sort_orders": [3.75, 120.5, 55.0, 30.0, 12.0, 0.0],
+					"team_key": "frc254"
+				},
+				{
+					"dq": 1,
+					"extra_stats": [38],
+					"matches_played": 12,
+					"qual_average": null,
+					"rank": 2,
+					"record": { "losses": 3, "ties": 1, "wins": 8 },
+					"sort_orders": [3.17, null, 48.0, null, 10.0, 0.0],
+					"team_key": "frc1678"
+				},
+				{
+					"dq": 0,
+					"extra_stats": [null],
+					"matches_played": 5,
+					"qual_average": null,
+					"rank": 30,
+					"record": null,
+					"sort_orders": [1.4, 80.0, 20.0, 10.0, 4.0, 0.0],
+					"team_key": "frc971"
+				},
+				{
+					"dq": 0,
+					"extra_stats": [20],
+					"matches_played": 12,
+					"qual_average": null,
+					"rank": 31,
+					"record": { "losses": 6, "ties": 0, "wins": 6 },
+					"sort_orders": [1.67, 70.0, 20.0, 10.0, 4.0, 0.0],
+					"team_key": "frc9999"
+				}
+			],
+			"sort_order_info": [
+				{ "name": "Ranking Score", "precision": 2 },
+				{ "name": "Avg Match", "precision": 2 },
+				{ "name": "Avg Auto", "precision": 2 },
+				{ "name": "Avg Stage", "precision": 2 },
+				{ "name": "Avg Amp", "precision": 2 },
+				{ "name": "Random", "precision": 0 }
+			]
+		}))
+	}
+
+	#[test]
+	fn rankings_and_records() {
+		let mut event_info = event_info();
+		event_info.apply_rankings(casj_rankings());
+
+		let team = &event_info.team_infos[&254];
+		assert_eq!(team.rank, Some(1));
+		assert_eq!(team.rp_average, Some(3.75));
+		assert_eq!(team.ranking_points, 45);
+		assert_eq!((team.wins, team.losses, team.ties), (11, 1, 0));
+		assert_eq!(team.dqs, 0);
+
+		let team = &event_info.team_infos[&1678];
+		assert_eq!(team.ranking_points, 38);
+		assert_eq!((team.wins, team.losses, team.ties), (8, 3, 1));
+		assert_eq!(team.dqs, 1);
+
+		// No record or total, so the record is kept and the total comes from the average
+		let team = &event_info.team_infos[&971];
+		assert_eq!(team.rank, Some(30));
+		assert_eq!((team.wins, team.losses, team.ties), (3, 2, 0));
+		assert_eq!(team.ranking_points, 7);
+
+		assert!(!event_info.team_infos.contains_key(&9999));
+	}
+
+	#[test]
+	fn sort_orders_skip_missing_values() {
+		let mut event_info = event_info();
+		event_info.apply_rankings(casj_rankings());
+
+		let sort_orders = &event_info.team_infos[&1678].sort_orders;
+		let names = sort_orders
+			.iter()
+			.map(|sort_order| sort_order.name.as_str())
+			.collect::<Vec<_>>();
+		assert_eq!(names, ["Ranking Score", "Avg Auto", "Avg Amp", "Random"]);
+		assert_eq!(sort_orders[1].value, 48.0);
+		assert_eq!(event_info.team_infos[&254].sort_orders.len(), 6);
+	}
+
+	#[test]
+	fn total_ranking_points_from_average() {
+		let mut event_info = event_info();
+		// Older events don't have the total in their extra stats
+		event_info.apply_rankings(rankings(json!({
+			"extra_stats_info": [],
+			"rankings": [{
+				"dq": 0,
+				"extra_stats": [],
+				"matches_played": 10,
+				"rank": 4,
+				"record": { "losses": 4, "ties": 0, "wins": 6 },
+				"sort_orders": [2.35, 60.0],
+				"team_key": "frc254"
+			}],
+			"sort_order_info": [
+				{ "name": "Ranking Score", "precision": 2 },
+				{ "name": "Avg Match", "precision": 2 }
+			]
+		})));
+		let team = &event_info.team_infos[&254];
+		assert_eq!(team.ranking_points, 24);
+		assert_eq!(team.rp_average, Some(2.35));
+
+		// Nothing to go on, so what was there is kept
+		let team = &event_info.team_infos[&1678];
+		assert_eq!(team.rank, None);
+		assert_eq!(team.ranking_points, 0);
+	}
+}
@@ -0,0 +1,194 @@
+use hmac::{Hmac, Mac};
+use serde::Deserialize;
+use sha2::Sha256;
+
+use crate::tba::{MatchId, RawTbaMatch, SetMatch};
+
+/// The header TBA puts the signature of each message in
+pub const SIGNATURE_HEADER: &str = "X-TBA-HMAC";
+
+/// A message sent to the webhook by TBA, only the ones that change event data are used
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(
+	tag = "message_type",
+	content = "message_data",
+	rename_all = "snake_case"
+)]
+pub enum WebhookMessage {
+	/// Sent when the webhook is added on TBA, the key has to be entered there before any other
+	/// messages are sent
+	Verification {
+		verification_key: String,
+	},
+	MatchScore {
+		event_key: String,
+		#[serde(rename = "match")]
+		tba_match: RawTbaMatch,
+	},
+	UpcomingMatch {
+		event_key: String,
+		match_key: String,
+		scheduled_time: Option<u64>,
+		predicted_time: Option<u64>,
+	},
+	ScheduleUpdated {
+		event_key: String,
+	},
+	/// Pings and messages about things other than event data
+	#[serde(untagged)]
+	Other(serde_json::Value),
+}
+
+/// Whether the signature is the HMAC-SHA256 of the message with the webhook secret, as hex
+pub fn verify_signature(secret: &str, body: &[u8], signature: &str) -> bool {
+	let Some(signature) = decode_hex(signature) else {
+		return false;
+	};
+	// Compared in constant time, so the time taken doesn't give away how close a guess was
+	hmac_sha256(secret.as_bytes(), body)
+		.verify_slice(&signature)
+		.is_ok()
+}
+
+fn hmac_sha256(key: &[u8], message: &[u8]) -> Hmac<Sha256> {
+	// HMAC takes keys of any length
+	Hmac::<Sha256>::new_from_slice(key)
+		.unwrap()
+		.chain_update(message)
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+	if !hex.len().is_multiple_of(2) {
+		return None;
+	}
+	(0..hex.len())
+		.step_by(2)
+		.map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+		.collect()
+}
+
+/// Get the match id from a TBA match key like `2024casj_qm1` or `2024casj_sf2m1`
+pub fn parse_match_key(match_key: &str) -> Option<MatchId> {
+	let (_, key) = match_key.split_once('_')?;
+	let (level, numbers) = key.split_at(key.find(|c: char| c.is_ascii_digit())?);
+	let set_match = match numbers.split_once('m') {
+		Some((set, num)) => SetMatch {
+			set: set.parse().ok()?,
+			num: num.parse().ok()?,
+		},
+		// Qualification matches only have a number
+		None => SetMatch {
+			set: 1,
+			num: numbers.parse().ok()?,
+		},
+	};
+	match level {
+		"qm" => Some(MatchId::Qualification(set_match)),
+		"qf" => Some(MatchId::Quarterfinal(set_match)),
+		"sf" => Some(MatchId::Semifinal(set_match)),
+		"f" => Some(MatchId::Final(set_match)),
+		_ => None,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn hex(bytes: &[u8]) -> String {
+		bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+	}
+
+	#[test]
+	fn hmac_rfc_4231() {
+		// The test cases from RFC 4231, except the truncated one
+		let cases: [(Vec<u8>, Vec<u8>, &str); 6] = [
+			(
+				vec![0x0b; 20],
+				b"Hi There".to_vec(),
+				"b0344c61d8db38535ca8afceaf0bf12b881dc200c9833da726e9376c2e32cff7",
+			),
+			(
+				b"Jefe".to_vec(),
+				b"what do ya want for nothing?".to_vec(),
+				"5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843",
+			),
+			(
+				vec![0xaa; 20],
+				vec![0xdd; 50],
+				"773ea91e36800e46854db8ebd09181a72959098b3ef8c122d9635514ced565fe",
+			),
+			(
+				(1..=25).collect(),
+				vec![0xcd; 50],
+				"82558a389a443c0ea4cc819899f2083a85f0faa3e578f8077a2e3ff46729665b",
+			),
+			(
+				vec![0xaa; 131],
+				b"Test Using Larger Than Block-Size Key - Hash Key First".to_vec(),
+				"60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54",
+			),
+			(
+				vec![0xaa; 131],
+				b"This is a test using a larger than block-size key and a larger than block-size data. The key needs to be hashed before being used by the HMAC algorithm.".to_vec(),
+				"9b09ffa71b942fcb27635fbcd5b0e944bfdc63644f0713938a7f51535c3a35e2",
+			),
+		];
+		for (key, message, expected) in cases {
+			assert_eq!(
+				hex(&hmac_sha256(&key, &message).finalize().into_bytes()),
+				expected
+			);
+		}
+	}
+
+	#[test]
+	fn signatures() {
+		let signature = "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843";
+		let body = b"what do ya want for nothing?";
+		assert!(verify_signature("Jefe", body, signature));
+		assert!(verify_signature("Jefe", body, &signature.to_uppercase()));
+		assert!(!verify_signature("jefe", body, signature));
+		assert!(!verify_signature(
+			"Jefe",
+			b"what do ya want for something?",
+			signature
+		));
+		assert!(!verify_signature("Jefe", body, &signature[..62]));
+		assert!(!verify_signature("Jefe", body, &signature[..63]));
+		assert!(!verify_signature("Jefe", body, ""));
+		assert!(!verify_signature(
+			"Jefe",
+			body,
+			&signature.replace('5', "g")
+		));
+	}
+
+	#[test]
+	fn match_keys() {
+		assert_eq!(
+			parse_match_key("2024casj_qm1"),
+			Some(MatchId::Qualification(SetMatch { set: 1, num: 1 }))
+		);
+		assert_eq!(
+			parse_match_key("2024casj_qm42"),
+			Some(MatchId::Qualification(SetMatch { set: 1, num: 42 }))
+		);
+		assert_eq!(
+			parse_match_key("2024casj_qf3m2"),
+			Some(MatchId::Quarterfinal(SetMatch { set: 3, num: 2 }))
+		);
+		assert_eq!(
+			parse_match_key("2024casj_sf12m1"),
+			Some(MatchId::Semifinal(SetMatch { set: 12, num: 1 }))
+		);
+		assert_eq!(
+			parse_match_key("2024casj_f1m3"),
+			Some(MatchId::Final(SetMatch { set: 1, num: 3 }))
+		);
+		assert_eq!(parse_match_key("2024casj_ef1m1"), None);
+		assert_eq!(parse_match_key("2024casj_qm"), None);
+		assert_eq!(parse_match_key("2024casj_sf1m"), None);
+		assert_eq!(parse_match_key("qm1"), None);
+	}
+}
@@ -0,0 +1,162 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use poem_openapi::{Enum, Object};
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::database;
+
+/// How long to wait before loading something again after it failed with no saved data to use,
+/// doubling after each failure up to the most
+const LOAD_RETRY_MIN: Duration = Duration::from_secs(30);
+const LOAD_RETRY_MAX: Duration = Duration::from_secs(10 * 60);
+
+/// Whether TBA, Statbotics, and the current event's data can be relied on
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, Object, TS)]
+#[ts(export, export_to = "../../client/src/generated/")]
+pub struct HealthReport {
+	pub upstreams: Vec<UpstreamHealth>,
+	/// True if no schedule or team list has been loaded for the current event, so analysis only
+	/// uses scouting data
+	pub scouting_only: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, Object, TS)]
+#[ts(export, export_to = "../../client/src/generated/")]
+pub struct UpstreamHealth {
+	pub name: String,
+	pub state: UpstreamState,
+	#[ts(type = "number | null")]
+	pub last_success_ms: Option<u64>,
+	#[ts(type = "number | null")]
+	pub last_failure_ms: Option<u64>,
+	pub last_error: Option<String>,
+	/// The number of events or teams using saved data because loading them failed
+	pub stale_items: usize,
+	/// When the oldest saved data that's being used was loaded
+	#[ts(type = "number | null")]
+	pub oldest_data_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize, Enum, TS)]
+#[ts(export, export_to = "../../client/src/generated/")]
+#[serde(rename_all = "snake_case")]
+#[oai(rename_all = "snake_case")]
+pub enum UpstreamState {
+	/// Nothing has been loaded yet
+	Unknown,
+	/// The last request worked and nothing is using saved data
+	Ok,
+	/// Some data is out of date, because the last request failed or saved data is being used
+	Stale,
+	/// Nothing could be loaded and there's no saved data
+	Unavailable,
+}
+
+/// Keeps track of how requests to an upstream like TBA have been going
+#[derive(Debug, Default)]
+pub struct UpstreamStatus {
+	inner: Mutex<StatusInner>,
+}
+
+#[derive(Debug, Default)]
+struct StatusInner {
+	last_success_ms: Option<u64>,
+	last_failure_ms: Option<u64>,
+	last_error: Option<String>,
+	/// When the saved data used for each item was loaded
+	saved_items: HashMap<String, u64>,
+}
+
+impl UpstreamStatus {
+	pub fn record_success(&self, item: &str) {
+		let mut inner = self.inner.lock().unwrap();
+		inner.last_success_ms = Some(database::now_ms());
+		inner.saved_items.remove(item);
+	}
+
+	pub fn record_failure(&self, error: impl ToString) {
+		let mut inner = self.inner.lock().unwrap();
+		inner.last_failure_ms = Some(database::now_ms());
+		inner.last_error = Some(error.to_string());
+	}
+
+	/// Note that saved data from `fetched_ms` is being used for an item because it couldn't be loaded
+	pub fn record_saved_data(&self, item: &str, fetched_ms: u64) {
+		self.inner
+			.lock()
+			.unwrap()
+			.saved_items
+			.insert(item.to_string(), fetched_ms);
+	}
+
+	/// Forget the saved data being used, when it's been replaced
+	pub fn clear_saved_data(&self) {
+		self.inner.lock().unwrap().saved_items.clear();
+	}
+
+	pub fn health(&self, name: &str) -> UpstreamHealth {
+		let inner = self.inner.lock().unwrap();
+		let last_failed = match (inner.last_success_ms, inner.last_failure_ms) {
+			(_, None) => false,
+			(None, Some(_)) => true,
+			(Some(success_ms), Some(failure_ms)) => failure_ms >= success_ms,
+		};
+		let state = if inner.last_success_ms.is_none() && inner.last_failure_ms.is_none() {
+			UpstreamState::Unknown
+		} else if last_failed && inner.last_success_ms.is_none() && inner.saved_items.is_empty() {
+			UpstreamState::Unavailable
+		} else if last_failed || !inner.saved_items.is_empty() {
+			UpstreamState::Stale
+		} else {
+			UpstreamState::Ok
+		};
+		UpstreamHealth {
+			name: name.to_string(),
+			state,
+			last_success_ms: inner.last_success_ms,
+			last_failure_ms: inner.last_failure_ms,
+			last_error: inner.last_error.clone(),
+			stale_items: inner.saved_items.len(),
+			oldest_data_ms: inner.saved_items.values().min().copied(),
+		}
+	}
+}
+
+/// When an event or team that failed to load is tried again
+#[derive(Debug, Clone, Copy)]
+pub struct LoadRetry {
+	failures: u32,
+	pub at: Instant,
+}
+
+impl LoadRetry {
+	/// Note another failure, waiting longer before the next try
+	pub fn schedule(load_retries: &mut HashMap<String, LoadRetry>, item: &str) {
+		let failures = load_retries
+			.get(item)
+			.map(|retry| retry.failures + 1)
+			.unwrap_or(1);
+		let delay = LOAD_RETRY_MIN
+			.saturating_mul(2u32.saturating_pow(failures - 1))
+			.min(LOAD_RETRY_MAX);
+		load_retries.insert(
+			item.to_string(),
+			LoadRetry {
+				failures,
+				at: Instant::now() + delay,
+			},
+		);
+	}
+}
+
+/// Whether an error means the upstream couldn't be reached, rather than it not having the data
+pub fn is_unreachable(err: &color_eyre::Report) -> bool {
+	err.downcast_ref::<reqwest::Error>().is_some_and(|err| {
+		err.is_connect()
+			|| err.is_timeout()
+			|| err.status().is_some_and(|status| status.is_server_error())
+	})
+}